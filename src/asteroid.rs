@@ -0,0 +1,76 @@
+use nalgebra_glm::Vec3;
+use rand::prelude::*;
+use rand::rngs::StdRng;
+use std::f32::consts::PI;
+use crate::ephemeris::orbit_offset;
+
+// Velocidad orbital (radianes por unidad de tiempo de simulación) a la distancia
+// de referencia; más lejos se orbita más despacio, según la tercera ley de Kepler
+const REFERENCE_RADIUS: f32 = 55.0;
+const REFERENCE_SPEED: f32 = 0.00065;
+
+pub struct Asteroid {
+    pub position: Vec3,
+    pub scale: f32,
+    pub rotation: Vec3,
+    pub albedo: f32,
+    base_rotation: Vec3,
+    semi_major_axis: f32,
+    eccentricity: f32,
+    inclination: f32,
+    // Longitud del nodo ascendente: gira el plano orbital alrededor del eje Y para
+    // que no todas las órbitas se inclinen sobre el mismo eje
+    node: f32,
+    phase: f32,
+    orbital_speed: f32,
+    spin: Vec3,
+}
+
+// Cinturón de asteroides generado con una semilla, así que es el mismo en cada ejecución
+pub struct AsteroidBelt {
+    pub asteroids: Vec<Asteroid>,
+}
+
+impl AsteroidBelt {
+    pub fn new(count: usize, inner_radius: f32, outer_radius: f32, seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+
+        let asteroids = (0..count)
+            .map(|_| {
+                let semi_major_axis = rng.gen_range(inner_radius..outer_radius);
+                Asteroid {
+                    position: Vec3::new(0.0, 0.0, 0.0),
+                    scale: rng.gen_range(0.05..0.2),
+                    rotation: Vec3::new(0.0, 0.0, 0.0),
+                    base_rotation: Vec3::new(rng.gen::<f32>() * 2.0 * PI, rng.gen::<f32>() * 2.0 * PI, 0.0),
+                    albedo: rng.gen_range(0.5..0.9),
+                    semi_major_axis,
+                    eccentricity: rng.gen_range(0.0..0.1),
+                    inclination: rng.gen_range(-0.15..0.15),
+                    node: rng.gen::<f32>() * 2.0 * PI,
+                    phase: rng.gen::<f32>() * 2.0 * PI,
+                    orbital_speed: REFERENCE_SPEED * (REFERENCE_RADIUS / semi_major_axis).powf(1.5),
+                    spin: Vec3::new(rng.gen_range(-0.02..0.02), rng.gen_range(-0.02..0.02), rng.gen_range(-0.02..0.02)),
+                }
+            })
+            .collect();
+
+        Self { asteroids }
+    }
+
+    // Coloca cada asteroide en su órbita (y su giro) para el tiempo de simulación dado
+    pub fn update(&mut self, simulation_time: f32, sun_position: Vec3) {
+        for asteroid in &mut self.asteroids {
+            let mean_anomaly = asteroid.phase + simulation_time * asteroid.orbital_speed;
+            let offset = orbit_offset(asteroid.semi_major_axis, asteroid.eccentricity, asteroid.inclination, mean_anomaly);
+            let (sin_node, cos_node) = asteroid.node.sin_cos();
+
+            asteroid.position = sun_position + Vec3::new(
+                offset.x * cos_node + offset.z * sin_node,
+                offset.y,
+                -offset.x * sin_node + offset.z * cos_node,
+            );
+            asteroid.rotation = asteroid.base_rotation + asteroid.spin * simulation_time;
+        }
+    }
+}
@@ -1,12 +1,22 @@
-use nalgebra_glm::Vec3;
+use nalgebra_glm::{Vec3, Mat3, Quat, quat_angle_axis, quat_identity, quat_normalize, quat_rotate_vec3, quat_slerp, mat3_to_quat};
 use std::f32::consts::PI;
 
+pub const DEFAULT_SPEED: f32 = 0.2;
+const MIN_SPEED: f32 = 0.05;
+const MAX_SPEED: f32 = 5.0;
+
+// Inercia de la nave: aceleración con los motores al máximo, en múltiplos de la
+// velocidad máxima por segundo, y amortiguación (1/s) que la frena al soltarlos.
+// Con ambos iguales la velocidad terminal es justo la velocidad máxima
+pub const ACCELERATION: f32 = 4.0;
+pub const DAMPING: f32 = 4.0;
+
 #[derive(Clone)]
 pub struct WarpState {
     pub start_position: Vec3,
     pub end_position: Vec3,
-    pub start_direction: Vec3,
-    pub end_direction: Vec3,
+    pub start_orientation: Quat,
+    pub end_orientation: Quat,
     pub progress: f32,
     pub duration: f32,
     pub is_active: bool,
@@ -17,8 +27,8 @@ impl WarpState {
         WarpState {
             start_position: Vec3::new(0.0, 0.0, 0.0),
             end_position: Vec3::new(0.0, 0.0, 0.0),
-            start_direction: Vec3::new(0.0, 0.0, -1.0),
-            end_direction: Vec3::new(0.0, 0.0, -1.0),
+            start_orientation: quat_identity(),
+            end_orientation: quat_identity(),
             progress: 0.0,
             duration: 1.0,
             is_active: false,
@@ -26,37 +36,127 @@ impl WarpState {
     }
 }
 
+// Cámara en tercera persona: sigue a la nave (la pose de la cámara) desde
+// `CHASE_OFFSET`, en el espacio de la nave, con un resorte críticamente amortiguado
+pub struct ChaseCamera {
+  pub position: Vec3,
+  velocity: Vec3,
+}
+
+// Detrás y un poco por encima de la nave (la nave mira hacia -Z)
+const CHASE_OFFSET: Vec3 = Vec3::new(0.0, 0.25, 1.2);
+// Frecuencia del resorte (1/s): más alta sigue a la nave más de cerca
+const CHASE_STIFFNESS: f32 = 6.0;
+// Punto delante de la nave al que mira la cámara de persecución
+const CHASE_LOOK_AHEAD: f32 = 2.0;
+
+// Orientación de la cámara como un cuaternión unitario: en el espacio local la
+// cámara mira hacia -Z, con +Y arriba y +X a la derecha
 pub struct Camera {
   pub eye: Vec3,
+  // Distancia base que avanza la cámara por paso fijo, sin el boost
+  pub speed: f32,
+  // Velocidad actual en unidades por segundo
+  pub velocity: Vec3,
   pub center: Vec3,
   pub up: Vec3,
-  pub pitch: f32,
-  pub yaw: f32,
-  pub roll: f32,
+  pub orientation: Quat,
   pub warp_state: WarpState,
-  pub previous_state: Option<(Vec3, Vec3, f32, f32, f32)>,
+  pub previous_state: Option<(Vec3, Vec3, Quat)>,
   pub bird_eye_active: bool,
+  // Con Some, la vista sale de detrás de la nave en lugar de desde `eye`
+  pub chase: Option<ChaseCamera>,
 }
 
 impl Camera {
   pub fn new(eye: Vec3, center: Vec3, up: Vec3) -> Self {
     let forward = (center - eye).normalize();
-    let pitch = (forward.y).asin();
-    let yaw = forward.z.atan2(forward.x);
-    
-    Camera {
+    let orientation = look_rotation(&forward, &Vec3::new(1.0, 0.0, 0.0));
+
+    let mut camera = Camera {
       eye,
+      speed: DEFAULT_SPEED,
+      velocity: Vec3::zeros(),
       center,
-      up: Vec3::new(0.0, 1.0, 0.0),
-      pitch,
-      yaw,
-      roll: 0.0,
+      up,
+      orientation,
       warp_state: WarpState::new(),
       previous_state: None,
       bird_eye_active: false,
+      chase: None,
+    };
+    camera.update_center();
+    camera
+  }
+
+  // Multiplica la velocidad base, siempre dentro de [MIN_SPEED, MAX_SPEED]
+  pub fn scale_speed(&mut self, factor: f32) {
+    self.speed = (self.speed * factor).clamp(MIN_SPEED, MAX_SPEED);
+  }
+
+  // Alterna entre primera persona y la cámara de persecución, que empieza ya en su sitio
+  pub fn toggle_chase(&mut self) {
+    self.chase = match self.chase {
+      Some(_) => None,
+      None => Some(ChaseCamera {
+        position: self.chase_target(),
+        velocity: Vec3::zeros(),
+      }),
+    };
+  }
+
+  fn chase_target(&self) -> Vec3 {
+    self.eye + quat_rotate_vec3(&self.orientation, &CHASE_OFFSET)
+  }
+
+  // Acerca la cámara de persecución a su objetivo con la solución exacta del resorte
+  // críticamente amortiguado, x(t) = (x0 + (v0 + w x0) t) e^(-w t), estable con cualquier dt
+  pub fn update_chase(&mut self, dt: f32) {
+    let target = self.chase_target();
+    let Some(chase) = &mut self.chase else {
+      return;
+    };
+
+    let offset = chase.position - target;
+    let slope = chase.velocity + offset * CHASE_STIFFNESS;
+    let decay = (-CHASE_STIFFNESS * dt).exp();
+    chase.position = target + (offset + slope * dt) * decay;
+    chase.velocity = (chase.velocity - slope * CHASE_STIFFNESS * dt) * decay;
+  }
+
+  // Desde dónde y hacia dónde se mira. En la vista aérea no se usa la persecución
+  pub fn view_eye(&self) -> Vec3 {
+    match &self.chase {
+      Some(chase) if !self.bird_eye_active => chase.position,
+      _ => self.eye,
+    }
+  }
+
+  pub fn view_center(&self) -> Vec3 {
+    match &self.chase {
+      Some(_) if !self.bird_eye_active => self.eye + self.get_forward() * CHASE_LOOK_AHEAD,
+      _ => self.center,
     }
   }
 
+  // Empuja en `direction` (cuya longitud es la fracción del empuje) hacia una
+  // velocidad terminal de `max_speed` unidades por segundo
+  pub fn accelerate(&mut self, direction: Vec3, max_speed: f32, dt: f32) {
+    self.velocity += direction * max_speed * ACCELERATION * dt;
+  }
+
+  // Dónde quedará la cámara tras `integrate(dt)`, para comprobar colisiones antes
+  pub fn predicted_position(&self, dt: f32) -> Vec3 {
+    self.eye + self.velocity * (-DAMPING * dt).exp() * dt
+  }
+
+  // Aplica la amortiguación y avanza la posición según la velocidad
+  pub fn integrate(&mut self, dt: f32) {
+    self.velocity *= (-DAMPING * dt).exp();
+    self.eye += self.velocity * dt;
+    self.update_center();
+  }
+
   pub fn move_forward(&mut self, amount: f32) {
     let forward = self.get_forward();
     self.eye += forward * amount;
@@ -68,49 +168,111 @@ impl Camera {
     self.update_center();
   }
 
+  // Gira alrededor del eje Y del mundo para que el horizonte no se incline
   pub fn rotate_yaw(&mut self, angle: f32) {
-    self.yaw += angle;
+    let rotation = quat_angle_axis(-angle, &world_up());
+    self.orientation = quat_normalize(&(rotation * self.orientation));
     self.update_center();
   }
 
+  // Gira alrededor del eje derecho nivelado, sin pasar de la vertical
   pub fn rotate_pitch(&mut self, angle: f32) {
-    self.pitch = (self.pitch + angle).clamp(-PI/2.0 + 0.1, PI/2.0 - 0.1);
+    let pitch = self.pitch();
+    let target = (pitch + angle).clamp(-PI/2.0 + 0.1, PI/2.0 - 0.1);
+    let axis = self.level_right().unwrap_or_else(|| self.get_right());
+
+    let rotation = quat_angle_axis(target - pitch, &axis);
+    self.orientation = quat_normalize(&(rotation * self.orientation));
     self.update_center();
   }
 
+  // Ángulo de elevación de la dirección de vista sobre el plano horizontal
+  pub fn pitch(&self) -> f32 {
+    self.get_forward().y.clamp(-1.0, 1.0).asin()
+  }
+
+  // Inclinación alrededor de la dirección de vista respecto al horizonte; positiva
+  // hacia la izquierda. Es 0 cuando se mira en vertical, donde no está definida
+  pub fn roll(&self) -> f32 {
+    let Some(level_right) = self.level_right() else {
+      return 0.0;
+    };
+    let level_up = level_right.cross(&self.get_forward());
+    let up = self.get_up();
+    (-up.dot(&level_right)).atan2(up.dot(&level_up))
+  }
+
   pub fn set_roll(&mut self, angle: f32) {
-    self.roll = angle;
+    let delta = angle - self.roll();
+    let rotation = quat_angle_axis(delta, &-self.get_forward());
+    self.orientation = quat_normalize(&(rotation * self.orientation));
+    self.update_center();
+  }
+
+  // Nivela el roll hacia cero de forma suave: decae exponencialmente a `rate` por segundo
+  pub fn auto_level(&mut self, dt: f32, rate: f32) {
+    self.ease_roll(0.0, dt, rate);
+  }
+
+  // Lleva el roll hacia `target` de forma suave: la diferencia decae exponencialmente
+  // a `rate` por segundo (con target = 0 nivela el horizonte)
+  pub fn ease_roll(&mut self, target: f32, dt: f32, rate: f32) {
+    let roll = self.roll();
+    self.set_roll(target + (roll - target) * (-rate * dt).exp());
+  }
+
+  // Rumbo de la vista alrededor del eje vertical del mundo; crece al girar a la
+  // derecha con `rotate_yaw`
+  pub fn yaw(&self) -> f32 {
+    let forward = self.get_forward();
+    forward.x.atan2(-forward.z)
   }
 
   pub fn get_forward(&self) -> Vec3 {
-    Vec3::new(
-      self.yaw.cos() * self.pitch.cos(),
-      self.pitch.sin(),
-      self.yaw.sin() * self.pitch.cos()
-    ).normalize()
+    quat_rotate_vec3(&self.orientation, &Vec3::new(0.0, 0.0, -1.0))
   }
 
   pub fn get_right(&self) -> Vec3 {
-    self.get_forward().cross(&self.get_up()).normalize()
+    quat_rotate_vec3(&self.orientation, &Vec3::new(1.0, 0.0, 0.0))
   }
 
   pub fn get_up(&self) -> Vec3 {
-    Vec3::new(0.0, 1.0, 0.0)
+    quat_rotate_vec3(&self.orientation, &Vec3::new(0.0, 1.0, 0.0))
+  }
+
+  // Eje derecho sin roll; None si se mira justo hacia arriba o hacia abajo
+  fn level_right(&self) -> Option<Vec3> {
+    let right = self.get_forward().cross(&world_up());
+    (right.magnitude() > 1e-4).then(|| right.normalize())
   }
 
   fn update_center(&mut self) {
     let forward = self.get_forward();
     self.center = self.eye + forward;
+    self.up = self.get_up();
   }
 
-  pub fn start_warp(&mut self, target_pos: Vec3, target_direction: Vec3) {
+  // Warp de `duration` segundos hasta `target_pos`, mirando hacia `target_direction`
+  pub fn start_warp(&mut self, target_pos: Vec3, target_direction: Vec3, duration: f32) {
     self.warp_state.start_position = self.eye;
     self.warp_state.end_position = target_pos;
-    self.warp_state.start_direction = self.get_forward();
-    self.warp_state.end_direction = target_direction;
+    self.warp_state.start_orientation = self.orientation;
+    self.warp_state.end_orientation = look_rotation(&target_direction.normalize(), &self.get_right());
     self.warp_state.progress = 0.0;
-    self.warp_state.duration = 1.0; // 1 segundo de duración
+    self.warp_state.duration = duration.max(f32::EPSILON);
     self.warp_state.is_active = true;
+    // El warp lleva a la nave; al llegar parte desde el reposo
+    self.velocity = Vec3::zeros();
+  }
+
+  // Velocidad relativa del warp en [0, 1]: la derivada del smoothstep normalizada,
+  // 0 al salir y al llegar y 1 a mitad de camino
+  pub fn warp_speed(&self) -> f32 {
+    if !self.warp_state.is_active {
+        return 0.0;
+    }
+    let p = self.warp_state.progress.clamp(0.0, 1.0);
+    4.0 * p * (1.0 - p)
   }
 
   pub fn update_warp(&mut self, dt: f32) {
@@ -122,17 +284,16 @@ impl Camera {
 
     if self.warp_state.progress >= 1.0 {
         self.eye = self.warp_state.end_position;
-        let direction = self.warp_state.end_direction;
-        self.pitch = (direction.y).asin();
-        self.yaw = direction.z.atan2(direction.x);
-        self.roll = 0.0;
+        self.orientation = self.warp_state.end_orientation;
+        self.velocity = Vec3::zeros();
         self.warp_state.is_active = false;
         self.update_center();
         return;
     }
 
-    // Función de suavizado
-    let t = (self.warp_state.progress * std::f32::consts::PI).sin();
+    // Smoothstep: monótono, arranca y llega con velocidad cero
+    let p = self.warp_state.progress;
+    let t = p * p * (3.0 - 2.0 * p);
     
     // Interpolar posición
     self.eye = self.warp_state.start_position.lerp(
@@ -140,18 +301,82 @@ impl Camera {
         t
     );
 
-    // Interpolar dirección
-    let direction = self.warp_state.start_direction.lerp(
-        &self.warp_state.end_direction,
+    // Interpolar orientación
+    let orientation = quat_slerp(
+        &self.warp_state.start_orientation,
+        &self.warp_state.end_orientation,
         t
-    ).normalize();
+    );
 
-    self.pitch = (direction.y).asin();
-    self.yaw = direction.z.atan2(direction.x);
-    
-    // Efecto de roll durante el warp
-    self.roll = (t * std::f32::consts::PI * 2.0).sin() * 0.5;
+    // Efecto de roll durante el warp, alrededor de la dirección de vista
+    let roll = (t * std::f32::consts::PI * 2.0).sin() * 0.5;
+    let forward = quat_rotate_vec3(&orientation, &Vec3::new(0.0, 0.0, -1.0));
+    self.orientation = quat_normalize(&(quat_angle_axis(roll, &-forward) * orientation));
     
     self.update_center();
   }
 }
+
+fn world_up() -> Vec3 {
+  Vec3::new(0.0, 1.0, 0.0)
+}
+
+// Orientación sin roll que mira hacia `forward`. Si se mira en vertical el
+// horizonte no define la derecha y se usa `fallback_right`
+fn look_rotation(forward: &Vec3, fallback_right: &Vec3) -> Quat {
+  let level_right = forward.cross(&world_up());
+  let right = if level_right.magnitude() > 1e-4 {
+    level_right.normalize()
+  } else {
+    (fallback_right - forward * fallback_right.dot(forward)).normalize()
+  };
+  let up = right.cross(forward);
+
+  mat3_to_quat(&Mat3::from_columns(&[right, up, -forward]))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn roll_decays_towards_level() {
+    let mut camera = Camera::new(Vec3::new(0.0, 0.0, 10.0), Vec3::new(0.0, 0.0, 0.0), world_up());
+    camera.rotate_yaw(0.4);
+    camera.set_roll(0.5);
+    assert!((camera.roll() - 0.5).abs() < 1e-4);
+    let (yaw, pitch) = (camera.yaw(), camera.pitch());
+
+    // Cada frame el roll se acerca a 0 sin pasarse, y en dos segundos casi desaparece
+    let (dt, rate) = (1.0 / 60.0, 3.0);
+    let mut previous = camera.roll();
+    for _ in 0..120 {
+      camera.auto_level(dt, rate);
+      let roll = camera.roll();
+      assert!(roll > 0.0 && roll < previous);
+      previous = roll;
+    }
+    assert!((previous - 0.5 * (-rate * 2.0_f32).exp()).abs() < 1e-3);
+
+    // Nivelar no cambia hacia dónde se mira
+    assert!((camera.yaw() - yaw).abs() < 1e-4);
+    assert!((camera.pitch() - pitch).abs() < 1e-4);
+  }
+
+
+  #[test]
+  fn ease_roll_settles_on_the_target() {
+    let mut camera = Camera::new(Vec3::new(0.0, 0.0, 10.0), Vec3::new(0.0, 0.0, 0.0), world_up());
+    let (dt, rate, target) = (1.0 / 60.0, 3.0, -0.3);
+
+    // Desde el horizonte el roll va hacia el objetivo sin pasarse
+    let mut previous = camera.roll();
+    for _ in 0..180 {
+      camera.ease_roll(target, dt, rate);
+      let roll = camera.roll();
+      assert!(roll < previous && roll > target);
+      previous = roll;
+    }
+    assert!((previous - target).abs() < 0.01);
+  }
+}
@@ -0,0 +1,45 @@
+use crate::vertex::Vertex;
+
+// Signed distance to the near plane in clip space (z = -w with an OpenGL-style
+// projection); vertices with a negative distance are behind it
+fn near_distance(vertex: &Vertex) -> f32 {
+    vertex.clip_position.z + vertex.clip_position.w
+}
+
+// Sutherland-Hodgman against the near plane only. A triangle fully in front is
+// returned as-is, one fully behind is dropped, and a partially clipped one becomes
+// a polygon of 3 or 4 vertices that is fanned back into 1 or 2 triangles
+pub fn clip_triangle_near(triangle: [Vertex; 3]) -> Vec<[Vertex; 3]> {
+    let distances = [
+        near_distance(&triangle[0]),
+        near_distance(&triangle[1]),
+        near_distance(&triangle[2]),
+    ];
+
+    if distances.iter().all(|&d| d >= 0.0) {
+        return vec![triangle];
+    }
+    if distances.iter().all(|&d| d < 0.0) {
+        return Vec::new();
+    }
+
+    let mut polygon: Vec<Vertex> = Vec::with_capacity(4);
+    for i in 0..3 {
+        let j = (i + 1) % 3;
+        let (current, next) = (&triangle[i], &triangle[j]);
+        let (d_current, d_next) = (distances[i], distances[j]);
+
+        if d_current >= 0.0 {
+            polygon.push(current.clone());
+        }
+        // The edge crosses the plane: add the intersection point
+        if (d_current >= 0.0) != (d_next >= 0.0) {
+            let t = d_current / (d_current - d_next);
+            polygon.push(current.lerp(next, t));
+        }
+    }
+
+    (1..polygon.len() - 1)
+        .map(|i| [polygon[0].clone(), polygon[i].clone(), polygon[i + 1].clone()])
+        .collect()
+}
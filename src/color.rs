@@ -1,4 +1,16 @@
 use std::fmt;
+use std::sync::OnceLock;
+use nalgebra_glm::Vec3;
+
+// Colors are stored gamma-encoded; this approximates the sRGB curve as a pure
+// power law: linear = encoded^GAMMA
+pub const GAMMA: f32 = 2.2;
+
+// Linear value of each 8-bit channel level, computed once
+fn linear_table() -> &'static [f32; 256] {
+  static TABLE: OnceLock<[f32; 256]> = OnceLock::new();
+  TABLE.get_or_init(|| std::array::from_fn(|level| (level as f32 / 255.0).powf(GAMMA)))
+}
 
 #[derive(Debug, Clone, Copy)]
 pub struct Color {
@@ -9,7 +21,7 @@ pub struct Color {
 
 impl Color {
   // Constructor to initialize the color using r, g, b values as u8
-  pub fn new(r: u8, g: u8, b: u8) -> Self {
+  pub const fn new(r: u8, g: u8, b: u8) -> Self {
     Color { r, g, b }
   }
 
@@ -27,6 +39,11 @@ impl Color {
     }
   }
 
+  // Channels as f32 in [0.0, 1.0]
+  pub fn to_float(self) -> Vec3 {
+    Vec3::new(self.r as f32, self.g as f32, self.b as f32) / 255.0
+  }
+
   // Function to create a color from a hex value
   pub fn from_hex(hex: u32) -> Self {
     let r = ((hex >> 16) & 0xFF) as u8;
@@ -36,7 +53,7 @@ impl Color {
   }
 
   // Function to return the color as a hex value
-  pub fn to_hex(&self) -> u32 {
+  pub fn to_hex(self) -> u32 {
     ((self.r as u32) << 16) | ((self.g as u32) << 8) | (self.b as u32)
   }
 
@@ -50,45 +67,23 @@ impl Color {
     }
   }
 
-  pub fn is_black(&self) -> bool {
-    self.r == 0 && self.g == 0 && self.b == 0 
-  }
-
-  // New blend mode methods
-  pub fn blend_normal(&self, blend: &Color) -> Color {
-    if blend.is_black() { *self } else { *blend }
-  }
-
-  pub fn blend_multiply(&self, blend: &Color) -> Color {
-    Color::new(
-      ((self.r as f32 * blend.r as f32) / 255.0) as u8,
-      ((self.g as f32 * blend.g as f32) / 255.0) as u8,
-      ((self.b as f32 * blend.b as f32) / 255.0) as u8
-    )
-  }
-
-  pub fn blend_add(&self, blend: &Color) -> Color {
-    Color::new(
-      (self.r as u16 + blend.r as u16).min(255) as u8,
-      (self.g as u16 + blend.g as u16).min(255) as u8,
-      (self.b as u16 + blend.b as u16).min(255) as u8
-    )
+  // Channels in linear light, [0.0, 1.0]
+  pub fn to_linear(self) -> Vec3 {
+    let table = linear_table();
+    Vec3::new(table[self.r as usize], table[self.g as usize], table[self.b as usize])
   }
 
-  pub fn blend_subtract(&self, blend: &Color) -> Color {
-    let r = (self.r as i16 - blend.r as i16).max(0).min(255) as u8;
-    let g = (self.g as i16 - blend.g as i16).max(0).min(255) as u8;
-    let b = (self.b as i16 - blend.b as i16).max(0).min(255) as u8;
-
-    Color::new(r, g, b)
+  // Gamma-encodes linear channels back into a color (clamping to [0.0, 1.0])
+  pub fn from_linear(linear: Vec3) -> Self {
+    let encode = |channel: f32| (channel.clamp(0.0, 1.0).powf(1.0 / GAMMA) * 255.0).round() as u8;
+    Color::new(encode(linear.x), encode(linear.y), encode(linear.z))
   }
 
-  pub fn blend_screen(&self, blend: &Color) -> Color {
-    Color::new(
-      255 - ((255 - self.r as u16) * (255 - blend.r as u16) / 255) as u8,
-      255 - ((255 - self.g as u16) * (255 - blend.g as u16) / 255) as u8,
-      255 - ((255 - self.b as u16) * (255 - blend.b as u16) / 255) as u8
-    )
+  // Like `lerp`, but mixing in linear light, so halfway between two colors has the
+  // average brightness instead of the darker, muddier gamma-space midpoint
+  pub fn lerp_linear(&self, other: &Color, t: f32) -> Self {
+    let t = t.clamp(0.0, 1.0);
+    Color::from_linear(self.to_linear().lerp(&other.to_linear(), t))
   }
 
   // Agregar estos nuevos métodos getter
@@ -142,3 +137,31 @@ impl fmt::Display for Color {
     write!(f, "Color(r: {}, g: {}, b: {})", self.r, self.g, self.b)
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn half_gray_blend_is_brighter_in_linear_space() {
+    let (black, white) = (Color::black(), Color::new(255, 255, 255));
+
+    // The naive blend lands on the encoded midpoint, which is only ~22% of white's light
+    let naive = black.lerp(&white, 0.5);
+    assert_eq!((naive.r, naive.g, naive.b), (128, 128, 128));
+    assert!((naive.to_linear().x - 0.22).abs() < 0.01);
+
+    // Blending in linear light keeps half of the light: 0.5^(1/2.2) * 255 ≈ 186
+    let linear = black.lerp_linear(&white, 0.5);
+    assert_eq!((linear.r, linear.g, linear.b), (186, 186, 186));
+    assert!((linear.to_linear().x - 0.5).abs() < 0.01);
+  }
+
+  #[test]
+  fn linear_round_trip_keeps_every_level() {
+    for level in 0..=255u8 {
+      let color = Color::new(level, level, level);
+      assert_eq!(Color::from_linear(color.to_linear()).r, level);
+    }
+  }
+}
@@ -0,0 +1,172 @@
+use nalgebra_glm::Vec3;
+use rand::prelude::*;
+use rand::rngs::StdRng;
+use std::f32::consts::PI;
+use crate::TrailParticle;
+
+// Parámetro gravitacional del Sol en unidades de escena (u³/s²)
+const SUN_GM: f32 = 2000.0;
+
+// Las partículas de la cola se alejan del Sol a esta velocidad (u/s) cuando el
+// núcleo está a TAIL_REFERENCE_DISTANCE; más cerca del Sol salen más rápido y la
+// cola se alarga
+const TAIL_DRIFT_SPEED: f32 = 8.0;
+const TAIL_REFERENCE_DISTANCE: f32 = 40.0;
+const TAIL_MAX_ACTIVITY: f32 = 4.0;
+const TAIL_LIFETIME: f32 = 1.5;
+const TAIL_MAX_PARTICLES: usize = 120;
+const TAIL_COLOR: u32 = 0xFFCCE6FF; // Blanco azulado
+
+struct TailParticle {
+    particle: TrailParticle,
+    velocity: Vec3,
+}
+
+// Cola de un cometa: las partículas nacen en el núcleo con velocidad en la dirección
+// contraria al Sol y se desvanecen al final de su vida
+pub struct CometTail {
+    particles: Vec<TailParticle>,
+}
+
+impl CometTail {
+    pub fn new() -> Self {
+        Self {
+            particles: Vec::with_capacity(TAIL_MAX_PARTICLES),
+        }
+    }
+
+    pub fn update(&mut self, dt: f32, nucleus: Vec3, sun_position: Vec3) {
+        self.particles.retain_mut(|tail_particle| {
+            tail_particle.particle.position += tail_particle.velocity * dt;
+            tail_particle.particle.lifetime -= dt;
+            tail_particle.particle.lifetime > 0.0
+        });
+
+        // Con el tiempo detenido no se emiten partículas
+        if dt <= 0.0 {
+            return;
+        }
+
+        let from_sun = nucleus - sun_position;
+        let distance = from_sun.magnitude().max(1.0);
+        // La actividad (y con ella la longitud de la cola) crece al acercarse al Sol
+        let activity = (TAIL_REFERENCE_DISTANCE / distance).powi(2).min(TAIL_MAX_ACTIVITY);
+
+        if self.particles.len() >= TAIL_MAX_PARTICLES {
+            self.particles.remove(0);
+        }
+        self.particles.push(TailParticle {
+            particle: TrailParticle::new(nucleus, TAIL_COLOR, TAIL_LIFETIME, 0.1),
+            velocity: from_sun / distance * TAIL_DRIFT_SPEED * activity,
+        });
+    }
+
+    pub fn particles(&self) -> impl Iterator<Item = &TrailParticle> {
+        self.particles.iter().map(|tail_particle| &tail_particle.particle)
+    }
+}
+
+pub struct Comet {
+    pub position: Vec3,
+    pub velocity: Vec3,
+    pub tail: CometTail,
+}
+
+impl Comet {
+    pub fn new(position: Vec3, velocity: Vec3) -> Self {
+        Self {
+            position,
+            velocity,
+            tail: CometTail::new(),
+        }
+    }
+
+    // Integra la atracción del Sol y mantiene la cola apuntando en dirección contraria
+    pub fn update(&mut self, dt: f32, sun_position: Vec3) {
+        let to_sun = sun_position - self.position;
+        let distance = to_sun.magnitude().max(2.0);
+        self.velocity += to_sun.normalize() * (SUN_GM / (distance * distance)) * dt;
+        self.position += self.velocity * dt;
+
+        self.tail.update(dt, self.position, sun_position);
+    }
+}
+
+// Lluvia de cometas: aparecen en el borde del sistema con trayectorias hiperbólicas
+// que cruzan el sistema interior y se eliminan al salir de los límites
+pub struct CometShower {
+    pub comets: Vec<Comet>,
+    count: usize,
+    spawn_radius: f32,
+    bounds: f32,
+    inner_radius: f32,
+    speed: f32,
+    rng: StdRng,
+}
+
+impl CometShower {
+    pub fn new(count: usize, spawn_radius: f32, bounds: f32, seed: u64) -> Self {
+        Self {
+            comets: Vec::new(),
+            count,
+            spawn_radius,
+            bounds,
+            inner_radius: 20.0,
+            speed: 30.0,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn trigger(&mut self, sun_position: Vec3) {
+        for _ in 0..self.count {
+            // Punto de entrada cerca del plano de la eclíptica
+            let theta = self.rng.gen::<f32>() * 2.0 * PI;
+            let height = self.rng.gen_range(-0.2..0.2) * self.spawn_radius;
+            let spawn = sun_position + Vec3::new(theta.cos(), 0.0, theta.sin()) * self.spawn_radius + Vec3::new(0.0, height, 0.0);
+
+            // Apuntar a un punto aleatorio del sistema interior
+            let aim_angle = self.rng.gen::<f32>() * 2.0 * PI;
+            let aim_distance = self.rng.gen::<f32>() * self.inner_radius;
+            let aim = sun_position + Vec3::new(aim_angle.cos(), 0.0, aim_angle.sin()) * aim_distance;
+
+            let velocity = (aim - spawn).normalize() * self.speed;
+            self.comets.push(Comet::new(spawn, velocity));
+        }
+    }
+
+    pub fn update(&mut self, dt: f32, sun_position: Vec3) {
+        for comet in &mut self.comets {
+            comet.update(dt, sun_position);
+        }
+
+        let bounds = self.bounds;
+        self.comets.retain(|comet| (comet.position - sun_position).magnitude() <= bounds);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shower_spawns_its_count_and_despawns_outside_the_bounds() {
+        let sun = Vec3::new(0.0, 0.0, 0.0);
+        let mut shower = CometShower::new(12, 150.0, 200.0, 7);
+        shower.trigger(sun);
+        assert_eq!(shower.comets.len(), 12);
+
+        // Todos nacen dentro de los límites, así que ninguno se elimina en el primer paso
+        shower.update(0.01, sun);
+        assert_eq!(shower.comets.len(), 12);
+
+        // Las trayectorias son hiperbólicas: al rato todos salieron del sistema
+        for _ in 0..4000 {
+            shower.update(0.01, sun);
+        }
+        assert!(shower.comets.is_empty());
+
+        // Otra lluvia vuelve a lanzar la misma cantidad
+        shower.trigger(sun);
+        assert_eq!(shower.comets.len(), 12);
+    }
+}
@@ -0,0 +1,96 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+use nalgebra_glm::Vec3;
+use crate::planet_type::PlanetType;
+
+// Julian date of the J2000.0 epoch (2000-01-01 12:00 TT)
+const J2000: f64 = 2451545.0;
+
+// Julian date of the Unix epoch (1970-01-01 00:00 UTC)
+const UNIX_EPOCH_JULIAN_DATE: f64 = 2440587.5;
+
+pub fn julian_date_now() -> f64 {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs_f64())
+        .unwrap_or(0.0);
+
+    UNIX_EPOCH_JULIAN_DATE + seconds / 86400.0
+}
+
+// Approximate heliocentric mean longitude (radians, in [0, 2π)) of the major
+// planets, from the J2000 mean elements: L = L0 + n * (JD - J2000).
+// Returns None for bodies that are not major planets.
+pub fn mean_longitude(planet: &PlanetType, julian_date: f64) -> Option<f32> {
+    // (L0 in degrees, n in degrees per day)
+    let (l0, rate) = match planet {
+        PlanetType::Mercury => (252.250_84, 4.092_338_8),
+        PlanetType::Venus => (181.979_73, 1.602_130_47),
+        PlanetType::Earth => (100.464_35, 0.985_609_1),
+        PlanetType::Mars => (355.453_32, 0.524_033_04),
+        PlanetType::Jupiter => (34.404_38, 0.083_086_76),
+        PlanetType::Saturn => (49.944_32, 0.033_460_63),
+        PlanetType::Uranus => (313.232_18, 0.011_731_29),
+        PlanetType::Neptune => (304.880_03, 0.005_981_06),
+        _ => return None,
+    };
+
+    let degrees: f64 = (l0 + rate * (julian_date - J2000)).rem_euclid(360.0);
+    Some(degrees.to_radians() as f32)
+}
+
+// Solves Kepler's equation M = E - e * sin(E) for the eccentric anomaly E
+// with a few Newton iterations (converges quickly for e < 0.9)
+pub fn eccentric_anomaly(mean_anomaly: f32, eccentricity: f32) -> f32 {
+    let mut anomaly = if eccentricity < 0.8 { mean_anomaly } else { std::f32::consts::PI };
+    for _ in 0..6 {
+        let error = anomaly - eccentricity * anomaly.sin() - mean_anomaly;
+        anomaly -= error / (1.0 - eccentricity * anomaly.cos());
+    }
+    anomaly
+}
+
+// Offset from the focus of an elliptical orbit with the periapsis along +X. The
+// orbit lies in the XZ plane tilted about the X axis by `inclination` (radians);
+// with eccentricity 0 and inclination 0 this is a flat circle of radius a.
+pub fn orbit_offset(semi_major_axis: f32, eccentricity: f32, inclination: f32, mean_anomaly: f32) -> Vec3 {
+    let anomaly = eccentric_anomaly(mean_anomaly.rem_euclid(std::f32::consts::TAU), eccentricity);
+    let semi_minor_axis = semi_major_axis * (1.0 - eccentricity * eccentricity).sqrt();
+
+    let x = semi_major_axis * (anomaly.cos() - eccentricity);
+    let z = semi_minor_axis * anomaly.sin();
+    let (sin_i, cos_i) = inclination.sin_cos();
+
+    Vec3::new(x, -z * sin_i, z * cos_i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn degrees(planet: &PlanetType, julian_date: f64) -> f32 {
+        mean_longitude(planet, julian_date).unwrap().to_degrees()
+    }
+
+    #[test]
+    fn mean_longitude_at_j2000_is_the_epoch_value() {
+        assert!((degrees(&PlanetType::Earth, J2000) - 100.464).abs() < 0.01);
+        assert!((degrees(&PlanetType::Mars, J2000) - 355.453).abs() < 0.01);
+        assert_eq!(mean_longitude(&PlanetType::Moon, J2000), None);
+    }
+
+    #[test]
+    fn mean_longitude_matches_the_almanac_within_tolerance() {
+        // 2024-01-01 00:00 UT. The Astronomical Almanac gives the Sun's mean longitude
+        // as 280.460° + 0.9856474° per day since J2000; the Earth is opposite it
+        let julian_date = 2460310.5;
+        let days = julian_date - J2000;
+        let expected = ((280.460 + 0.985_647_4 * days + 180.0) % 360.0) as f32;
+        assert!((degrees(&PlanetType::Earth, julian_date) - expected).abs() < 0.5);
+
+        // Always wrapped into [0, 360)
+        for planet in [PlanetType::Mercury, PlanetType::Neptune] {
+            let longitude = degrees(&planet, julian_date);
+            assert!((0.0..360.0).contains(&longitude));
+        }
+    }
+}
@@ -8,6 +8,10 @@ pub struct Fragment {
     pub normal: Vec3,
     pub intensity: f32,
     pub vertex_position: Vec3,
+    // Interpolated clip-space w (view depth) of the fragment
+    pub clip_w: f32,
+    // Fraction of the pixel covered, below 1 only on the edges of anti-aliased lines
+    pub coverage: f32,
 }
 
 impl Fragment {
@@ -18,7 +22,8 @@ impl Fragment {
         normal: Vec3,
         intensity: f32,
         vertex_position: Vec3,
-    ) -> Self {  
+        clip_w: f32,
+    ) -> Self {
         Fragment {
             position,
             color,
@@ -26,6 +31,8 @@ impl Fragment {
             normal,
             intensity,
             vertex_position,
+            clip_w,
+            coverage: 1.0,
         }
     }
 }
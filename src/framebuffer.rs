@@ -1,29 +1,326 @@
 // framebuffer.rs
 
+use crate::color::GAMMA;
+
+// How depth values are stored and compared
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DepthFormat {
+    Float32,
+    // NDC depth in [-1, 1] quantized to 24 bits and compared as integers
+    Packed24,
+}
+
+const PACKED_DEPTH_MAX: u32 = (1 << 24) - 1;
+
+enum DepthBuffer {
+    Float32(Vec<f32>),
+    Packed24(Vec<u32>),
+}
+
+impl DepthBuffer {
+    fn new(format: DepthFormat, size: usize) -> Self {
+        match format {
+            DepthFormat::Float32 => DepthBuffer::Float32(vec![f32::INFINITY; size]),
+            DepthFormat::Packed24 => DepthBuffer::Packed24(vec![u32::MAX; size]),
+        }
+    }
+
+    fn clear(&mut self) {
+        match self {
+            DepthBuffer::Float32(values) => values.fill(f32::INFINITY),
+            // u32::MAX is above every encoded depth, so it acts as "infinitely far"
+            DepthBuffer::Packed24(values) => values.fill(u32::MAX),
+        }
+    }
+
+    fn passes(&self, index: usize, depth: f32) -> bool {
+        match self {
+            DepthBuffer::Float32(values) => depth < values[index],
+            DepthBuffer::Packed24(values) => encode_packed_depth(depth) < values[index],
+        }
+    }
+
+    fn write(&mut self, index: usize, depth: f32) {
+        match self {
+            DepthBuffer::Float32(values) => values[index] = depth,
+            DepthBuffer::Packed24(values) => values[index] = encode_packed_depth(depth),
+        }
+    }
+}
+
+fn encode_packed_depth(depth: f32) -> u32 {
+    let normalized = (depth.clamp(-1.0, 1.0) + 1.0) * 0.5;
+    (normalized * PACKED_DEPTH_MAX as f32).round() as u32
+}
+
 pub struct Framebuffer {
+    // Color and depth are stored per sample, at `scale` times the display resolution
     pub buffer: Vec<u32>,
-    pub z_buffer: Vec<f32>,
+    z_buffer: DepthBuffer,
+    // Scratch depth for geometry that must stay on top (the first-person ship); it is
+    // swapped in by `begin_overlay_depth` so the scene's z-buffer is left untouched
+    overlay_depth: DepthBuffer,
     pub width: usize,
     pub height: usize,
+    // Display-sized colors produced by `resolve`
+    pub resolved: Vec<u32>,
+    pub display_width: usize,
+    pub display_height: usize,
+    pub scale: usize,
     current_color: u32,
     background_color: u32,
+    // Scratch buffers for `apply_bloom` (bright pass and blur), display-sized
+    bloom_buffers: (Vec<[f32; 3]>, Vec<[f32; 3]>),
+    // Scratch copy of `resolved` for effects that read other pixels while writing
+    post_buffer: Vec<u32>,
+    // Optional per-sample HDR colors (channels may exceed 1.0). While enabled every
+    // write also lands here, and `tone_map` turns it back into `buffer`
+    hdr: Option<Vec<[f32; 3]>>,
 }
 
 impl Framebuffer {
-    pub fn new(width: usize, height: usize) -> Self {
+    pub fn new(width: usize, height: usize, depth_format: DepthFormat) -> Self {
+        Self::new_supersampled(width, height, 1, depth_format)
+    }
+
+    // Renders at `scale`x`scale` samples per display pixel; `resolve` averages them back down
+    pub fn new_supersampled(width: usize, height: usize, scale: usize, depth_format: DepthFormat) -> Self {
+        let scale = scale.max(1);
+        let (sample_width, sample_height) = (width * scale, height * scale);
+
         Framebuffer {
-            buffer: vec![0; width * height],
-            z_buffer: vec![f32::INFINITY; width * height],
-            width,
-            height,
+            buffer: vec![0; sample_width * sample_height],
+            z_buffer: DepthBuffer::new(depth_format, sample_width * sample_height),
+            overlay_depth: DepthBuffer::new(depth_format, sample_width * sample_height),
+            width: sample_width,
+            height: sample_height,
+            resolved: vec![0; width * height],
+            display_width: width,
+            display_height: height,
+            scale,
             current_color: 0,
             background_color: 0,
+            bloom_buffers: (Vec::new(), Vec::new()),
+            post_buffer: Vec::new(),
+            hdr: None,
+        }
+    }
+
+    // Samples covering the display pixel that contains sample (x, y). Single-pixel
+    // primitives (stars, trail points) draw to all of them so resolving doesn't dim them
+    pub fn display_pixel_samples(&self, x: usize, y: usize) -> impl Iterator<Item = (usize, usize)> {
+        let (left, top) = (x - x % self.scale, y - y % self.scale);
+        let (right, bottom) = ((left + self.scale).min(self.width), (top + self.scale).min(self.height));
+        (top..bottom).flat_map(move |sy| (left..right).map(move |sx| (sx, sy)))
+    }
+
+    // Box-downsamples each scale x scale block into one display pixel. Blocks that are
+    // all background are copied as-is; any other block averages every sample, so
+    // silhouette edges blend into whatever lies behind them
+    pub fn resolve(&mut self) {
+        if self.scale == 1 {
+            self.resolved.copy_from_slice(&self.buffer);
+            return;
+        }
+
+        let samples = (self.scale * self.scale) as u32;
+        for y in 0..self.display_height {
+            for x in 0..self.display_width {
+                let mut sum = [0u32; 3];
+                let mut all_background = true;
+
+                for sy in 0..self.scale {
+                    let row = (y * self.scale + sy) * self.width + x * self.scale;
+                    for &sample in &self.buffer[row..row + self.scale] {
+                        all_background &= sample == self.background_color;
+                        sum[0] += (sample >> 16) & 0xFF;
+                        sum[1] += (sample >> 8) & 0xFF;
+                        sum[2] += sample & 0xFF;
+                    }
+                }
+
+                self.resolved[y * self.display_width + x] = if all_background {
+                    self.background_color
+                } else {
+                    ((sum[0] / samples) << 16) | ((sum[1] / samples) << 8) | (sum[2] / samples)
+                };
+            }
+        }
+    }
+
+    // Glow around bright pixels of the resolved image: everything above the
+    // `threshold` luminance (0..1) is blurred with a separable Gaussian of
+    // `blur_radius` pixels and added back scaled by `intensity`. Runs after `resolve`
+    pub fn apply_bloom(&mut self, threshold: f32, blur_radius: usize, intensity: f32) {
+        let (width, height) = (self.display_width, self.display_height);
+        let (bright, blurred) = &mut self.bloom_buffers;
+        bright.resize(width * height, [0.0; 3]);
+        blurred.resize(width * height, [0.0; 3]);
+
+        // Bright pass: keep only the part of each pixel above the threshold
+        for (target, &pixel) in bright.iter_mut().zip(&self.resolved) {
+            let rgb = unpack_rgb(pixel);
+            let luminance = 0.2126 * rgb[0] + 0.7152 * rgb[1] + 0.0722 * rgb[2];
+            let excess = if luminance > threshold { (luminance - threshold) / luminance } else { 0.0 };
+            *target = rgb.map(|channel| channel * excess);
+        }
+
+        let kernel = gaussian_kernel(blur_radius);
+        let radius = blur_radius as isize;
+
+        // Horizontal pass into `blurred`, then vertical pass back into `bright`
+        for y in 0..height {
+            for x in 0..width {
+                let mut sum = [0.0; 3];
+                for (offset, weight) in (-radius..=radius).zip(&kernel) {
+                    let sx = (x as isize + offset).clamp(0, width as isize - 1) as usize;
+                    let sample = bright[y * width + sx];
+                    for channel in 0..3 {
+                        sum[channel] += sample[channel] * weight;
+                    }
+                }
+                blurred[y * width + x] = sum;
+            }
+        }
+        for y in 0..height {
+            for x in 0..width {
+                let mut sum = [0.0; 3];
+                for (offset, weight) in (-radius..=radius).zip(&kernel) {
+                    let sy = (y as isize + offset).clamp(0, height as isize - 1) as usize;
+                    let sample = blurred[sy * width + x];
+                    for channel in 0..3 {
+                        sum[channel] += sample[channel] * weight;
+                    }
+                }
+                bright[y * width + x] = sum;
+            }
+        }
+
+        for (pixel, glow) in self.resolved.iter_mut().zip(bright.iter()) {
+            let rgb = unpack_rgb(*pixel);
+            let mut bloomed = [0.0; 3];
+            for channel in 0..3 {
+                bloomed[channel] = rgb[channel] + glow[channel] * intensity;
+            }
+            *pixel = pack_rgb(bloomed);
+        }
+    }
+
+    // Darkens the resolved image towards the corners: pixels lose up to `strength`
+    // (0..1) of their brightness, growing with the squared distance from the center
+    pub fn apply_vignette(&mut self, strength: f32) {
+        if strength <= 0.0 {
+            return;
+        }
+        let (width, height) = (self.display_width, self.display_height);
+        let (center_x, center_y) = (width as f32 * 0.5, height as f32 * 0.5);
+        let corner_squared = center_x * center_x + center_y * center_y;
+
+        for y in 0..height {
+            for x in 0..width {
+                let (dx, dy) = (x as f32 + 0.5 - center_x, y as f32 + 0.5 - center_y);
+                let falloff = 1.0 - strength.min(1.0) * (dx * dx + dy * dy) / corner_squared;
+                let pixel = &mut self.resolved[y * width + x];
+                *pixel = pack_rgb(unpack_rgb(*pixel).map(|channel| channel * falloff));
+            }
+        }
+    }
+
+    // Splits the red and blue channels radially: red is sampled farther from the
+    // center and blue closer, by up to `strength` pixels at the corners
+    pub fn apply_chromatic_aberration(&mut self, strength: f32) {
+        if strength <= 0.0 {
+            return;
+        }
+        let (width, height) = (self.display_width, self.display_height);
+        let (center_x, center_y) = (width as f32 * 0.5, height as f32 * 0.5);
+        let corner = (center_x * center_x + center_y * center_y).sqrt();
+        self.post_buffer.clone_from(&self.resolved);
+        let source = &self.post_buffer;
+
+        let sample = |x: f32, y: f32| -> u32 {
+            let sx = (x as isize).clamp(0, width as isize - 1) as usize;
+            let sy = (y as isize).clamp(0, height as isize - 1) as usize;
+            source[sy * width + sx]
+        };
+
+        for y in 0..height {
+            for x in 0..width {
+                // Offset along the direction from the center, proportional to the distance
+                let (dx, dy) = (x as f32 + 0.5 - center_x, y as f32 + 0.5 - center_y);
+                let (offset_x, offset_y) = (dx / corner * strength, dy / corner * strength);
+                let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+
+                let red = sample(px + offset_x, py + offset_y) & 0xFF0000;
+                let green = source[y * width + x] & 0x00FF00;
+                let blue = sample(px - offset_x, py - offset_y) & 0x0000FF;
+                self.resolved[y * width + x] = red | green | blue;
+            }
         }
     }
 
     pub fn clear(&mut self) {
         self.buffer.fill(self.background_color);
-        self.z_buffer.fill(f32::INFINITY);
+        self.z_buffer.clear();
+        if let Some(hdr) = &mut self.hdr {
+            hdr.fill(unpack_rgb(self.background_color));
+        }
+    }
+
+    pub fn set_hdr(&mut self, enabled: bool) {
+        self.hdr = enabled.then(|| self.buffer.iter().map(|&pixel| unpack_rgb(pixel)).collect());
+    }
+
+    pub fn hdr_enabled(&self) -> bool {
+        self.hdr.is_some()
+    }
+
+    // Depth-tested write of an unclamped color. Without HDR it is clamped to 8 bits
+    pub fn point_hdr(&mut self, x: usize, y: usize, depth: f32, rgb: [f32; 3], write_depth: bool) {
+        let index = y * self.width + x;
+        if self.z_buffer.passes(index, depth) {
+            self.buffer[index] = pack_rgb(rgb);
+            if let Some(hdr) = &mut self.hdr {
+                hdr[index] = rgb;
+            }
+            if write_depth {
+                self.z_buffer.write(index, depth);
+            }
+        }
+    }
+
+    // Converts the HDR samples back into `buffer` with the ACES filmic curve (Narkowicz
+    // fit) applied in linear space. Does nothing unless HDR is enabled; runs before `resolve`
+    pub fn tone_map(&mut self, exposure: f32) {
+        let Some(hdr) = &self.hdr else {
+            return;
+        };
+
+        for (pixel, rgb) in self.buffer.iter_mut().zip(hdr) {
+            *pixel = pack_rgb(rgb.map(|channel| {
+                let linear = channel.max(0.0).powf(GAMMA) * exposure;
+                let mapped = (linear * (2.51 * linear + 0.03)) / (linear * (2.43 * linear + 0.59) + 0.14);
+                mapped.clamp(0.0, 1.0).powf(1.0 / GAMMA)
+            }));
+        }
+    }
+
+    // Keeps the HDR copy in sync with an 8-bit write
+    fn mirror_hdr(&mut self, index: usize) {
+        if let Some(hdr) = &mut self.hdr {
+            hdr[index] = unpack_rgb(self.buffer[index]);
+        }
+    }
+
+    // Until `end_overlay_depth`, depth tests and writes go to an empty scratch buffer:
+    // what is drawn lands on top of the scene and is depth-sorted only against itself
+    pub fn begin_overlay_depth(&mut self) {
+        self.overlay_depth.clear();
+        std::mem::swap(&mut self.z_buffer, &mut self.overlay_depth);
+    }
+
+    pub fn end_overlay_depth(&mut self) {
+        std::mem::swap(&mut self.z_buffer, &mut self.overlay_depth);
     }
 
     pub fn set_current_color(&mut self, color: u32) {
@@ -36,14 +333,260 @@ impl Framebuffer {
 
     pub fn should_draw(&self, x: usize, y: usize, depth: f32) -> bool {
         let index = y * self.width + x;
-        depth < self.z_buffer[index]
+        self.z_buffer.passes(index, depth)
     }
 
     pub fn point(&mut self, x: usize, y: usize, depth: f32) {
         let index = y * self.width + x;
-        if depth < self.z_buffer[index] {
+        if self.z_buffer.passes(index, depth) {
             self.buffer[index] = self.current_color;
-            self.z_buffer[index] = depth;
+            self.z_buffer.write(index, depth);
+            self.mirror_hdr(index);
+        }
+    }
+
+    // Writes straight to the color buffer, ignoring depth (for screen-space overlays)
+    pub fn point_no_depth(&mut self, x: usize, y: usize, color: u32) {
+        if x < self.width && y < self.height {
+            self.buffer[y * self.width + x] = color;
+            self.mirror_hdr(y * self.width + x);
+        }
+    }
+
+    // Alpha-blends an 0xAARRGGBB color over the current pixel. It is depth-tested
+    // but never writes depth, so blended geometry doesn't hide what's behind it
+    pub fn blend_point(&mut self, x: usize, y: usize, depth: f32, color: u32) {
+        let index = y * self.width + x;
+        if self.z_buffer.passes(index, depth) {
+            self.buffer[index] = blend_argb(color, self.buffer[index]);
+            self.mirror_hdr(index);
+        }
+    }
+
+    // Draws text with the built-in bitmap font, always on top (no depth test)
+    pub fn draw_text(&mut self, x: usize, y: usize, text: &str, color: u32) {
+        for (index, character) in text.chars().enumerate() {
+            let origin_x = x + index * GLYPH_ADVANCE;
+
+            for (row, bits) in glyph(character).iter().enumerate() {
+                for column in 0..GLYPH_WIDTH {
+                    if bits & (1 << (GLYPH_WIDTH - 1 - column)) != 0 {
+                        self.point_no_depth(origin_x + column, y + row, color);
+                    }
+                }
+            }
+        }
+    }
+
+    // Saves the resolved color buffer as an RGB8 PNG. Pixels are 0x00RRGGBB (the alpha
+    // byte is ignored) and row 0 is the top of the screen, so no vertical flip is needed
+    pub fn save_png(&self, path: &str) -> image::ImageResult<()> {
+        let rgb: Vec<u8> = self.resolved.iter()
+            .flat_map(|&pixel| [(pixel >> 16) as u8, (pixel >> 8) as u8, pixel as u8])
+            .collect();
+        image::save_buffer(path, &rgb, self.display_width as u32, self.display_height as u32, image::ColorType::Rgb8)
+    }
+
+    // Depth-tested write that leaves the z-buffer untouched
+    pub fn point_without_depth_write(&mut self, x: usize, y: usize, depth: f32) {
+        let index = y * self.width + x;
+        if self.z_buffer.passes(index, depth) {
+            self.buffer[index] = self.current_color;
+            self.mirror_hdr(index);
+        }
+    }
+}
+
+// Blends a 0xAARRGGBB overlay (HUD, text) over the resolved scene, so it must run after
+// `resolve`. The overlay is display-sized; fully transparent pixels are skipped
+pub fn composite_overlay(scene: &mut Framebuffer, overlay: &Framebuffer) {
+    for (destination, &source) in scene.resolved.iter_mut().zip(&overlay.buffer) {
+        if source >> 24 != 0 {
+            *destination = blend_argb(source, *destination);
+        }
+    }
+}
+
+// 0x00RRGGBB to channels in [0, 1] and back (clamping)
+fn unpack_rgb(pixel: u32) -> [f32; 3] {
+    [(pixel >> 16) & 0xFF, (pixel >> 8) & 0xFF, pixel & 0xFF].map(|channel| channel as f32 / 255.0)
+}
+
+fn pack_rgb(rgb: [f32; 3]) -> u32 {
+    let [r, g, b] = rgb.map(|channel| (channel.clamp(0.0, 1.0) * 255.0).round() as u32);
+    (r << 16) | (g << 8) | b
+}
+
+// Normalized Gaussian weights for offsets -radius..=radius, with sigma = radius / 2
+fn gaussian_kernel(radius: usize) -> Vec<f32> {
+    let sigma = (radius as f32 / 2.0).max(0.5);
+    let weights: Vec<f32> = (-(radius as isize)..=radius as isize)
+        .map(|offset| (-((offset * offset) as f32) / (2.0 * sigma * sigma)).exp())
+        .collect();
+    let total: f32 = weights.iter().sum();
+    weights.into_iter().map(|weight| weight / total).collect()
+}
+
+// dst = src * a + dst * (1 - a), per channel, using the source alpha byte
+fn blend_argb(source: u32, destination: u32) -> u32 {
+    let alpha = (source >> 24) & 0xFF;
+    if alpha == 0xFF {
+        return source & 0x00FFFFFF;
+    }
+
+    let blend_channel = |shift: u32| {
+        let src = (source >> shift) & 0xFF;
+        let dst = (destination >> shift) & 0xFF;
+        ((src * alpha + dst * (255 - alpha)) / 255) << shift
+    };
+
+    blend_channel(16) | blend_channel(8) | blend_channel(0)
+}
+
+pub const GLYPH_WIDTH: usize = 5;
+pub const GLYPH_HEIGHT: usize = 7;
+// Horizontal advance per character, including one pixel of spacing
+pub const GLYPH_ADVANCE: usize = GLYPH_WIDTH + 1;
+
+// 5x7 bitmap font: one byte per row, bit 4 is the leftmost pixel.
+// Lowercase letters are drawn as uppercase, accented letters without their
+// accent, and unknown characters as '?'
+fn glyph(character: char) -> [u8; GLYPH_HEIGHT] {
+    match character.to_ascii_uppercase() {
+        'á' | 'Á' => glyph('A'),
+        'é' | 'É' => glyph('E'),
+        'í' | 'Í' => glyph('I'),
+        'ó' | 'Ó' => glyph('O'),
+        'ú' | 'Ú' | 'ü' | 'Ü' => glyph('U'),
+        'ñ' | 'Ñ' => glyph('N'),
+        'A' => [0b01110, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001],
+        'B' => [0b11110, 0b10001, 0b10001, 0b11110, 0b10001, 0b10001, 0b11110],
+        'C' => [0b01110, 0b10001, 0b10000, 0b10000, 0b10000, 0b10001, 0b01110],
+        'D' => [0b11110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b11110],
+        'E' => [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b11111],
+        'F' => [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b10000],
+        'G' => [0b01110, 0b10001, 0b10000, 0b10111, 0b10001, 0b10001, 0b01111],
+        'H' => [0b10001, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001],
+        'I' => [0b01110, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110],
+        'J' => [0b00111, 0b00010, 0b00010, 0b00010, 0b00010, 0b10010, 0b01100],
+        'K' => [0b10001, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010, 0b10001],
+        'L' => [0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b11111],
+        'M' => [0b10001, 0b11011, 0b10101, 0b10101, 0b10001, 0b10001, 0b10001],
+        'N' => [0b10001, 0b10001, 0b11001, 0b10101, 0b10011, 0b10001, 0b10001],
+        'O' => [0b01110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110],
+        'P' => [0b11110, 0b10001, 0b10001, 0b11110, 0b10000, 0b10000, 0b10000],
+        'Q' => [0b01110, 0b10001, 0b10001, 0b10001, 0b10101, 0b10010, 0b01101],
+        'R' => [0b11110, 0b10001, 0b10001, 0b11110, 0b10100, 0b10010, 0b10001],
+        'S' => [0b01111, 0b10000, 0b10000, 0b01110, 0b00001, 0b00001, 0b11110],
+        'T' => [0b11111, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100],
+        'U' => [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110],
+        'V' => [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100],
+        'W' => [0b10001, 0b10001, 0b10001, 0b10101, 0b10101, 0b10101, 0b01010],
+        'X' => [0b10001, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001, 0b10001],
+        'Y' => [0b10001, 0b10001, 0b01010, 0b00100, 0b00100, 0b00100, 0b00100],
+        'Z' => [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b11111],
+        '0' => [0b01110, 0b10001, 0b10011, 0b10101, 0b11001, 0b10001, 0b01110],
+        '1' => [0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110],
+        '2' => [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000, 0b11111],
+        '3' => [0b11111, 0b00010, 0b00100, 0b00010, 0b00001, 0b10001, 0b01110],
+        '4' => [0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010, 0b00010],
+        '5' => [0b11111, 0b10000, 0b11110, 0b00001, 0b00001, 0b10001, 0b01110],
+        '6' => [0b00110, 0b01000, 0b10000, 0b11110, 0b10001, 0b10001, 0b01110],
+        '7' => [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b01000, 0b01000],
+        '8' => [0b01110, 0b10001, 0b10001, 0b01110, 0b10001, 0b10001, 0b01110],
+        '9' => [0b01110, 0b10001, 0b10001, 0b01111, 0b00001, 0b00010, 0b01100],
+        ' ' => [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000],
+        '.' => [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b01100],
+        ',' => [0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b00100, 0b01000],
+        ':' => [0b00000, 0b01100, 0b01100, 0b00000, 0b01100, 0b01100, 0b00000],
+        '-' => [0b00000, 0b00000, 0b00000, 0b11111, 0b00000, 0b00000, 0b00000],
+        '+' => [0b00000, 0b00100, 0b00100, 0b11111, 0b00100, 0b00100, 0b00000],
+        '/' => [0b00001, 0b00010, 0b00010, 0b00100, 0b01000, 0b01000, 0b10000],
+        '(' => [0b00010, 0b00100, 0b01000, 0b01000, 0b01000, 0b00100, 0b00010],
+        ')' => [0b01000, 0b00100, 0b00010, 0b00010, 0b00010, 0b00100, 0b01000],
+        '%' => [0b11000, 0b11001, 0b00010, 0b00100, 0b01000, 0b10011, 0b00011],
+        '=' => [0b00000, 0b00000, 0b11111, 0b00000, 0b11111, 0b00000, 0b00000],
+        '!' => [0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00000, 0b00100],
+        '?' => [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b00000, 0b00100],
+        '_' => [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b11111],
+        '[' => [0b01110, 0b01000, 0b01000, 0b01000, 0b01000, 0b01000, 0b01110],
+        ']' => [0b01110, 0b00010, 0b00010, 0b00010, 0b00010, 0b00010, 0b01110],
+        '<' => [0b00010, 0b00100, 0b01000, 0b10000, 0b01000, 0b00100, 0b00010],
+        '>' => [0b01000, 0b00100, 0b00010, 0b00001, 0b00010, 0b00100, 0b01000],
+        '\'' => [0b00100, 0b00100, 0b01000, 0b00000, 0b00000, 0b00000, 0b00000],
+        '*' => [0b00000, 0b00100, 0b10101, 0b01110, 0b10101, 0b00100, 0b00000],
+        '#' => [0b01010, 0b01010, 0b11111, 0b01010, 0b11111, 0b01010, 0b01010],
+        _ => glyph('?'),
+    }
+}
+
+pub fn text_width(text: &str) -> usize {
+    (text.chars().count() * GLYPH_ADVANCE).saturating_sub(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packed_depth_keeps_the_float_ordering() {
+        let depths: Vec<f32> = (0..=2000).map(|i| -1.0 + i as f32 / 1000.0).collect();
+        for pair in depths.windows(2) {
+            assert!(encode_packed_depth(pair[0]) < encode_packed_depth(pair[1]));
+        }
+
+        // The ends of the range map to the ends of 24 bits, and anything outside is clamped
+        assert_eq!(encode_packed_depth(-1.0), 0);
+        assert_eq!(encode_packed_depth(1.0), PACKED_DEPTH_MAX);
+        assert_eq!(encode_packed_depth(5.0), PACKED_DEPTH_MAX);
+    }
+
+    #[test]
+    fn packed_depth_test_matches_float_depth_test() {
+        for format in [DepthFormat::Float32, DepthFormat::Packed24] {
+            let mut framebuffer = Framebuffer::new(1, 1, format);
+            framebuffer.set_current_color(0x112233);
+            framebuffer.point(0, 0, 0.9);
+            framebuffer.set_current_color(0x445566);
+            framebuffer.point(0, 0, 0.5);
+            // Farther than what is already there
+            framebuffer.set_current_color(0x778899);
+            framebuffer.point(0, 0, 0.7);
+            assert_eq!(framebuffer.buffer[0], 0x445566, "{:?}", format);
         }
     }
+
+    #[test]
+    fn half_alpha_overlay_blends_halfway() {
+        let mut scene = Framebuffer::new(3, 1, DepthFormat::Float32);
+        scene.resolved = vec![0x000000, 0x204060, 0xFF0000];
+        let mut overlay = Framebuffer::new(3, 1, DepthFormat::Float32);
+        // Half-transparent white, fully transparent red and opaque blue
+        overlay.buffer = vec![0x80FFFFFF, 0x00FF0000, 0xFF0000FF];
+
+        composite_overlay(&mut scene, &overlay);
+
+        assert_eq!(scene.resolved[0], 0x808080);
+        assert_eq!(scene.resolved[1], 0x204060);
+        assert_eq!(scene.resolved[2], 0x0000FF);
+    }
+
+
+    #[test]
+    fn saved_png_matches_the_screen() {
+        let mut framebuffer = Framebuffer::new(3, 2, DepthFormat::Float32);
+        // Top row first, with a stray alpha byte that must not leak into the colors
+        framebuffer.resolved = vec![0xFF0000, 0x00FF00, 0x0000FF, 0x80123456, 0xFFFFFF, 0x000000];
+
+        let path = std::env::temp_dir().join(format!("screenshot_test_{}.png", std::process::id()));
+        framebuffer.save_png(path.to_str().unwrap()).unwrap();
+        let saved = image::open(&path).unwrap().into_rgb8();
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(saved.dimensions(), (3, 2));
+        assert_eq!(saved.get_pixel(0, 0).0, [0xFF, 0x00, 0x00]);
+        assert_eq!(saved.get_pixel(2, 0).0, [0x00, 0x00, 0xFF]);
+        assert_eq!(saved.get_pixel(0, 1).0, [0x12, 0x34, 0x56]);
+        assert_eq!(saved.get_pixel(1, 1).0, [0xFF, 0xFF, 0xFF]);
+    }
 }
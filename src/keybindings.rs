@@ -0,0 +1,266 @@
+use std::fs;
+use minifb::{Key, KeyRepeat, Window};
+
+// Cantidad de ranuras de warp (teclas 1-9 por defecto, ver `warp_slot` en la escena)
+pub const WARP_SLOTS: usize = 9;
+
+// Teclas de cada acción de la nave y de cada interruptor de la simulación y la
+// pantalla. Cada acción acepta varias teclas (por ejemplo los dos Shift), y
+// cualquiera de ellas la activa
+pub struct KeyBindings {
+    pub forward: Vec<Key>,
+    pub back: Vec<Key>,
+    pub yaw_left: Vec<Key>,
+    pub yaw_right: Vec<Key>,
+    pub pitch_up: Vec<Key>,
+    pub pitch_down: Vec<Key>,
+    pub ascend: Vec<Key>,
+    pub descend: Vec<Key>,
+    pub boost: Vec<Key>,
+    pub speed_up: Vec<Key>,
+    pub speed_down: Vec<Key>,
+    pub bird_eye: Vec<Key>,
+    // warp[i] lleva al cuerpo con `warp_slot = i + 1`
+    pub warp: [Vec<Key>; WARP_SLOTS],
+    pub quit: Vec<Key>,
+    // Simulación: pausa, mitad/doble de velocidad y cámara lenta (mientras se mantiene)
+    pub pause: Vec<Key>,
+    pub time_slower: Vec<Key>,
+    pub time_faster: Vec<Key>,
+    pub bullet_time: Vec<Key>,
+    pub comet_shower: Vec<Key>,
+    pub collisions: Vec<Key>,
+    // Cámara y ratón
+    pub chase_camera: Vec<Key>,
+    pub mouse_look: Vec<Key>,
+    // Estilo de dibujo y efectos
+    pub toon: Vec<Key>,
+    pub poster: Vec<Key>,
+    pub trail_style: Vec<Key>,
+    pub orbits: Vec<Key>,
+    pub render_mode: Vec<Key>,
+    pub hdr: Vec<Key>,
+    pub bloom: Vec<Key>,
+    pub vignette: Vec<Key>,
+    pub aberration: Vec<Key>,
+    // Interfaz
+    pub fps: Vec<Key>,
+    pub minimap: Vec<Key>,
+    pub interface: Vec<Key>,
+    pub screenshot: Vec<Key>,
+}
+
+impl Default for KeyBindings {
+    // Los controles de siempre, pensados para un teclado QWERTY
+    fn default() -> Self {
+        Self {
+            forward: vec![Key::W],
+            back: vec![Key::S],
+            yaw_left: vec![Key::A],
+            yaw_right: vec![Key::D],
+            pitch_up: vec![Key::Up],
+            pitch_down: vec![Key::Down],
+            ascend: vec![Key::Q],
+            descend: vec![Key::E],
+            boost: vec![Key::LeftShift, Key::RightShift],
+            speed_up: vec![Key::Equal, Key::NumPadPlus],
+            speed_down: vec![Key::Minus, Key::NumPadMinus],
+            bird_eye: vec![Key::B],
+            warp: [
+                vec![Key::Key1], vec![Key::Key2], vec![Key::Key3],
+                vec![Key::Key4], vec![Key::Key5], vec![Key::Key6],
+                vec![Key::Key7], vec![Key::Key8], vec![Key::Key9],
+            ],
+            quit: vec![Key::Escape],
+            pause: vec![Key::Space],
+            time_slower: vec![Key::LeftBracket],
+            time_faster: vec![Key::RightBracket],
+            bullet_time: vec![Key::Z],
+            comet_shower: vec![Key::K],
+            collisions: vec![Key::N],
+            chase_camera: vec![Key::C],
+            mouse_look: vec![Key::L],
+            toon: vec![Key::T],
+            poster: vec![Key::P],
+            trail_style: vec![Key::R],
+            orbits: vec![Key::O],
+            render_mode: vec![Key::F2],
+            hdr: vec![Key::F3],
+            bloom: vec![Key::G],
+            vignette: vec![Key::V],
+            aberration: vec![Key::X],
+            fps: vec![Key::F1],
+            minimap: vec![Key::M],
+            interface: vec![Key::H],
+            screenshot: vec![Key::F12],
+        }
+    }
+}
+
+impl KeyBindings {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|error| format!("{}: {}", path, error))?;
+        Self::parse(&text).map_err(|error| format!("{}: {}", path, error))
+    }
+
+    // Líneas `accion = "Tecla"` o `accion = "Tecla, OtraTecla"`; las acciones que no
+    // aparecen conservan sus teclas por defecto
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut bindings = Self::default();
+
+        for (index, raw_line) in text.lines().enumerate() {
+            let line_number = index + 1;
+            let line = strip_comment(raw_line).trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let (action, value) = line.split_once('=')
+                .ok_or_else(|| format!("línea {}: se esperaba `accion = \"Tecla\"`", line_number))?;
+            let names = value.trim().strip_prefix('"').and_then(|rest| rest.strip_suffix('"'))
+                .ok_or_else(|| format!("línea {}: las teclas van entre comillas", line_number))?;
+            let keys = names.split(',')
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .map(|name| key_from_name(name).ok_or_else(|| format!("línea {}: tecla desconocida `{}`", line_number, name)))
+                .collect::<Result<Vec<Key>, String>>()?;
+
+            let slot = bindings.action_mut(action.trim())
+                .ok_or_else(|| format!("línea {}: acción desconocida `{}`", line_number, action.trim()))?;
+            *slot = keys;
+        }
+
+        Ok(bindings)
+    }
+
+    // Teclas de la acción con ese nombre en el archivo (`warp_1` a `warp_9` para los warps)
+    fn action_mut(&mut self, action: &str) -> Option<&mut Vec<Key>> {
+        if let Some(slot) = action.strip_prefix("warp_").and_then(|slot| slot.parse::<usize>().ok()) {
+            return slot.checked_sub(1).and_then(|index| self.warp.get_mut(index));
+        }
+        match action {
+            "forward" => Some(&mut self.forward),
+            "back" => Some(&mut self.back),
+            "yaw_left" => Some(&mut self.yaw_left),
+            "yaw_right" => Some(&mut self.yaw_right),
+            "pitch_up" => Some(&mut self.pitch_up),
+            "pitch_down" => Some(&mut self.pitch_down),
+            "ascend" => Some(&mut self.ascend),
+            "descend" => Some(&mut self.descend),
+            "boost" => Some(&mut self.boost),
+            "speed_up" => Some(&mut self.speed_up),
+            "speed_down" => Some(&mut self.speed_down),
+            "bird_eye" => Some(&mut self.bird_eye),
+            "quit" => Some(&mut self.quit),
+            "pause" => Some(&mut self.pause),
+            "time_slower" => Some(&mut self.time_slower),
+            "time_faster" => Some(&mut self.time_faster),
+            "bullet_time" => Some(&mut self.bullet_time),
+            "comet_shower" => Some(&mut self.comet_shower),
+            "collisions" => Some(&mut self.collisions),
+            "chase_camera" => Some(&mut self.chase_camera),
+            "mouse_look" => Some(&mut self.mouse_look),
+            "toon" => Some(&mut self.toon),
+            "poster" => Some(&mut self.poster),
+            "trail_style" => Some(&mut self.trail_style),
+            "orbits" => Some(&mut self.orbits),
+            "render_mode" => Some(&mut self.render_mode),
+            "hdr" => Some(&mut self.hdr),
+            "bloom" => Some(&mut self.bloom),
+            "vignette" => Some(&mut self.vignette),
+            "aberration" => Some(&mut self.aberration),
+            "fps" => Some(&mut self.fps),
+            "minimap" => Some(&mut self.minimap),
+            "interface" => Some(&mut self.interface),
+            "screenshot" => Some(&mut self.screenshot),
+            _ => None,
+        }
+    }
+}
+
+// Si alguna de las teclas está presionada
+pub fn any_down(window: &Window, keys: &[Key]) -> bool {
+    keys.iter().any(|&key| window.is_key_down(key))
+}
+
+// Si alguna de las teclas se acaba de presionar en este frame
+pub fn any_pressed(window: &Window, keys: &[Key], repeat: KeyRepeat) -> bool {
+    keys.iter().any(|&key| window.is_key_pressed(key, repeat))
+}
+
+// Teclas que se pueden usar en el archivo, con el nombre de la variante de `minifb::Key`
+// (los dígitos también se aceptan solos: "1" es Key1)
+const NAMED_KEYS: &[Key] = &[
+    Key::Key0, Key::Key1, Key::Key2, Key::Key3, Key::Key4,
+    Key::Key5, Key::Key6, Key::Key7, Key::Key8, Key::Key9,
+    Key::A, Key::B, Key::C, Key::D, Key::E, Key::F, Key::G, Key::H, Key::I,
+    Key::J, Key::K, Key::L, Key::M, Key::N, Key::O, Key::P, Key::Q, Key::R,
+    Key::S, Key::T, Key::U, Key::V, Key::W, Key::X, Key::Y, Key::Z,
+    Key::F1, Key::F2, Key::F3, Key::F4, Key::F5, Key::F6,
+    Key::F7, Key::F8, Key::F9, Key::F10, Key::F11, Key::F12,
+    Key::Down, Key::Left, Key::Right, Key::Up,
+    Key::Apostrophe, Key::Backquote, Key::Backslash, Key::Comma, Key::Equal,
+    Key::LeftBracket, Key::Minus, Key::Period, Key::RightBracket, Key::Semicolon, Key::Slash,
+    Key::Backspace, Key::Delete, Key::End, Key::Enter, Key::Escape, Key::Home,
+    Key::Insert, Key::PageDown, Key::PageUp, Key::Space, Key::Tab,
+    Key::LeftShift, Key::RightShift, Key::LeftCtrl, Key::RightCtrl, Key::LeftAlt, Key::RightAlt,
+    Key::NumPad0, Key::NumPad1, Key::NumPad2, Key::NumPad3, Key::NumPad4,
+    Key::NumPad5, Key::NumPad6, Key::NumPad7, Key::NumPad8, Key::NumPad9,
+    Key::NumPadPlus, Key::NumPadMinus, Key::NumPadEnter,
+];
+
+fn strip_comment(line: &str) -> &str {
+    // Un # dentro de una cadena no inicia un comentario
+    let mut in_string = false;
+    for (index, character) in line.char_indices() {
+        match character {
+            '"' => in_string = !in_string,
+            '#' if !in_string => return &line[..index],
+            _ => {}
+        }
+    }
+    line
+}
+
+fn key_from_name(name: &str) -> Option<Key> {
+    let name = if name.len() == 1 && name.chars().all(|c| c.is_ascii_digit()) {
+        format!("Key{}", name)
+    } else {
+        name.to_string()
+    };
+    NAMED_KEYS.iter()
+        .find(|key| format!("{:?}", key).eq_ignore_ascii_case(&name))
+        .copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundled_file_matches_the_defaults() {
+        let bundled = KeyBindings::parse(include_str!("../assets/keys.toml")).unwrap();
+        let defaults = KeyBindings::default();
+        assert_eq!(bundled.forward, defaults.forward);
+        assert_eq!(bundled.warp, defaults.warp);
+        assert_eq!(bundled.pause, defaults.pause);
+        assert_eq!(bundled.time_slower, defaults.time_slower);
+        assert_eq!(bundled.bullet_time, defaults.bullet_time);
+        assert_eq!(bundled.render_mode, defaults.render_mode);
+        assert_eq!(bundled.screenshot, defaults.screenshot);
+    }
+
+    #[test]
+    fn toggles_can_be_remapped_and_keep_their_defaults_otherwise() {
+        // En AZERTY la Z es avanzar, así que la cámara lenta pasa a otra tecla
+        let bindings = KeyBindings::parse("forward = \"Z\"\nbullet_time = \"W\"\npause = \"P, Space\"").unwrap();
+        assert_eq!(bindings.forward, vec![Key::Z]);
+        assert_eq!(bindings.bullet_time, vec![Key::W]);
+        assert_eq!(bindings.pause, vec![Key::P, Key::Space]);
+        assert_eq!(bindings.toon, vec![Key::T]);
+        assert_eq!(bindings.screenshot, vec![Key::F12]);
+
+        let error = KeyBindings::parse("bloom = \"G\"\nbrillo = \"B\"").err().unwrap();
+        assert!(error.contains("línea 2"), "{}", error);
+    }
+}
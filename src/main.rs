@@ -1,6 +1,7 @@
-use nalgebra_glm::{Vec3, Vec4, Mat4, look_at, perspective};
-use minifb::{Key, Window, WindowOptions, KeyRepeat};
+use nalgebra_glm::{Vec2, Vec3, Vec4, Mat4, look_at, perspective, quat_to_mat4, scaling, translation};
+use minifb::{Window, WindowOptions, KeyRepeat, MouseMode, MouseButton};
 use std::f32::consts::PI;
+use rayon::prelude::*;
 mod skybox;
 
 mod framebuffer;
@@ -12,25 +13,148 @@ mod fragment;
 mod shaders;
 mod camera;
 mod planet_type;
-
-use framebuffer::Framebuffer;
+mod render_pass;
+mod ephemeris;
+mod comet;
+mod asteroid;
+mod wormhole;
+mod texture;
+mod clipping;
+mod scene;
+mod keybindings;
+
+use framebuffer::{Framebuffer, DepthFormat, GLYPH_HEIGHT, text_width, composite_overlay};
 use vertex::Vertex;
-use obj::Obj;
-use camera::Camera;
-use triangle::triangle;
-use shaders::{vertex_shader, fragment_shader};
+use fragment::Fragment;
+use obj::{Obj, IndexedMesh};
+use camera::{Camera, DEFAULT_SPEED};
+use triangle::{triangle, wireframe, line, line_aa, point, signed_area};
+use shaders::{vertex_shader, project_vertex, fragment_shader, apply_fog, emission, static_surface, bake_surface, Surface};
+use clipping::clip_triangle_near;
+use scene::{SceneConfig, BodyConfig};
+use keybindings::{KeyBindings, any_down, any_pressed};
 use fastnoise_lite::{FastNoiseLite, NoiseType, FractalType};
 use planet_type::PlanetType;
-use skybox::Skybox;
+use render_pass::RenderPass;
+use ephemeris::{mean_longitude, julian_date_now, orbit_offset};
+use comet::{Comet, CometShower, CometTail};
+use asteroid::{Asteroid, AsteroidBelt};
+use wormhole::Wormhole;
+use texture::Texture;
+use skybox::{Skybox, SkyConfig, StarTemperature, GravitationalLens, CUBEMAP_FACE_NAMES, DEFAULT_SKY_SEED};
+use color::Color;
 
 pub struct CelestialBody {
+    // Nombre para la interfaz (etiquetas, tooltips, avisos)
+    name: String,
     position: Vec3,
     scale: f32,
     rotation: Vec3,
+    // Eje de giro inclinado y velocidad de giro alrededor de él
+    axial_tilt: Vec3,
+    spin_speed: f32,
     shader_type: PlanetType,
-    orbital_distance: f32,
+    // Órbita elíptica con el cuerpo padre (el Sol o `parent_index`) en un foco
+    semi_major_axis: f32,
+    eccentricity: f32,
+    // Inclinación (radianes) del plano orbital respecto al del padre
+    inclination: f32,
     orbital_speed: f32,
+    orbital_phase: f32,
     trail: Trail,
+    speed_colored_trail: bool,
+    render_pass: RenderPass,
+    albedo: f32,
+    warp_slot: Option<u32>,
+    warp_distance: f32,
+    rings: Option<Rings>,
+    backface_culling: bool,
+    // Cola de partículas de los cuerpos de tipo cometa, siempre opuesta al Sol
+    comet_tail: Option<CometTail>,
+    // Índice del cuerpo alrededor del que orbita; None = el Sol
+    parent_index: Option<usize>,
+    // Semilla del ruido de su superficie, para que dos cuerpos del mismo tipo no
+    // tengan el mismo terreno
+    seed: u64,
+    // Imagen de la superficie (si la escena le asigna una) y su peso frente al ruido
+    texture: Option<Texture>,
+    texture_blend: f32,
+}
+
+// Disco de anillos que acompaña al cuerpo con su misma inclinación
+pub struct Rings {
+    inner_radius: f32,
+    outer_radius: f32,
+    mesh: IndexedMesh,
+}
+
+impl Rings {
+    fn new(inner_radius: f32, outer_radius: f32) -> Self {
+        Self {
+            inner_radius,
+            outer_radius,
+            mesh: Obj::annulus(inner_radius, outer_radius, 96).get_indexed_mesh(),
+        }
+    }
+}
+
+impl CelestialBody {
+    // Orientación fija del cuerpo (sin el giro): su rotación más la inclinación del eje.
+    // Los anillos la usan tal cual
+    fn tilt_matrix(&self) -> Mat4 {
+        create_model_matrix(self.position, self.scale, self.rotation + self.axial_tilt)
+    }
+
+    // Matriz de modelo en el instante `simulation_time`: el giro acumulado es alrededor
+    // del eje y del modelo, que la inclinación convierte en el eje inclinado
+    fn model_matrix(&self, simulation_time: f32) -> Mat4 {
+        let spin = create_model_matrix(Vec3::new(0.0, 0.0, 0.0), 1.0, Vec3::new(0.0, self.spin_speed * simulation_time, 0.0));
+        self.tilt_matrix() * spin
+    }
+
+    // `index` es la posición del cuerpo en la escena, de la que sale su semilla si
+    // la escena no indica una
+    fn from_config(index: usize, config: BodyConfig) -> Self {
+        // Los modelos sin un winding consistente se dibujan por ambas caras
+        let backface_culling = !matches!(config.shader_type, PlanetType::Rei);
+        let comet_tail = matches!(config.shader_type, PlanetType::Comet).then(CometTail::new);
+        // Sin la imagen el cuerpo conserva su shader procedural
+        let texture = config.texture.as_deref().and_then(|path| match Texture::load(path) {
+            Ok(texture) => Some(texture.with_mipmaps()),
+            Err(error) => {
+                eprintln!("No se pudo cargar la textura {} ({}), se usa el shader procedural", path, error);
+                None
+            }
+        });
+
+        Self {
+            name: config.name.unwrap_or_else(|| config.shader_type.name().to_string()),
+            position: config.position,
+            scale: config.scale,
+            rotation: config.rotation,
+            axial_tilt: config.axial_tilt,
+            spin_speed: config.spin_speed,
+            shader_type: config.shader_type,
+            semi_major_axis: config.semi_major_axis,
+            eccentricity: config.eccentricity,
+            inclination: config.inclination,
+            orbital_speed: config.orbital_speed,
+            orbital_phase: 0.0,
+            trail: Trail::new(config.trail_capacity),
+            speed_colored_trail: config.speed_colored_trail,
+            render_pass: RenderPass::Opaque,
+            albedo: config.albedo,
+            warp_slot: config.warp_slot,
+            warp_distance: config.warp_distance,
+            rings: config.rings.map(|(inner_radius, outer_radius)| Rings::new(inner_radius, outer_radius)),
+            backface_culling,
+            comet_tail,
+            parent_index: config.parent,
+            seed: config.seed.unwrap_or(NOISE_SEED + index as u64),
+            texture,
+            texture_blend: config.texture_blend,
+        }
+    }
 }
 
 pub struct Uniforms {
@@ -39,24 +163,243 @@ pub struct Uniforms {
     projection_matrix: Mat4,
     viewport_matrix: Mat4,
     time: u32,
-    noise: FastNoiseLite,
+    noise: NoiseLayers,
     camera_position: Vec3,
     sun_position: Vec3,
+    sun_pulse: f32,
+    // Brillo extra del Sol cuando el framebuffer es HDR (1.0 sin HDR)
+    sun_emission: f32,
+    // Cuánta luz del Sol llega al cuerpo que se dibuja (ver `light_scale`)
+    light_scale: f32,
+    // Radio del Sol y posición/radio de la Luna, para la sombra de los eclipses sobre
+    // la Tierra (radio 0 = no hay Luna en la escena)
+    sun_radius: f32,
+    moon_position: Vec3,
+    moon_radius: f32,
+    // Anillos del cuerpo que se dibuja, para las sombras entre el disco y el planeta
+    rings: Option<RingGeometry>,
+    toon_enabled: bool,
+    toon: ToonParams,
+    ice_caps: IceCapParams,
+    albedo: f32,
+    alpha: f32,
+    silhouette_mode: bool,
+    // Descartar los triángulos que dan la espalda a la cámara en el dibujo actual
+    cull_backfaces: bool,
+    // Índice del grupo del modelo que se está dibujando (para colorear cada parte)
+    mesh_group: usize,
+    // Usar el color de material de los vértices (modelos con .mtl) en lugar del color fijo
+    vertex_colors: bool,
+    // Niebla por distancia (el skybox no la recibe)
+    fog_color: Color,
+    fog_density: f32,
+}
+
+impl Uniforms {
+    // Uniforms de partida, sin cuerpo ni cámara: cada dibujo sobreescribe lo que necesita
+    fn new(projection_matrix: Mat4, viewport_matrix: Mat4) -> Self {
+        Uniforms {
+            model_matrix: Mat4::identity(),
+            view_matrix: Mat4::identity(),
+            projection_matrix,
+            viewport_matrix,
+            time: 0,
+            noise: NoiseLayers::new(),
+            camera_position: Vec3::new(0.0, 0.0, 0.0),
+            sun_position: Vec3::new(0.0, 0.0, 0.0),
+            sun_pulse: sun_pulse(0),
+            sun_emission: 1.0,
+            light_scale: 1.0,
+            sun_radius: 0.0,
+            moon_position: Vec3::new(0.0, 0.0, 0.0),
+            moon_radius: 0.0,
+            rings: None,
+            toon_enabled: false,
+            toon: ToonParams::default(),
+            ice_caps: IceCapParams::default(),
+            albedo: 1.0,
+            alpha: 1.0,
+            silhouette_mode: false,
+            cull_backfaces: true,
+            mesh_group: 0,
+            vertex_colors: false,
+            fog_color: FOG_COLOR,
+            fog_density: FOG_DENSITY,
+        }
+    }
+}
+
+// Disco de anillos en coordenadas del mundo: centro y normal del plano, radios del
+// disco y radio del planeta que rodea
+pub struct RingGeometry {
+    center: Vec3,
+    normal: Vec3,
+    inner_radius: f32,
+    outer_radius: f32,
+    planet_radius: f32,
+}
+
+// Parámetros del modo cel-shading: bandas de luz y contorno en la silueta
+pub struct ToonParams {
+    levels: u32,
+    outline_threshold: f32,
+    outline_color: Color,
+}
+
+impl Default for ToonParams {
+    fn default() -> Self {
+        Self {
+            levels: 3,
+            outline_threshold: 0.25,
+            outline_color: Color::new(10, 10, 20),
+        }
+    }
+}
+
+// Latitud (en radianes) a partir de la cual empiezan los casquetes polares.
+// Se pueden modificar en tiempo de ejecución para simular las estaciones.
+pub struct IceCapParams {
+    earth_latitude: f32,
+    mars_latitude: f32,
+}
+
+impl Default for IceCapParams {
+    fn default() -> Self {
+        Self {
+            earth_latitude: 68.0_f32.to_radians(),
+            mars_latitude: 78.0_f32.to_radians(),
+        }
+    }
+}
+
+// Estado del control de cámara con el ratón: si está capturado y dónde estaba
+// el cursor en el frame anterior
+pub struct MouseLook {
+    captured: bool,
+    previous_position: Option<(f32, f32)>,
+}
+
+impl MouseLook {
+    fn new() -> Self {
+        MouseLook {
+            captured: false,
+            previous_position: None,
+        }
+    }
+
+    fn toggle(&mut self) {
+        self.captured = !self.captured;
+        // Evitar un salto de la cámara con el movimiento acumulado mientras estaba suelto
+        self.previous_position = None;
+    }
+}
+
+// Cuánto entró `position` en la franja del borde de un eje de `size` píxeles: de 0 al
+// comienzo de la franja a -1 en el borde inicial (izquierda/arriba) o a 1 en el final
+fn mouse_edge_push(position: f32, size: f32) -> f32 {
+    let band = (size * MOUSE_EDGE_BAND).max(1.0);
+    let last = size - 1.0;
+    if position < band {
+        -(1.0 - position / band).min(1.0)
+    } else if position > last - band {
+        ((position - (last - band)) / band).min(1.0)
+    } else {
+        0.0
+    }
+}
+
+// Estado de los controles que se alternan con una tecla
+pub struct InputState {
+    mouse_look: MouseLook,
+    // Con N se desactivan las colisiones para poder volar dentro de los cuerpos
+    collisions: bool,
+    // Rumbo de la cámara en el frame anterior, para medir qué tan rápido gira
+    previous_yaw: Option<f32>,
+}
+
+impl InputState {
+    fn new() -> Self {
+        InputState {
+            mouse_look: MouseLook::new(),
+            collisions: true,
+            previous_yaw: None,
+        }
+    }
 }
 
 pub struct Spaceship {
     model: Obj,
     scale: f32,
     offset: Vec3,
+    render_pass: RenderPass,
+    backface_culling: bool,
+}
+
+impl Spaceship {
+    // En primera persona la nave va pegada delante de la cámara (a `offset`, en el
+    // espacio de la cámara); con la cámara de persecución está en la pose de la cámara
+    fn position(&self, camera: &Camera) -> Vec3 {
+        if camera.chase.is_some() {
+            return camera.eye;
+        }
+        camera.eye
+            + camera.get_forward() * self.offset.z
+            + camera.get_up() * self.offset.y
+            + camera.get_right() * self.offset.x
+    }
+
+    // Salida de los motores, justo detrás de la cola del modelo (la nave mira hacia -Z)
+    fn engine_position(&self, camera: &Camera) -> Vec3 {
+        self.position(camera) - camera.get_forward() * (ENGINE_TAIL_OFFSET * self.scale)
+    }
 }
 
+// Distancia del centro de nave.obj a su cola, en unidades del modelo
+const ENGINE_TAIL_OFFSET: f32 = 6.4;
+
 pub struct TrailParticle {
     position: Vec3,
     color: u32,
     lifetime: f32,
+    // Vida con la que nació, para saber qué fracción de ella ya pasó
+    initial_lifetime: f32,
     size: f32,
 }
 
+impl TrailParticle {
+    fn new(position: Vec3, color: u32, lifetime: f32, size: f32) -> Self {
+        Self { position, color, lifetime, initial_lifetime: lifetime, size }
+    }
+
+    // Edad relativa: 0 recién creada (cabeza de la estela), 1 a punto de desaparecer (cola)
+    fn age(&self) -> f32 {
+        if self.initial_lifetime <= 0.0 {
+            return 1.0;
+        }
+        (1.0 - self.lifetime / self.initial_lifetime).clamp(0.0, 1.0)
+    }
+}
+
+// Color de una partícula según su edad: su propio color en la cabeza, que se oscurece
+// y se enfría hacia TRAIL_TAIL_COLOR en la cola. Conserva el alfa de `color`
+fn trail_gradient(color: u32, age: f32) -> u32 {
+    let head = Color::from_hex(color & 0x00FFFFFF);
+    let tail = head.lerp_linear(&TRAIL_TAIL_COLOR, 0.6) * TRAIL_TAIL_BRIGHTNESS;
+    (color & 0xFF000000) | head.lerp_linear(&tail, age).to_hex()
+}
+
+// Tinte de la luz del Sol sobre una partícula: cerca del Sol (a menos de
+// TRAIL_WARM_DISTANCE) toma un tono cálido, que cae como 1/distancia, y lejos de su luz
+// se enfría hacia el azul. Conserva el alfa de `color`
+fn sun_tint(color: u32, position: &Vec3, sun_position: &Vec3) -> u32 {
+    let distance = (position - sun_position).magnitude().max(f32::EPSILON);
+    let warmth = (TRAIL_WARM_DISTANCE / distance).min(1.0);
+    let tinted = Color::from_hex(color & 0x00FFFFFF)
+        .lerp_linear(&TRAIL_WARM_TINT, warmth * TRAIL_TINT_STRENGTH)
+        .lerp_linear(&TRAIL_COOL_TINT, (1.0 - warmth) * TRAIL_TINT_STRENGTH);
+    (color & 0xFF000000) | tinted.to_hex()
+}
+
 pub struct Trail {
     particles: Vec<TrailParticle>,
     max_particles: usize,
@@ -80,42 +423,262 @@ impl Trail {
         });
     }
 
-    fn add_particle(&mut self, position: Vec3, color: u32, is_moon: bool) {
-        if self.particles.len() >= self.max_particles {
-            self.particles.remove(0);
+    // Agrega partículas entre `previous` y `current`, separadas a lo sumo
+    // TRAIL_PARTICLE_SPACING, para que los cuerpos rápidos no dejen una estela a
+    // trazos. La primera partícula de la estela va solo en `current`
+    fn add_segment(&mut self, previous: Vec3, current: Vec3, color: u32, is_moon: bool) {
+        if self.particles.is_empty() {
+            self.add_particle(current, color, is_moon);
+            return;
+        }
+
+        let distance = (current - previous).magnitude();
+        let steps = ((distance / TRAIL_PARTICLE_SPACING).ceil() as usize).clamp(1, MAX_TRAIL_SEGMENT_PARTICLES);
+        for step in 1..=steps {
+            self.push_particle(previous.lerp(&current, step as f32 / steps as f32), color, is_moon);
+        }
+        self.evict_oldest();
+    }
+
+    // Suelta una partícula cada `interval` segundos mientras se llama cada frame (`dt`
+    // es el tiempo desde la llamada anterior), repartidas entre `previous` y `current`
+    // para que el escape no quede a trazos cuando la nave va rápido
+    fn emit(&mut self, previous: Vec3, current: Vec3, color: u32, lifetime: f32, interval: f32, dt: f32) {
+        self.spawn_timer += dt;
+        let count = (self.spawn_timer / interval).floor() as usize;
+        self.spawn_timer -= count as f32 * interval;
+        for index in 1..=count {
+            let position = previous.lerp(&current, index as f32 / count as f32);
+            self.particles.push(TrailParticle::new(position, color, lifetime, EXHAUST_PARTICLE_SIZE));
         }
+        self.evict_oldest();
+    }
+
+    fn add_particle(&mut self, position: Vec3, color: u32, is_moon: bool) {
+        self.push_particle(position, color, is_moon);
+        self.evict_oldest();
+    }
 
-        let lifetime = if is_moon { 2.0 } else { 200000.0 };
+    fn push_particle(&mut self, position: Vec3, color: u32, is_moon: bool) {
+        let lifetime = if is_moon { MOON_TRAIL_LIFETIME } else { TRAIL_LIFETIME };
         let size = if is_moon { 0.2 } else { 0.50000 };
 
-        self.particles.push(TrailParticle {
-            position,
-            color,
-            lifetime,
-            size,
-        });
+        self.particles.push(TrailParticle::new(position, color, lifetime, size));
+    }
+
+    // Descarta las partículas más antiguas que pasen de `max_particles`, de una vez
+    fn evict_oldest(&mut self) {
+        let excess = self.particles.len().saturating_sub(self.max_particles);
+        self.particles.drain(..excess);
     }
 }
 
-fn create_noise() -> FastNoiseLite {
-    create_cloud_noise() 
+// Segundos de simulación que dura una partícula de la estela de un planeta y de una
+// luna (corta, para que no tape la órbita del planeta), y color y brillo hacia los
+// que se apaga la cola
+const TRAIL_LIFETIME: f32 = 600.0;
+const MOON_TRAIL_LIFETIME: f32 = 2.0;
+const TRAIL_TAIL_COLOR: Color = Color::new(40, 30, 90);
+const TRAIL_TAIL_BRIGHTNESS: f32 = 0.35;
+
+// Tinte sutil de la luz del Sol sobre las estelas (ver `sun_tint`)
+const TRAIL_WARM_DISTANCE: f32 = 15.0;
+const TRAIL_WARM_TINT: Color = Color::new(255, 180, 110);
+const TRAIL_COOL_TINT: Color = Color::new(110, 150, 255);
+const TRAIL_TINT_STRENGTH: f32 = 0.25;
+
+// Distancia máxima entre dos partículas seguidas de una estela y tope de partículas
+// que se agregan en un solo paso (un salto muy grande queda con huecos)
+const TRAIL_PARTICLE_SPACING: f32 = 0.25;
+const MAX_TRAIL_SEGMENT_PARTICLES: usize = 16;
+
+// Escape de los motores: partículas cortas y blanco-azuladas, una cada
+// EXHAUST_SPAWN_INTERVAL segundos de tiempo real mientras se acelera hacia adelante
+const EXHAUST_MAX_PARTICLES: usize = 256;
+const EXHAUST_LIFETIME: f32 = 0.6;
+const EXHAUST_SPAWN_INTERVAL: f32 = 0.01;
+const EXHAUST_PARTICLE_SIZE: f32 = 0.05;
+const EXHAUST_COLOR: Color = Color::new(180, 215, 255);
+const EXHAUST_RIBBON_WIDTH: f32 = 0.04;
+
+// Resplandor de los motores: radio en unidades del mundo con el brillo al máximo,
+// y qué tan rápido (1/s) sigue a la velocidad de la nave
+const ENGINE_GLOW_RADIUS: f32 = 0.05;
+const ENGINE_GLOW_COLOR: Color = Color::new(200, 225, 255);
+const ENGINE_GLOW_RESPONSE: f32 = 8.0;
+
+const SCENE_PATH: &str = "assets/scene.toml";
+const KEY_BINDINGS_PATH: &str = "assets/keys.toml";
+
+// Modelos; si alguno falta se reemplaza por una forma generada en lugar de abortar
+const SPHERE_MODEL_PATH: &str = "assets/models/esfera.obj";
+const SPACESHIP_MODEL_PATH: &str = "assets/models/nave.obj";
+const REI_MODEL_PATH: &str = "assets/models/Rei_A-Pose_2.obj";
+// Medidas aproximadas de nave.obj, para que la caja de reemplazo ocupe lo mismo en pantalla
+const SPACESHIP_FALLBACK_HALF_EXTENTS: Vec3 = Vec3::new(3.8, 1.2, 4.4);
+
+// Muestras por lado de cada píxel (2 = 4 muestras) para suavizar los bordes
+const SUPERSAMPLE_SCALE: usize = 2;
+
+// Resolución (ancho, alto) de las texturas equirectangulares horneadas
+const BAKE_RESOLUTION: (usize, usize) = (1024, 512);
+
+// Paso fijo de la simulación, en segundos reales. Las órbitas, estelas y cometas
+// avanzan siempre en pasos de este tamaño, y las velocidades (de la cámara, las
+// órbitas) están expresadas por paso, así que el movimiento no depende de los FPS
+const FIXED_DT: f32 = 1.0 / 60.0;
+
+// Tiempo real máximo que se simula por frame, para no entrar en una espiral de
+// pasos atrasados si un frame tarda mucho (por ejemplo al mover la ventana)
+const MAX_FRAME_TIME: f32 = 0.25;
+
+// Cámara lenta: escala de tiempo objetivo mientras se mantiene la tecla y
+// fracción del camino que recorre la escala hacia su objetivo en cada frame
+const BULLET_TIME_SCALE: f32 = 0.2;
+const TIME_SCALE_EASE: f32 = 0.08;
+
+// Rango del multiplicador de velocidad de la simulación ([ y ])
+const MIN_TIME_MULTIPLIER: f32 = 1.0 / 16.0;
+const MAX_TIME_MULTIPLIER: f32 = 16.0;
+
+fn ease_time_scale(time_scale: f32, target: f32, ease: f32) -> f32 {
+    time_scale + (target - time_scale) * ease.clamp(0.0, 1.0)
+}
+
+// Niebla de profundidad: color hacia el que se desvanecen los cuerpos lejanos y
+// densidad por unidad de distancia (a 1/densidad unidades queda un 63% de niebla)
+const FOG_COLOR: Color = Color::new(6, 6, 16);
+const FOG_DENSITY: f32 = 0.002;
+
+// Bloom (tecla G): luminancia a partir de la que un píxel brilla, radio del
+// desenfoque en píxeles de la ventana y cuánto brillo se suma de vuelta
+const BLOOM_THRESHOLD: f32 = 0.75;
+const BLOOM_RADIUS: usize = 8;
+const BLOOM_INTENSITY: f32 = 0.9;
+
+// Cerca del agujero negro la pantalla se oscurece en las esquinas y los canales rojo y
+// azul se separan. La intensidad crece como 1/distancia a partir de BLACK_HOLE_EFFECT_REACH
+// radios del agujero y llega al máximo a la mitad de esa distancia
+const BLACK_HOLE_EFFECT_REACH: f32 = 12.0;
+const VIGNETTE_MAX_STRENGTH: f32 = 0.85;
+const CHROMATIC_ABERRATION_MAX_PIXELS: f32 = 6.0;
+
+// HDR (tecla F3): brillo del Sol por encima de 1.0 y exposición del tone mapping
+const SUN_HDR_EMISSION: f32 = 2.5;
+const HDR_EXPOSURE: f32 = 1.0;
+
+// Atenuación de la luz del Sol con 1/distancia²: a LIGHT_REFERENCE_DISTANCE el brillo
+// es el normal (1.0) y el factor se limita al rango indicado para que los planetas
+// interiores no se quemen y los exteriores no queden completamente negros
+const LIGHT_REFERENCE_DISTANCE: f32 = 30.0;
+const LIGHT_SCALE_RANGE: (f32, f32) = (0.25, 1.6);
+
+// Radio del anillo de Einstein del agujero negro, en radios aparentes del cuerpo
+const BLACK_HOLE_LENS_STRENGTH: f32 = 2.5;
+
+// Multiplicador de velocidad con Shift, factor de cada pulsación de +/- y
+// banking máximo al girar
+const BOOST_MULTIPLIER: f32 = 5.0;
+const SPEED_STEP: f32 = 1.25;
+const MAX_BANK_ANGLE: f32 = PI / 6.0;
+// Velocidad de giro con A/D (PI/128 por paso fijo), en radianes por segundo
+const KEY_YAW_RATE: f32 = PI / 128.0 / FIXED_DT;
+
+// Radianes de giro de la cámara por píxel de movimiento del ratón
+const MOUSE_SENSITIVITY: f32 = 0.003;
+// minifb no puede recentrar ni atrapar el cursor, así que al llegar al borde de la
+// ventana ya no hay movimiento que medir. En esa franja del borde (fracción del
+// tamaño de la ventana) la cámara sigue girando, más rápido cuanto más pegado esté
+// el cursor, hasta MOUSE_EDGE_TURN_RATE radianes por segundo
+const MOUSE_EDGE_BAND: f32 = 0.05;
+const MOUSE_EDGE_TURN_RATE: f32 = KEY_YAW_RATE;
+
+// Cinturón de asteroides entre Marte y Júpiter: cantidad, radios orbitales y
+// semilla fija para que sea el mismo en cada ejecución
+const ASTEROID_COUNT: usize = 300;
+const ASTEROID_BELT_RADII: (f32, f32) = (52.0, 60.0);
+const ASTEROID_BELT_SEED: u64 = 0x5EED_BE17;
+
+// Escala del núcleo de los cometas
+const COMET_SCALE: f32 = 0.15;
+
+// Rango de rapidez (unidades por frame) que cubre la rampa de color de las estelas
+const TRAIL_SPEED_MIN: f32 = 0.02;
+const TRAIL_SPEED_MAX: f32 = 0.04;
+
+// Mapa de calor: azul (lento) -> cian -> verde -> amarillo -> rojo (rápido)
+fn speed_to_color(speed: f32, min: f32, max: f32) -> Color {
+    let ramp = [
+        Color::new(0, 0, 255),
+        Color::new(0, 255, 255),
+        Color::new(0, 255, 0),
+        Color::new(255, 255, 0),
+        Color::new(255, 0, 0),
+    ];
+
+    let t = if max > min { ((speed - min) / (max - min)).clamp(0.0, 1.0) } else { 0.0 };
+    let scaled = t * (ramp.len() - 1) as f32;
+    let index = (scaled.floor() as usize).min(ramp.len() - 2);
+
+    ramp[index].lerp(&ramp[index + 1], scaled - index as f32)
+}
+
+// Pulso del Sol: misma frecuencia que el término `pulsate` de lava_shader
+const SUN_PULSE_FREQUENCY: f32 = 0.008;
+const SUN_PULSE_AMPLITUDE: f32 = 0.1;
+
+// Factor de brillo compartido por el Sol y la luz que reciben los planetas
+fn sun_pulse(time: u32) -> f32 {
+    1.0 + (time as f32 * SUN_PULSE_FREQUENCY).sin() * SUN_PULSE_AMPLITUDE
+}
+
+// Semilla del ruido de superficie para lo que no es un cuerpo de la escena
+// (cometas, asteroides) y base de la semilla por defecto de cada cuerpo
+const NOISE_SEED: u64 = 1337;
+
+// Generadores de ruido con nombre en los uniforms; cada shader usa el que le sirve
+// (lava para el Sol, terreno para los continentes, celdas para agrupar ciudades y
+// nubes para el resto)
+pub struct NoiseLayers {
+    cloud: FastNoiseLite,
+    ground: FastNoiseLite,
+    lava: FastNoiseLite,
+    cell: FastNoiseLite,
+}
+
+impl NoiseLayers {
+    fn new() -> Self {
+        Self {
+            cloud: create_cloud_noise(),
+            ground: create_ground_noise(),
+            lava: create_lava_noise(),
+            cell: create_cell_noise(),
+        }
+    }
+
+    // Misma semilla para todas las capas (la de cada cuerpo al dibujarlo)
+    fn set_seed(&mut self, seed: u64) {
+        for noise in [&mut self.cloud, &mut self.ground, &mut self.lava, &mut self.cell] {
+            noise.set_seed(Some(seed as i32));
+        }
+    }
 }
 
 fn create_cloud_noise() -> FastNoiseLite {
-    let mut noise = FastNoiseLite::with_seed(1337);
+    let mut noise = FastNoiseLite::with_seed(NOISE_SEED as i32);
     noise.set_noise_type(Some(NoiseType::OpenSimplex2));
     noise
 }
 
 fn create_cell_noise() -> FastNoiseLite {
-    let mut noise = FastNoiseLite::with_seed(1337);
+    let mut noise = FastNoiseLite::with_seed(NOISE_SEED as i32);
     noise.set_noise_type(Some(NoiseType::Cellular));
     noise.set_frequency(Some(0.1));
     noise
 }
 
 fn create_ground_noise() -> FastNoiseLite {
-    let mut noise = FastNoiseLite::with_seed(1337);
+    let mut noise = FastNoiseLite::with_seed(NOISE_SEED as i32);
     
    
     noise.set_noise_type(Some(NoiseType::Cellular)); 
@@ -203,284 +766,1494 @@ fn create_viewport_matrix(width: f32, height: f32) -> Mat4 {
     )
 }
 
+// Buffers de trabajo reutilizados entre llamadas a `render`: se vacían sin liberar
+// su memoria, así que tras el primer dibujo grande ya no hay más reservas
+pub struct RenderContext {
+    transformed_vertices: Vec<Vertex>,
+    triangles: Vec<[Vertex; 3]>,
+    fragments: Vec<Fragment>,
+    // Color sombreado de cada fragmento (canales en flotante, pueden pasar de 1.0
+    // con HDR), en el mismo orden que `fragments`
+    colors: Vec<Vec3>,
+    // Triángulos descartados por backface culling, triángulos rasterizados y
+    // fragmentos generados desde el último reinicio
+    culled_triangles: usize,
+    drawn_triangles: usize,
+    drawn_fragments: usize,
+    // Cómo se rasterizan los triángulos (F2 alterna entre los modos)
+    render_mode: RenderMode,
+}
+
+// Rellenar los triángulos, dibujar solo sus aristas o solo sus vértices.
+// Los dos últimos sirven para revisar la teselación (LOD) y el recorte
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RenderMode {
+    Filled,
+    Wireframe,
+    Points,
+}
+
+impl RenderMode {
+    fn next(self) -> Self {
+        match self {
+            RenderMode::Filled => RenderMode::Wireframe,
+            RenderMode::Wireframe => RenderMode::Points,
+            RenderMode::Points => RenderMode::Filled,
+        }
+    }
+}
+
+impl RenderContext {
+    // `fragment_capacity` reserva de antemano los buffers de fragmentos y colores (por
+    // ejemplo, un fragmento por muestra del framebuffer) para no crecerlos durante los primeros frames
+    fn new(fragment_capacity: usize) -> Self {
+        Self {
+            transformed_vertices: Vec::new(),
+            triangles: Vec::new(),
+            fragments: Vec::with_capacity(fragment_capacity),
+            colors: Vec::with_capacity(fragment_capacity),
+            culled_triangles: 0,
+            drawn_triangles: 0,
+            drawn_fragments: 0,
+            render_mode: RenderMode::Filled,
+        }
+    }
+
+    fn clear(&mut self) {
+        self.transformed_vertices.clear();
+        self.triangles.clear();
+        self.fragments.clear();
+        self.colors.clear();
+    }
+}
+
+// Fragmentos por tarea del pool de rayon; con bloques más chicos repartir cuesta
+// más que sombrear
+const SHADING_CHUNK_SIZE: usize = 1024;
+
+// Ejecuta el fragment shader sobre todos los fragmentos, repartidos en bloques
+// contiguos entre los hilos de rayon. Cada tarea escribe solo su bloque de `colors`
+fn shade_fragments(
+    fragments: &[Fragment],
+    colors: &mut [Vec3],
+    uniforms: &Uniforms,
+    planet_type: &PlanetType,
+    surface: Surface,
+) {
+    let gain = emission(planet_type, uniforms);
+    fragments.par_chunks(SHADING_CHUNK_SIZE)
+        .zip(colors.par_chunks_mut(SHADING_CHUNK_SIZE))
+        .for_each(|(fragments, colors)| {
+            for (fragment, color) in fragments.iter().zip(colors.iter_mut()) {
+                let shaded = fragment_shader(fragment, uniforms, planet_type, surface).to_float() * gain;
+                *color = apply_fog(shaded, fragment, uniforms);
+            }
+        });
+}
+
 fn render(
     framebuffer: &mut Framebuffer,
+    context: &mut RenderContext,
     uniforms: &Uniforms,
-    vertex_array: &[Vertex],
-    planet_type: &PlanetType
+    mesh: &IndexedMesh,
+    planet_type: &PlanetType,
+    render_pass: RenderPass,
+    surface: Surface,
 ) {
-    // Vertex Shader Stage
-    let mut transformed_vertices = Vec::with_capacity(vertex_array.len());
-    for vertex in vertex_array {
+    context.clear();
+
+    // Vertex Shader Stage: each unique vertex is transformed once into the cache
+    for vertex in &mesh.vertices {
         let transformed = vertex_shader(vertex, uniforms);
-        transformed_vertices.push(transformed);
+        context.transformed_vertices.push(transformed);
     }
 
-    // Primitive Assembly Stage
-    let mut triangles = Vec::new();
-    for i in (0..transformed_vertices.len()).step_by(3) {
-        if i + 2 < transformed_vertices.len() {
-            triangles.push([
-                transformed_vertices[i].clone(),
-                transformed_vertices[i + 1].clone(),
-                transformed_vertices[i + 2].clone(),
-            ]);
+    // Primitive Assembly Stage: triangles are assembled from the cache by index,
+    // clipped against the near plane in clip space and perspective-divided
+    let transformed_vertices = &context.transformed_vertices;
+    for indices in mesh.indices.chunks_exact(3) {
+        let triangle = [
+            transformed_vertices[indices[0] as usize].clone(),
+            transformed_vertices[indices[1] as usize].clone(),
+            transformed_vertices[indices[2] as usize].clone(),
+        ];
+        for mut clipped in clip_triangle_near(triangle) {
+            for vertex in &mut clipped {
+                project_vertex(vertex, uniforms);
+            }
+
+            // Backface culling: el área con signo en pantalla indica hacia dónde mira
+            if uniforms.cull_backfaces {
+                let [a, b, c] = &clipped;
+                if signed_area(&a.transformed_position, &b.transformed_position, &c.transformed_position) <= 0.0 {
+                    context.culled_triangles += 1;
+                    continue;
+                }
+            }
+
+            context.triangles.push(clipped);
         }
     }
 
     // Rasterization Stage
-    let mut fragments = Vec::new();
-    for tri in &triangles {
-        fragments.extend(triangle(&tri[0], &tri[1], &tri[2]));
+    let viewport = (framebuffer.width, framebuffer.height);
+    for tri in &context.triangles {
+        match context.render_mode {
+            RenderMode::Filled => triangle(&tri[0], &tri[1], &tri[2], viewport, &mut context.fragments),
+            RenderMode::Wireframe => wireframe(&tri[0], &tri[1], &tri[2], &mut context.fragments),
+            RenderMode::Points => context.fragments.extend(tri.iter().filter_map(point)),
+        }
     }
+    context.drawn_triangles += context.triangles.len();
+    context.drawn_fragments += context.fragments.len();
 
-    // Fragment Shader Stage
-    for fragment in fragments {
+    // Fragment Shader Stage: fragments already hidden in the z-buffer are dropped,
+    // the rest are shaded in parallel, and the z-test/write below stays serial and in
+    // order, so the result is identical to shading one fragment at a time
+    context.fragments.retain(|fragment| {
         let x = fragment.position.x as usize;
         let y = fragment.position.y as usize;
-        if x < framebuffer.width && y < framebuffer.height {
-            let depth = if matches!(planet_type, PlanetType::Spaceship) {
-                // Forzar que la nave siempre esté al frente
-                -1.0
-            } else {
-                fragment.depth
-            };
+        x < framebuffer.width && y < framebuffer.height && framebuffer.should_draw(x, y, fragment.depth)
+    });
+    context.colors.resize(context.fragments.len(), Vec3::zeros());
+    shade_fragments(&context.fragments, &mut context.colors, uniforms, planet_type, surface);
+
+    for (fragment, rgb) in context.fragments.iter().zip(&context.colors) {
+        let x = fragment.position.x as usize;
+        let y = fragment.position.y as usize;
+        let depth = fragment.depth;
+
+        if framebuffer.should_draw(x, y, depth) {
+            let color = Color::from_float(rgb.x, rgb.y, rgb.z).to_hex();
+            // Los cuerpos que se desvanecen y los bordes de las líneas suavizadas se mezclan
+            let alpha = uniforms.alpha * fragment.coverage;
+            if alpha < 1.0 {
+                let alpha = (alpha.clamp(0.0, 1.0) * 255.0) as u32;
+                framebuffer.blend_point(x, y, depth, color | (alpha << 24));
+                continue;
+            }
+
+            // Con HDR se guarda el valor sin recortar; el tone mapping lo convierte al final
+            if framebuffer.hdr_enabled() {
+                framebuffer.point_hdr(x, y, depth, [rgb.x, rgb.y, rgb.z], render_pass.writes_depth());
+                continue;
+            }
 
-            if framebuffer.should_draw(x, y, depth) {
-                let shaded_color = fragment_shader(&fragment, &uniforms, planet_type);
-                let color = shaded_color.to_hex();
-                framebuffer.set_current_color(color);
+            framebuffer.set_current_color(color);
+            if render_pass.writes_depth() {
                 framebuffer.point(x, y, depth);
+            } else {
+                framebuffer.point_without_depth_write(x, y, depth);
             }
         }
     }
 }
 
-fn calculate_detail_level(distance: f32) -> usize {
-    if distance < 5.0 {
-        0  // Máximo detalle
-    } else if distance < 20.0 {
-        1  // Detalle medio
-    } else {
-        2  // Bajo detalle
-    }
+// Todo lo necesario para dibujar la escena que no forma parte de la simulación:
+// uniforms, mallas, cielo, superficies horneadas y buffers de trabajo. `main` lo usa
+// en cada frame y `render_frame` lo usa para dibujar una sola imagen sin ventana
+pub struct Renderer {
+    uniforms: Uniforms,
+    context: RenderContext,
+    skybox: Skybox,
+    lod_meshes: Vec<IndexedMesh>,
+    lod_table: LodTable,
+    draw_distance: DrawDistance,
+    rei_mesh: IndexedMesh,
+    rei_materials: bool,
+    spaceship: Spaceship,
+    spaceship_parts: Vec<IndexedMesh>,
+    wormhole_mesh: IndexedMesh,
+    // Superficie precalculada de cada cuerpo (mismo orden que los cuerpos), ver --bake-surfaces
+    baked_surfaces: Vec<Option<Texture>>,
+    trail_style: TrailStyle,
+    // Dibujar la órbita de cada cuerpo (tecla O)
+    show_orbits: bool,
+    // Brillo del resplandor de los motores, de 0 (apagados) a 1 (a toda velocidad)
+    engine_glow: f32,
 }
 
-fn get_lod_mesh(vertex_arrays: &[Vertex], detail_level: usize) -> &[Vertex] {
-    // Por ahora, retornamos el mismo mesh para todos los niveles
-    vertex_arrays
+// Estado de la simulación que se dibuja en un frame
+pub struct World<'a> {
+    bodies: &'a [CelestialBody],
+    wormholes: &'a [Wormhole],
+    asteroids: &'a [Asteroid],
+    comets: &'a [Comet],
+    // Partículas del escape de la nave, de la más vieja a la más nueva
+    exhaust: &'a [TrailParticle],
 }
 
-struct Frustum {
-    fov: f32,
-    near: f32,
-    far: f32,
-    aspect: f32,
-}
+impl Renderer {
+    // Carga los modelos y prepara los uniforms para dibujar en `framebuffer`
+    fn new(framebuffer: &Framebuffer, skybox: Skybox) -> Self {
+        let obj = Obj::with_consistent_winding(SPHERE_MODEL_PATH).unwrap_or_else(|error| {
+            eprintln!("No se pudo cargar {} ({}), se usa una esfera generada", SPHERE_MODEL_PATH, error);
+            Obj::fallback_sphere()
+        });
+        // Un mesh por nivel de LOD: el modelo completo y dos versiones simplificadas
+        let lod_meshes: Vec<IndexedMesh> = std::iter::once(obj.get_indexed_mesh())
+            .chain(LOD_CELL_SIZES.iter().map(|&cell_size| obj.decimated(cell_size).get_indexed_mesh()))
+            .collect();
+
+        // Cargar el modelo de la nave (asegúrate de tener un modelo .obj de una nave)
+        let spaceship = Spaceship {
+            model: Obj::load(SPACESHIP_MODEL_PATH).unwrap_or_else(|error| {
+                eprintln!("No se pudo cargar {} ({}), la nave se dibuja como una caja", SPACESHIP_MODEL_PATH, error);
+                Obj::fallback_box(SPACESHIP_FALLBACK_HALF_EXTENTS)
+            }),
+            scale: 0.02,
+            offset: Vec3::new(0.0, -0.1, -1.0),
+            render_pass: RenderPass::Overlay,
+            // La nave tiene superficies finas que deben verse por ambas caras
+            backface_culling: false,
+        };
+        // Una lista de vértices por grupo del modelo, para colorear cada parte por separado
+        let spaceship_parts: Vec<IndexedMesh> = spaceship.model.group_names()
+            .iter()
+            .map(|name| spaceship.model.get_indexed_mesh_for_group(name))
+            .collect();
+
+        // Cargar el modelo de Rei
+        let rei_model = Obj::load(REI_MODEL_PATH).unwrap_or_else(|error| {
+            eprintln!("No se pudo cargar {} ({}), Rei se dibuja como una esfera", REI_MODEL_PATH, error);
+            Obj::fallback_sphere()
+        });
+
+        // La proyección usa la proporción de la imagen y el viewport la resolución de
+        // muestreo del framebuffer, que es donde se rasteriza la escena
+        let projection_matrix = create_perspective_matrix(framebuffer.width as f32, framebuffer.height as f32);
+        let viewport_matrix = create_viewport_matrix(framebuffer.width as f32, framebuffer.height as f32);
+
+        let uniforms = Uniforms::new(projection_matrix, viewport_matrix);
 
-impl Frustum {
-    fn new(fov: f32, near: f32, far: f32, aspect: f32) -> Self {
         Self {
-            fov,
-            near,
-            far,
-            aspect,
+            uniforms,
+            context: RenderContext::new(framebuffer.width * framebuffer.height),
+            skybox,
+            lod_meshes,
+            lod_table: LodTable::default(),
+            draw_distance: DrawDistance::default(),
+            rei_mesh: rei_model.get_indexed_mesh(),
+            rei_materials: rei_model.has_materials(),
+            spaceship,
+            spaceship_parts,
+            wormhole_mesh: Obj::annulus(0.8, 1.0, 48).get_indexed_mesh(),
+            baked_surfaces: Vec::new(),
+            trail_style: TrailStyle::Points,
+            show_orbits: false,
+            engine_glow: 0.0,
         }
     }
 
-    fn is_visible(&self, camera_pos: &Vec3, camera_forward: &Vec3, object_pos: &Vec3, object_radius: f32) -> bool {
-        // Verificar distancia
-        let to_object = object_pos - camera_pos;
-        let distance = to_object.magnitude();
-        
-        // Si está muy cerca o muy lejos, no renderizar
-        if distance < self.near || distance > self.far {
-            return false;
-        }
-
-        // Verificar si está dentro del campo de visión
-        let direction = to_object.normalize();
-        let angle = camera_forward.dot(&direction).acos();
-        
-        // Convertir FOV a radianes y comparar
-        let half_fov = (self.fov * std::f32::consts::PI / 180.0) / 2.0;
-        
-        // Añadir el radio del objeto al ángulo de visión
-        let apparent_angle = half_fov + (object_radius / distance).asin();
-        
-        angle <= apparent_angle
+    // Reemplaza los niveles de LOD de esfera.obj por esferas generadas, una por nivel
+    // con la teselación de `GENERATED_SPHERE_LODS`
+    fn use_generated_spheres(&mut self) {
+        self.lod_meshes = GENERATED_SPHERE_LODS.iter()
+            .map(|&(rings, sectors)| Obj::uv_sphere(rings, sectors).get_indexed_mesh())
+            .collect();
     }
-}
 
-fn check_collision(position: &Vec3, celestial_bodies: &[CelestialBody]) -> bool {
-    for body in celestial_bodies {
-        let distance = (position - body.position).magnitude();
-        let collision_radius = body.scale * 2.0;
-        
-        if distance < collision_radius {
-            return true; // Hay colisión
-        }
+    // Hornea una sola vez la superficie de los cuerpos estáticos en una textura,
+    // en lugar de evaluar el ruido en cada fragmento
+    fn bake_surfaces(&mut self, bodies: &[CelestialBody]) {
+        self.baked_surfaces = bodies.iter()
+            .map(|body| {
+                self.uniforms.noise.set_seed(body.seed);
+                static_surface(&body.shader_type).map(|surface| bake_surface(surface, &self.uniforms, BAKE_RESOLUTION))
+            })
+            .collect();
+        self.uniforms.noise.set_seed(NOISE_SEED);
     }
-    false // No hay colisión
-}
 
-fn handle_input(window: &Window, camera: &mut Camera, celestial_bodies: &[CelestialBody]) {
-    let movement_speed = 0.2;
-    let rotation_speed = PI/128.0;
-    let bank_angle = PI/16.0;
-
-    // Manejar la vista aérea
-    if window.is_key_down(Key::B) {
-        if !camera.bird_eye_active {
-            // Guardar el estado actual antes de cambiar a vista aérea
-            camera.previous_state = Some((
-                camera.eye,
-                camera.center,
-                camera.pitch,
-                camera.yaw,
-                camera.roll
-            ));
-            set_bird_eye_view(camera, celestial_bodies);
-            camera.bird_eye_active = true;
-        }
-    } else if camera.bird_eye_active {
-        // Restaurar la posición anterior cuando se suelta B
-        if let Some((prev_eye, prev_center, prev_pitch, prev_yaw, prev_roll)) = camera.previous_state {
-            camera.eye = prev_eye;
-            camera.center = prev_center;
-            camera.pitch = prev_pitch;
-            camera.yaw = prev_yaw;
-            camera.roll = prev_roll;
-            camera.previous_state = None;
-            camera.bird_eye_active = false;
+    // Dibuja todos los pases de la escena vista desde `camera` en el framebuffer (que
+    // ya debe estar limpio). No hace el tone mapping ni promedia las muestras
+    fn draw_scene(&mut self, framebuffer: &mut Framebuffer, camera: &Camera, world: &World, simulation_time: f32) {
+        let time = simulation_time as u32;
+        let uniforms = &mut self.uniforms;
+        let context = &mut self.context;
+
+        // El Sol y la Luna de la Tierra se toman de los cuerpos de este frame
+        let sun = world.bodies.iter().find(|body| matches!(body.shader_type, PlanetType::Sun));
+        let sun_position = sun.map_or(Vec3::new(0.0, 0.0, 0.0), |sun| sun.position);
+        uniforms.sun_position = sun_position;
+        uniforms.sun_radius = sun.map_or(0.0, |sun| sun.scale);
+        let moon = earth_moon(world.bodies);
+        uniforms.moon_position = moon.map_or(Vec3::new(0.0, 0.0, 0.0), |moon| moon.position);
+        uniforms.moon_radius = moon.map_or(0.0, |moon| moon.scale);
+
+        uniforms.sun_pulse = sun_pulse(time);
+        uniforms.time = time;
+        uniforms.camera_position = camera.view_eye();
+        uniforms.view_matrix = create_view_matrix(camera.view_eye(), camera.view_center(), camera.up);
+        let frustum = Frustum::from_matrix(&(uniforms.projection_matrix * uniforms.view_matrix));
+
+        for pass in RenderPass::ORDER {
+            match pass {
+                RenderPass::Skybox => {
+                    // Lente gravitacional alrededor del agujero negro
+                    let lens = world.bodies.iter()
+                        .find(|body| matches!(body.shader_type, PlanetType::BlackHole))
+                        .and_then(|black_hole| GravitationalLens::around(
+                            framebuffer,
+                            uniforms,
+                            black_hole.position,
+                            black_hole.scale,
+                            BLACK_HOLE_LENS_STRENGTH,
+                        ));
+                    self.skybox.render(framebuffer, uniforms, camera.view_eye(), lens.as_ref(), camera.warp_speed());
+                },
+                RenderPass::Opaque | RenderPass::Transparent => {
+                    // Cuerpos del pase actual, con culling
+                    for (index, body, fade) in bodies_in_pass(world.bodies, pass, camera.view_eye(), &self.draw_distance) {
+                        let distance = (body.position - camera.view_eye()).magnitude();
+                        let ring_extent = body.rings.as_ref().map_or(0.0, |rings| rings.outer_radius);
+                        let apparent_radius = body.scale * ring_extent.max(2.0);
+
+                        if frustum.sphere_visible(&body.position, apparent_radius) {
+                            uniforms.model_matrix = body.model_matrix(simulation_time);
+                            uniforms.albedo = body.albedo;
+                            uniforms.light_scale = light_scale(&body.position, &sun_position);
+                            uniforms.rings = body.rings.as_ref().map(|rings| {
+                                let ring_matrix = body.tilt_matrix();
+                                RingGeometry {
+                                    center: body.position,
+                                    normal: (ring_matrix * Vec4::new(0.0, 1.0, 0.0, 0.0)).xyz().normalize(),
+                                    inner_radius: rings.inner_radius * body.scale,
+                                    outer_radius: rings.outer_radius * body.scale,
+                                    planet_radius: body.scale,
+                                }
+                            });
+                            uniforms.alpha = fade;
+                            uniforms.cull_backfaces = body.backface_culling;
+                            uniforms.vertex_colors = matches!(body.shader_type, PlanetType::Rei) && self.rei_materials;
+
+                            // Usar los vértices correspondientes según el tipo
+                            let detail_level = calculate_detail_level(distance, body.scale, &self.lod_table);
+                            let vertices = match body.shader_type {
+                                PlanetType::Rei => &self.rei_mesh,
+                                _ => get_lod_mesh(&self.lod_meshes, detail_level),
+                            };
+                            let surface = Surface {
+                                baked: self.baked_surfaces.get(index).and_then(Option::as_ref),
+                                map: body.texture.as_ref().map(|texture| (texture, body.texture_blend)),
+                            };
+
+                            uniforms.noise.set_seed(body.seed);
+                            render(framebuffer, context, uniforms, vertices, &body.shader_type, pass, surface);
+
+                            // Los anillos siguen la inclinación del cuerpo, pero no su giro
+                            if let Some(rings) = &body.rings {
+                                uniforms.model_matrix = body.tilt_matrix();
+                                // El disco es de una sola capa y se ve desde ambos lados
+                                uniforms.cull_backfaces = false;
+                                render(framebuffer, context, uniforms, &rings.mesh, &PlanetType::Rings, pass, Surface::default());
+                            }
+                        }
+                    }
+                    uniforms.vertex_colors = false;
+                    uniforms.noise.set_seed(NOISE_SEED);
+
+                    // Anillos de entrada y salida de los agujeros de gusano
+                    if pass == RenderPass::Opaque {
+                        for wormhole in world.wormholes {
+                            for ring_position in [wormhole.entry, wormhole.exit] {
+                                if frustum.sphere_visible(&ring_position, wormhole.radius) {
+                                    uniforms.model_matrix = create_model_matrix(ring_position, wormhole.radius, wormhole.ring_rotation());
+                                    uniforms.albedo = 1.0;
+                                    uniforms.light_scale = 1.0;
+                                    uniforms.alpha = 1.0;
+                                    uniforms.cull_backfaces = false;
+                                    render(framebuffer, context, uniforms, &self.wormhole_mesh, &PlanetType::Wormhole, pass, Surface::default());
+                                }
+                            }
+                        }
+                    }
+
+                    // Asteroides del cinturón, con culling y LOD como los cuerpos
+                    if pass == RenderPass::Opaque {
+                        uniforms.alpha = 1.0;
+                        uniforms.cull_backfaces = true;
+                        for asteroid in world.asteroids {
+                            if !frustum.sphere_visible(&asteroid.position, asteroid.scale) {
+                                continue;
+                            }
+                            let distance = (asteroid.position - camera.view_eye()).magnitude();
+                            let detail_level = calculate_detail_level(distance, asteroid.scale, &self.lod_table);
+
+                            uniforms.model_matrix = create_model_matrix(asteroid.position, asteroid.scale, asteroid.rotation);
+                            uniforms.albedo = asteroid.albedo;
+                            uniforms.light_scale = light_scale(&asteroid.position, &sun_position);
+                            render(framebuffer, context, uniforms, get_lod_mesh(&self.lod_meshes, detail_level), &PlanetType::Asteroid, pass, Surface::default());
+                        }
+                    }
+
+                    // Núcleos de los cometas de la lluvia
+                    if pass == RenderPass::Opaque {
+                        for comet in world.comets {
+                            if frustum.sphere_visible(&comet.position, COMET_SCALE) {
+                                uniforms.model_matrix = create_model_matrix(comet.position, COMET_SCALE, Vec3::new(0.0, 0.0, 0.0));
+                                uniforms.albedo = 1.0;
+                                uniforms.light_scale = light_scale(&comet.position, &sun_position);
+                                uniforms.alpha = 1.0;
+                                uniforms.cull_backfaces = true;
+                                render(framebuffer, context, uniforms, get_lod_mesh(&self.lod_meshes, 0), &PlanetType::Comet, pass, Surface::default());
+                            }
+                        }
+                    }
+
+                    // Las estelas y las órbitas son transparentes: van después de todos los cuerpos opacos
+                    if pass == RenderPass::Transparent {
+                        if self.show_orbits {
+                            for body in world.bodies {
+                                let (center, parent_inclination) = match body.parent_index {
+                                    Some(parent) => (world.bodies[parent].position, world.bodies[parent].inclination),
+                                    None => (sun_position, 0.0),
+                                };
+                                render_orbit(framebuffer, uniforms, &mut context.fragments, body, center, parent_inclination);
+                            }
+                        }
+                        for body in world.bodies {
+                            match self.trail_style {
+                                TrailStyle::Points => {
+                                    for particle in &body.trail.particles {
+                                        render_trail(framebuffer, uniforms, particle);
+                                    }
+                                },
+                                TrailStyle::Ribbon => {
+                                    render_trail_ribbon(framebuffer, uniforms, &body.trail.particles, RIBBON_WIDTH, RIBBON_TAPER);
+                                },
+                            }
+                        }
+                        let comet_tails = world.comets.iter().map(|comet| &comet.tail)
+                            .chain(world.bodies.iter().filter_map(|body| body.comet_tail.as_ref()));
+                        for tail in comet_tails {
+                            for particle in tail.particles() {
+                                render_trail(framebuffer, uniforms, particle);
+                            }
+                        }
+                        // El escape de la nave se dibuja con el mismo estilo que las estelas
+                        match self.trail_style {
+                            TrailStyle::Points => {
+                                for particle in world.exhaust {
+                                    render_trail(framebuffer, uniforms, particle);
+                                }
+                            },
+                            TrailStyle::Ribbon => {
+                                render_trail_ribbon(framebuffer, uniforms, world.exhaust, EXHAUST_RIBBON_WIDTH, RIBBON_TAPER);
+                            },
+                        }
+                    }
+                },
+                RenderPass::Overlay => {
+                    // Solo renderizar la nave si no estamos en vista aérea.
+                    // En primera persona va pegada delante de la cámara con un z-buffer aparte
+                    // para quedar siempre al frente sin tocar la profundidad de la escena; con la
+                    // cámara de persecución está en su propia posición (la de la cámara) y los
+                    // cuerpos la pueden tapar
+                    if !camera.bird_eye_active {
+                        let spaceship = &self.spaceship;
+                        let first_person = camera.chase.is_none();
+                        if first_person {
+                            framebuffer.begin_overlay_depth();
+                        }
+                        let ship_position = spaceship.position(camera);
+
+                        // La nave mira hacia -Z en su espacio local, igual que la cámara
+                        uniforms.model_matrix = translation(&ship_position)
+                            * quat_to_mat4(&camera.orientation)
+                            * scaling(&Vec3::new(spaceship.scale, spaceship.scale, spaceship.scale));
+                        uniforms.albedo = 1.0;
+                        uniforms.light_scale = 1.0;
+                        uniforms.alpha = 1.0;
+                        uniforms.cull_backfaces = spaceship.backface_culling;
+                        uniforms.vertex_colors = spaceship.model.has_materials();
+
+                        for (group, part) in self.spaceship_parts.iter().enumerate() {
+                            uniforms.mesh_group = group;
+                            render(framebuffer, context, uniforms, part, &PlanetType::Spaceship, spaceship.render_pass, Surface::default());
+                        }
+                        uniforms.mesh_group = 0;
+                        uniforms.vertex_colors = false;
+
+                        if self.engine_glow > 0.0 {
+                            render_engine_glow(framebuffer, uniforms, spaceship.engine_position(camera), self.engine_glow);
+                        }
+
+                        if first_person {
+                            framebuffer.end_overlay_depth();
+                        }
+                    }
+                },
+            }
         }
     }
+}
 
-    // Solo procesar otros controles si no estamos en vista aérea
-    if !camera.bird_eye_active {
-        // Añadir warping a planetas específicos con KeyRepeat::No
-        if window.is_key_pressed(Key::Key1, KeyRepeat::No) {
-            warp_to_planet(camera, &celestial_bodies[0], 8.0); // Sol (más lejos por ser más grande)
-        } else if window.is_key_pressed(Key::Key2, KeyRepeat::No) {
-            warp_to_planet(camera, &celestial_bodies[3], 3.0); // Tierra
-        } else if window.is_key_pressed(Key::Key3, KeyRepeat::No) {
-            warp_to_planet(camera, &celestial_bodies[5], 5.0); // Júpiter (más lejos por ser grande)
-        } else if window.is_key_pressed(Key::Key4, KeyRepeat::No) {
-            warp_to_planet(camera, &celestial_bodies[10], 12.0); // Agujero Negro (mucho más lejos)
-        }
+// La Luna de la Tierra (la que tiene a la Tierra como padre), que proyecta su sombra sobre ella
+fn earth_moon(bodies: &[CelestialBody]) -> Option<&CelestialBody> {
+    bodies.iter().find(|body| {
+        matches!(body.shader_type, PlanetType::Moon)
+            && body.parent_index.and_then(|parent| bodies.get(parent))
+                .is_some_and(|parent| matches!(parent.shader_type, PlanetType::Earth))
+    })
+}
 
-        // Calcular la nueva posición antes de aplicarla
-        let mut new_position = camera.eye;
+// Dibuja un solo frame de `bodies` vistos desde `camera` sin abrir ninguna ventana, en
+// un framebuffer nuevo de `width` x `height`, y devuelve los píxeles (0xRRGGBB, fila por
+// fila). Sirve para generar miniaturas (--thumbnail) o comprobar el render en pruebas
+pub fn render_frame(width: usize, height: usize, camera: &Camera, bodies: &[CelestialBody], time: u32) -> Vec<u32> {
+    let mut framebuffer = Framebuffer::new_supersampled(width, height, SUPERSAMPLE_SCALE, DepthFormat::Float32);
+    framebuffer.set_background_color(0x000000);
+    framebuffer.clear();
 
-        // Movimiento lateral con rotación
-        if window.is_key_down(Key::A) {
-            camera.rotate_yaw(-rotation_speed);
-            camera.set_roll(bank_angle);
-        } else if window.is_key_down(Key::D) {
-            camera.rotate_yaw(rotation_speed);
-            camera.set_roll(-bank_angle);
-        } else {
-            camera.set_roll(camera.roll * 0.9);
-        }
+    let mut renderer = Renderer::new(&framebuffer, Skybox::default());
+    let world = World { bodies, wormholes: &[], asteroids: &[], comets: &[], exhaust: &[] };
+    renderer.draw_scene(&mut framebuffer, camera, &world, time as f32);
 
-        // Control de pitch
-        if window.is_key_down(Key::Up) {
-            camera.rotate_pitch(rotation_speed);
-        }
-        if window.is_key_down(Key::Down) {
-            camera.rotate_pitch(-rotation_speed);
-        }
+    framebuffer.tone_map(HDR_EXPOSURE);
+    framebuffer.resolve();
+    framebuffer.resolved
+}
 
-        // Calcular el movimiento deseado
-        let mut movement = Vec3::new(0.0, 0.0, 0.0);
-        
-        if window.is_key_down(Key::W) {
-            movement += camera.get_forward() * movement_speed;
-        }
-        if window.is_key_down(Key::S) {
-            movement += camera.get_forward() * (-movement_speed * 0.5);
-        }
-        if window.is_key_down(Key::Q) {
-            movement += camera.get_up() * (movement_speed * 0.7);
-        }
-        if window.is_key_down(Key::E) {
-            movement += camera.get_up() * (-movement_speed * 0.7);
-        }
+// Distancia máxima de dibujo; en la banda final los cuerpos se desvanecen
+// en lugar de desaparecer de golpe
+struct DrawDistance {
+    distance: f32,
+    fade_band: f32,
+}
 
-        // Verificar colisiones antes de aplicar el movimiento
-        new_position += movement;
-        
-        if !check_collision(&new_position, celestial_bodies) {
-            camera.eye = new_position;
-            camera.center = camera.eye + camera.get_forward();
+impl Default for DrawDistance {
+    fn default() -> Self {
+        Self {
+            distance: 600.0,
+            fade_band: 150.0,
         }
     }
 }
 
-fn warp_to_planet(camera: &mut Camera, body: &CelestialBody, distance: f32) {
-    // Calcular la posición relativa considerando el movimiento orbital
-    let offset = Vec3::new(0.0, 0.0, distance + body.scale);
-    
-    // Usar la posición actual del planeta
-    let target_pos = body.position + offset;
-    
-    // Calcular la dirección mirando hacia el planeta
-    let target_direction = (body.position - target_pos).normalize();
-    
-    // Iniciar el warp con la posición y dirección calculadas
-    camera.start_warp(target_pos, target_direction);
+// Cuerpos que se dibujan en `pass`, con su índice y su opacidad, en el orden de la
+// lista. Los que se desvanecen por la distancia se dibujan como transparentes
+fn bodies_in_pass<'a>(
+    bodies: &'a [CelestialBody],
+    pass: RenderPass,
+    eye: Vec3,
+    draw_distance: &'a DrawDistance,
+) -> impl Iterator<Item = (usize, &'a CelestialBody, f32)> + 'a {
+    bodies.iter().enumerate().filter_map(move |(index, body)| {
+        let fade = draw_distance_fade((body.position - eye).magnitude(), draw_distance)?;
+        let body_pass = if fade < 1.0 { RenderPass::Transparent } else { body.render_pass };
+        (body_pass == pass).then_some((index, body, fade))
+    })
 }
 
-fn render_trail(
-    framebuffer: &mut Framebuffer,
-    uniforms: &Uniforms,
-    particle: &TrailParticle,
-) {
-    let model_matrix = create_model_matrix(
-        particle.position,
-        particle.size,
-        Vec3::new(0.0, 0.0, 0.0)
-    );
-
-    let position_clip = uniforms.projection_matrix * uniforms.view_matrix * model_matrix * Vec4::new(0.0, 0.0, 0.0, 1.0);
-    
-    if position_clip.w <= 0.0 {
-        return;
+// Opacidad de un cuerpo a cierta distancia de la cámara, o None si queda fuera
+fn draw_distance_fade(distance: f32, draw_distance: &DrawDistance) -> Option<f32> {
+    if distance >= draw_distance.distance {
+        return None;
     }
 
-    let position_ndc = Vec3::new(
-        position_clip.x / position_clip.w,
-        position_clip.y / position_clip.w,
-        position_clip.z / position_clip.w,
-    );
+    let fade_start = draw_distance.distance - draw_distance.fade_band;
+    if distance <= fade_start || draw_distance.fade_band <= 0.0 {
+        Some(1.0)
+    } else {
+        Some(1.0 - (distance - fade_start) / draw_distance.fade_band)
+    }
+}
 
-    let position_screen = uniforms.viewport_matrix * Vec4::new(
-        position_ndc.x,
-        position_ndc.y,
-        position_ndc.z,
-        1.0,
-    );
+// Umbrales de LOD expresados en radios del cuerpo (distancia / escala),
+// ordenados de menor a mayor. Cada umbral superado baja un nivel de detalle.
+struct LodTable {
+    breakpoints: Vec<f32>,
+}
 
-    let x = position_screen.x as usize;
-    let y = position_screen.y as usize;
+impl LodTable {
+    fn new(breakpoints: Vec<f32>) -> Self {
+        Self { breakpoints }
+    }
+}
 
-    if x < framebuffer.width && y < framebuffer.height {
-        let alpha = (particle.lifetime * 255.0) as u32;
-        let color = (particle.color & 0x00FFFFFF) | (alpha << 24);
-        
-        framebuffer.set_current_color(color);
-        framebuffer.point(x, y, position_screen.z);
+impl Default for LodTable {
+    fn default() -> Self {
+        Self::new(vec![5.0, 20.0])
     }
 }
 
-fn set_bird_eye_view(camera: &mut Camera, celestial_bodies: &[CelestialBody]) {
-    // Encontrar el sol (primer cuerpo celeste en nuestro array)
-    let sun_position = celestial_bodies[0].position;
+fn calculate_detail_level(distance: f32, scale: f32, lod_table: &LodTable) -> usize {
+    // Un cuerpo pequeño pierde detalle antes que uno grande a la misma distancia
+    let relative_distance = distance / scale.max(f32::EPSILON);
+
+    lod_table.breakpoints
+        .iter()
+        .take_while(|&&breakpoint| relative_distance >= breakpoint)
+        .count() // 0 = máximo detalle
+}
+
+// Lado de la rejilla de simplificación de cada nivel de LOD después del 0,
+// en unidades del modelo (la esfera tiene radio 1)
+const LOD_CELL_SIZES: [f32; 2] = [0.3, 0.6];
+
+// Paralelos y meridianos de cada nivel de LOD con --generated-spheres
+const GENERATED_SPHERE_LODS: [(u32, u32); 3] = [(24, 48), (12, 24), (6, 12)];
+
+fn get_lod_mesh(lod_meshes: &[IndexedMesh], detail_level: usize) -> &IndexedMesh {
+    // Los niveles por encima del último disponible usan el mesh más simple
+    &lod_meshes[detail_level.min(lod_meshes.len() - 1)]
+}
+
+// Los seis planos del volumen de visión (izquierda, derecha, abajo, arriba, cerca, lejos)
+// como (normal, d), con la normal apuntando hacia dentro
+struct Frustum {
+    planes: [Vec4; 6],
+}
+
+impl Frustum {
+    // Extrae los planos de una matriz proyección * vista (método de Gribb-Hartmann)
+    fn from_matrix(view_projection: &Mat4) -> Self {
+        let row = |i: usize| {
+            let r = view_projection.row(i);
+            Vec4::new(r[0], r[1], r[2], r[3])
+        };
+        let (x, y, z, w) = (row(0), row(1), row(2), row(3));
+
+        let planes = [w + x, w - x, w + y, w - y, w + z, w - z].map(|plane| {
+            let length = Vec3::new(plane.x, plane.y, plane.z).magnitude();
+            plane / length
+        });
+
+        Self { planes }
+    }
+
+    // Una esfera es visible salvo que quede por completo detrás de alguno de los planos
+    fn sphere_visible(&self, center: &Vec3, radius: f32) -> bool {
+        self.planes.iter().all(|plane| {
+            plane.x * center.x + plane.y * center.y + plane.z * center.z + plane.w >= -radius
+        })
+    }
+}
+
+// Distancia desde `position` hasta la esfera de colisión del cuerpo (negativa si está dentro)
+fn surface_distance(position: &Vec3, body: &CelestialBody) -> f32 {
+    let collision_radius = body.scale * 2.0;
+    (position - body.position).magnitude() - collision_radius
+}
+
+// Devuelve el cuerpo en el que penetra `position` y la normal de su esfera de
+// colisión en ese punto (hacia afuera), o None si no hay colisión
+fn check_collision(position: &Vec3, celestial_bodies: &[CelestialBody]) -> Option<(usize, Vec3)> {
+    for (index, body) in celestial_bodies.iter().enumerate() {
+        if surface_distance(position, body) < 0.0 {
+            let to_position = position - body.position;
+            let distance = to_position.magnitude();
+            let normal = if distance > 0.0 { to_position / distance } else { Vec3::new(0.0, 1.0, 0.0) };
+            return Some((index, normal)); // Hay colisión
+        }
+    }
+    None // No hay colisión
+}
+
+// Cuerpo más cercano a la superficie y la distancia hasta ella
+fn nearest_body(position: &Vec3, celestial_bodies: &[CelestialBody]) -> Option<(usize, f32)> {
+    celestial_bodies.iter()
+        .enumerate()
+        .map(|(index, body)| (index, surface_distance(position, body)))
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+}
+
+// Velocidad (por segundo) con la que el roll sigue al banking que pide el giro, y
+// con la que la cámara recupera el horizonte al dejar de girar
+const BANK_RESPONSE: f32 = 4.0;
+
+// Frames hacia adelante en los que se avisa de una colisión inminente
+const COLLISION_LOOKAHEAD_FRAMES: f32 = 45.0;
+
+// Predicción por barrido: devuelve el cuerpo que se alcanzaría primero si se mantiene
+// la velocidad actual (unidades por frame) durante `lookahead` frames
+fn collision_imminent(position: &Vec3, velocity: &Vec3, celestial_bodies: &[CelestialBody], lookahead: f32) -> Option<usize> {
+    let mut closest: Option<(usize, f32)> = None;
+
+    for (index, body) in celestial_bodies.iter().enumerate() {
+        let collision_radius = body.scale * 2.0;
+        let to_position = position - body.position;
+
+        // |p + v*t - c|² = r²  =>  a*t² + b*t + c = 0
+        let a = velocity.dot(velocity);
+        let b = 2.0 * to_position.dot(velocity);
+        let c = to_position.dot(&to_position) - collision_radius * collision_radius;
+
+        let hit_time = if c <= 0.0 {
+            0.0 // Ya estamos dentro del radio de colisión
+        } else if a <= f32::EPSILON {
+            continue; // Sin movimiento no hay colisión futura
+        } else {
+            let discriminant = b * b - 4.0 * a * c;
+            if discriminant < 0.0 {
+                continue;
+            }
+            (-b - discriminant.sqrt()) / (2.0 * a)
+        };
+
+        if (0.0..=lookahead).contains(&hit_time) && !closest.is_some_and(|(_, time)| time <= hit_time) {
+            closest = Some((index, hit_time));
+        }
+    }
+
+    closest.map(|(index, _)| index)
+}
+
+// Retícula roja en el centro de la pantalla avisando de la colisión
+fn render_collision_warning(overlay: &mut Framebuffer) {
+    let center_x = overlay.width as f32 / 2.0;
+    let center_y = overlay.height as f32 / 2.0;
+    let radius = 14.0;
+    let color = 0xFFFF2020;
+
+    let segments = 96;
+    for i in 0..segments {
+        let angle = i as f32 / segments as f32 * 2.0 * PI;
+        let x = center_x + angle.cos() * radius;
+        let y = center_y + angle.sin() * radius;
+        overlay.point_no_depth(x as usize, y as usize, color);
+    }
+
+    for offset in 4..9 {
+        overlay.point_no_depth(center_x as usize + offset, center_y as usize, color);
+        overlay.point_no_depth(center_x as usize - offset, center_y as usize, color);
+        overlay.point_no_depth(center_x as usize, center_y as usize + offset, color);
+        overlay.point_no_depth(center_x as usize, center_y as usize - offset, color);
+    }
+}
+
+// Rayo (origen, dirección) desde la cámara a través de un punto de la pantalla
+fn screen_ray(screen_x: f32, screen_y: f32, screen_size: (f32, f32), camera: &Camera, projection_matrix: &Mat4) -> Option<(Vec3, Vec3)> {
+    let ndc_x = 2.0 * screen_x / screen_size.0 - 1.0;
+    let ndc_y = 1.0 - 2.0 * screen_y / screen_size.1;
+
+    let view_matrix = create_view_matrix(camera.view_eye(), camera.view_center(), camera.up);
+    let inverse = (projection_matrix * view_matrix).try_inverse()?;
+
+    let near = inverse * Vec4::new(ndc_x, ndc_y, -1.0, 1.0);
+    let far = inverse * Vec4::new(ndc_x, ndc_y, 1.0, 1.0);
+    let near = Vec3::new(near.x, near.y, near.z) / near.w;
+    let far = Vec3::new(far.x, far.y, far.z) / far.w;
+
+    Some((camera.view_eye(), (far - near).normalize()))
+}
+
+// Cuerpo más cercano bajo un punto de la pantalla (intersección rayo-esfera)
+fn screen_to_body(
+    screen_x: f32,
+    screen_y: f32,
+    screen_size: (f32, f32),
+    camera: &Camera,
+    projection_matrix: &Mat4,
+    celestial_bodies: &[CelestialBody],
+) -> Option<usize> {
+    let (origin, direction) = screen_ray(screen_x, screen_y, screen_size, camera, projection_matrix)?;
+    let mut closest: Option<(usize, f32)> = None;
+
+    for (index, body) in celestial_bodies.iter().enumerate() {
+        let to_origin = origin - body.position;
+        let b = to_origin.dot(&direction);
+        let c = to_origin.dot(&to_origin) - body.scale * body.scale;
+        let discriminant = b * b - c;
+        if discriminant < 0.0 {
+            continue;
+        }
+
+        let hit_distance = -b - discriminant.sqrt();
+        if hit_distance > 0.0 && !closest.is_some_and(|(_, distance)| distance <= hit_distance) {
+            closest = Some((index, hit_distance));
+        }
+    }
+
+    closest.map(|(index, _)| index)
+}
+
+// Factor de iluminación de un cuerpo en `position`: cae con el cuadrado de la
+// distancia al Sol, normalizado a LIGHT_REFERENCE_DISTANCE y limitado a LIGHT_SCALE_RANGE
+fn light_scale(position: &Vec3, sun_position: &Vec3) -> f32 {
+    let distance_squared = (position - sun_position).magnitude_squared().max(f32::EPSILON);
+    let scale = LIGHT_REFERENCE_DISTANCE * LIGHT_REFERENCE_DISTANCE / distance_squared;
+    scale.clamp(LIGHT_SCALE_RANGE.0, LIGHT_SCALE_RANGE.1)
+}
+
+// Pares (ocultador, ocultado) en los que un cuerpo proyecta su sombra sobre otro:
+// el ocultador está entre el Sol y el ocultado, casi alineado con ambos
+fn detect_eclipses(celestial_bodies: &[CelestialBody], sun_position: &Vec3) -> Vec<(usize, usize)> {
+    let mut eclipses = Vec::new();
+
+    for (occluded_index, occluded) in celestial_bodies.iter().enumerate() {
+        let to_occluded = occluded.position - sun_position;
+        let occluded_distance = to_occluded.magnitude();
+        if occluded_distance <= f32::EPSILON {
+            continue; // El propio Sol
+        }
+        let shadow_axis = to_occluded / occluded_distance;
+
+        for (occluder_index, occluder) in celestial_bodies.iter().enumerate() {
+            if occluder_index == occluded_index {
+                continue;
+            }
+
+            let to_occluder = occluder.position - sun_position;
+            let along_axis = to_occluder.dot(&shadow_axis);
+            if along_axis <= f32::EPSILON || along_axis >= occluded_distance {
+                continue; // No está entre el Sol y el otro cuerpo
+            }
+
+            // Distancia del ocultador al eje Sol-ocultado, con tolerancia de la suma de radios
+            let off_axis = (to_occluder - shadow_axis * along_axis).magnitude();
+            if off_axis < occluder.scale + occluded.scale {
+                eclipses.push((occluder_index, occluded_index));
+            }
+        }
+    }
+
+    eclipses
+}
+
+fn tooltip_lines(body: &CelestialBody, sun_position: &Vec3) -> Vec<String> {
+    vec![
+        body.name.clone(),
+        format!("Escala: {:.2}", body.scale),
+        format!("Dist. al Sol: {:.1}", (body.position - sun_position).magnitude()),
+    ]
+}
+
+// Distancias a las que las etiquetas empiezan a desvanecerse y desaparecen del todo
+const LABEL_FADE_START: f32 = 120.0;
+const LABEL_FADE_END: f32 = 220.0;
+
+// Nombre de cada cuerpo encima de su disco en pantalla. Se ocultan los que están
+// detrás de la cámara, fuera de la pantalla o tapados por algo más cercano (según el
+// z-buffer), y se desvanecen con la distancia
+fn render_labels(overlay: &mut Framebuffer, framebuffer: &Framebuffer, uniforms: &Uniforms, celestial_bodies: &[CelestialBody]) {
+    let pixels_per_unit = uniforms.projection_matrix[(1, 1)] * framebuffer.height as f32 / 2.0;
+
+    for body in celestial_bodies {
+        let to_camera = uniforms.camera_position - body.position;
+        let distance = to_camera.magnitude();
+        if distance <= body.scale * 1.05 {
+            continue;
+        }
+
+        let fade = 1.0 - ((distance - LABEL_FADE_START) / (LABEL_FADE_END - LABEL_FADE_START)).clamp(0.0, 1.0);
+        if fade <= 0.0 {
+            continue;
+        }
+
+        // Punto justo delante de la superficie, del lado de la cámara
+        let front = body.position + to_camera / distance * body.scale * 1.05;
+        let Some((screen, w)) = project_to_screen(uniforms, front) else {
+            continue;
+        };
+        if screen.x < 0.0 || screen.y < 0.0 || screen.x >= framebuffer.width as f32 || screen.y >= framebuffer.height as f32 {
+            continue;
+        }
+        if !framebuffer.should_draw(screen.x as usize, screen.y as usize, screen.z) {
+            continue; // Tapado por otro cuerpo
+        }
+
+        // Coordenadas del overlay, que tiene la resolución de la ventana
+        let scale = framebuffer.scale as f32;
+        let radius = body.scale * pixels_per_unit / w;
+        let x = screen.x / scale - text_width(&body.name) as f32 / 2.0;
+        let y = (screen.y - radius) / scale - GLYPH_HEIGHT as f32 - 3.0;
+        if x < 0.0 || y < 0.0 || x as usize + text_width(&body.name) >= overlay.width {
+            continue;
+        }
+
+        let alpha = (fade * 255.0) as u32;
+        let color = (alpha << 24) | body.shader_type.palette_color().to_hex();
+        overlay.draw_text(x as usize, y as usize, &body.name, color);
+    }
+}
+
+// Intensidad (0..1) de la distorsión de pantalla según la cercanía al agujero negro:
+// `alcance / distancia - 1`, que vale 0 en el borde del alcance y crece al acercarse
+fn black_hole_distortion(eye: &Vec3, celestial_bodies: &[CelestialBody]) -> f32 {
+    celestial_bodies.iter()
+        .find(|body| matches!(body.shader_type, PlanetType::BlackHole))
+        .map(|black_hole| {
+            let reach = black_hole.scale * BLACK_HOLE_EFFECT_REACH;
+            let distance = (eye - black_hole.position).magnitude().max(f32::EPSILON);
+            (reach / distance - 1.0).clamp(0.0, 1.0)
+        })
+        .unwrap_or(0.0)
+}
+
+// Panel de la esquina inferior izquierda: posición, velocidad y cuerpo más cercano
+fn render_hud(overlay: &mut Framebuffer, camera: &Camera, velocity: &Vec3, celestial_bodies: &[CelestialBody], collisions: bool) {
+    let mut lines = vec![
+        format!("Pos: {:.1} {:.1} {:.1}", camera.eye.x, camera.eye.y, camera.eye.z),
+        format!("Vel: {:.2} (base {:.2})", velocity.magnitude(), camera.speed),
+    ];
+    if let Some((index, distance)) = nearest_body(&camera.eye, celestial_bodies) {
+        lines.push(format!("Cerca: {} a {:.1}", celestial_bodies[index].name, distance.max(0.0)));
+    }
+    if !collisions {
+        lines.push("Colisiones: desactivadas (N)".to_string());
+    }
+
+    let line_height = GLYPH_HEIGHT + 3;
+    let top = overlay.height.saturating_sub(lines.len() * line_height + 8);
+    for (index, line) in lines.iter().enumerate() {
+        overlay.draw_text(8, top + index * line_height, line, 0xFFB0D8B0);
+    }
+}
+
+// Peso de cada frame nuevo en el promedio móvil del tiempo de frame: más bajo da
+// una lectura más estable pero que tarda más en reaccionar
+const FRAME_TIME_SMOOTHING: f32 = 0.05;
+
+// Tiempo de frame real (sin el tope de la simulación), suavizado con un promedio
+// móvil exponencial para que el contador no salte de un frame a otro
+struct FrameStats {
+    average_frame_time: f32,
+}
+
+impl FrameStats {
+    fn new() -> Self {
+        Self { average_frame_time: 0.0 }
+    }
+
+    fn record(&mut self, frame_time: f32) {
+        if self.average_frame_time == 0.0 {
+            self.average_frame_time = frame_time;
+        } else {
+            self.average_frame_time += (frame_time - self.average_frame_time) * FRAME_TIME_SMOOTHING;
+        }
+    }
+
+    fn fps(&self) -> f32 {
+        if self.average_frame_time > 0.0 { 1.0 / self.average_frame_time } else { 0.0 }
+    }
+}
+
+// FPS y tiempo de frame promedio en la esquina superior izquierda (F1)
+fn render_frame_stats(overlay: &mut Framebuffer, stats: &FrameStats) {
+    let lines = [
+        format!("FPS: {:.0}", stats.fps()),
+        format!("Frame: {:.2} ms", stats.average_frame_time * 1000.0),
+    ];
+    let line_height = GLYPH_HEIGHT + 3;
+    for (index, line) in lines.iter().enumerate() {
+        overlay.draw_text(8, 8 + index * line_height, line, 0xFFB0D8B0);
+    }
+}
+
+// Minimapa de la esquina superior derecha: lado en píxeles, margen y cuánto espacio
+// se deja alrededor de la órbita más lejana
+const MINIMAP_SIZE: usize = 140;
+const MINIMAP_MARGIN: usize = 8;
+const MINIMAP_PADDING: f32 = 1.1;
+const MINIMAP_HEADING_LENGTH: f32 = 9.0;
+
+// Vista ortográfica del sistema desde arriba (mirando hacia -y, con +x a la derecha y
+// +z hacia abajo), centrada en el Sol: un punto por cuerpo, de tamaño según su escala,
+// y la cámara como un marcador con una línea hacia donde mira
+fn render_minimap(overlay: &mut Framebuffer, camera: &Camera, celestial_bodies: &[CelestialBody], sun_position: &Vec3) {
+    if overlay.width < MINIMAP_SIZE + MINIMAP_MARGIN * 2 || overlay.height < MINIMAP_SIZE + MINIMAP_MARGIN * 2 {
+        return;
+    }
+    let left = overlay.width - MINIMAP_SIZE - MINIMAP_MARGIN;
+    let top = MINIMAP_MARGIN;
+
+    for py in top..=top + MINIMAP_SIZE {
+        for px in left..=left + MINIMAP_SIZE {
+            let border = py == top || py == top + MINIMAP_SIZE || px == left || px == left + MINIMAP_SIZE;
+            overlay.point_no_depth(px, py, if border { 0xFF606070 } else { 0xB0101018 });
+        }
+    }
+
+    // Escala para que quepa el cuerpo más alejado del Sol
+    let extent = celestial_bodies.iter()
+        .map(|body| Vec2::new(body.position.x - sun_position.x, body.position.z - sun_position.z).magnitude())
+        .fold(1.0, f32::max) * MINIMAP_PADDING;
+    let half = MINIMAP_SIZE as f32 / 2.0;
+    let pixels_per_unit = half / extent;
+    let center = (left as f32 + half, top as f32 + half);
+
+    // Proyección ortográfica: se descarta la altura y se recorta al borde del mapa
+    let to_map = |position: &Vec3| {
+        let x = (position.x - sun_position.x) * pixels_per_unit;
+        let y = (position.z - sun_position.z) * pixels_per_unit;
+        (center.0 + x.clamp(-half + 1.0, half - 1.0), center.1 + y.clamp(-half + 1.0, half - 1.0))
+    };
+
+    for body in celestial_bodies {
+        let (x, y) = to_map(&body.position);
+        let radius = (body.scale * pixels_per_unit).clamp(1.0, 4.0);
+        let color = 0xFF000000 | body.shader_type.palette_color().to_hex();
+        minimap_disc(overlay, x, y, radius, color);
+    }
+
+    // La cámara: un punto blanco con una línea en la dirección a la que mira
+    let (x, y) = to_map(&camera.eye);
+    let forward = camera.get_forward();
+    let heading = Vec2::new(forward.x, forward.z);
+    if heading.magnitude() > 1e-3 {
+        let heading = heading.normalize();
+        for step in 0..=MINIMAP_HEADING_LENGTH as usize {
+            let (hx, hy) = (x + heading.x * step as f32, y + heading.y * step as f32);
+            if (hx - center.0).abs() < half && (hy - center.1).abs() < half {
+                overlay.point_no_depth(hx as usize, hy as usize, 0xFFFFE070);
+            }
+        }
+    }
+    minimap_disc(overlay, x, y, 2.0, 0xFFFFFFFF);
+}
+
+// Círculo relleno en el minimapa
+fn minimap_disc(overlay: &mut Framebuffer, x: f32, y: f32, radius: f32, color: u32) {
+    let reach = radius.ceil() as isize;
+    for dy in -reach..=reach {
+        for dx in -reach..=reach {
+            if (dx * dx + dy * dy) as f32 <= radius * radius {
+                let (px, py) = (x as isize + dx, y as isize + dy);
+                if px >= 0 && py >= 0 && (px as usize) < overlay.width && (py as usize) < overlay.height {
+                    overlay.point_no_depth(px as usize, py as usize, color);
+                }
+            }
+        }
+    }
+}
+
+// Caja oscura con texto junto al cursor, ajustada para no salirse de la pantalla
+fn render_tooltip(overlay: &mut Framebuffer, x: usize, y: usize, lines: &[String]) {
+    let padding = 4;
+    let line_height = GLYPH_HEIGHT + 2;
+    let box_width = lines.iter().map(|line| text_width(line)).max().unwrap_or(0) + padding * 2;
+    let box_height = lines.len() * line_height + padding * 2 - 2;
+
+    let left = (x + 12).min(overlay.width.saturating_sub(box_width + 1));
+    let top = (y + 12).min(overlay.height.saturating_sub(box_height + 1));
+
+    for py in top..=top + box_height {
+        for px in left..=left + box_width {
+            let border = py == top || py == top + box_height || px == left || px == left + box_width;
+            overlay.point_no_depth(px, py, if border { 0xFF606070 } else { 0xE0101018 });
+        }
+    }
+
+    for (index, line) in lines.iter().enumerate() {
+        overlay.draw_text(left + padding, top + padding + index * line_height, line, 0xFFE0E0E0);
+    }
+}
+
+// `dt` son los segundos reales del frame; las velocidades están dadas por paso fijo
+// (FIXED_DT) y se escalan para que la nave se mueva igual a cualquier framerate
+fn handle_input(window: &Window, keys: &KeyBindings, camera: &mut Camera, celestial_bodies: &[CelestialBody], input: &mut InputState, dt: f32) {
+    let steps = dt / FIXED_DT;
+    // Shift multiplica la velocidad base de la cámara mientras se mantiene
+    let boost = if any_down(window, &keys.boost) { BOOST_MULTIPLIER } else { 1.0 };
+    // Velocidad máxima en unidades por segundo (camera.speed está dada por paso fijo)
+    let max_speed = camera.speed * boost / FIXED_DT;
+    let rotation_speed = PI/128.0 * steps;
+    // Banking de un giro sostenido con A/D: crece con la velocidad, pero despacio y con
+    // tope, para que los giros rápidos no se descontrolen
+    let bank_angle = (PI/16.0 * (camera.speed * boost / DEFAULT_SPEED).sqrt()).min(MAX_BANK_ANGLE);
+
+    // Manejar la vista aérea
+    if any_down(window, &keys.bird_eye) {
+        if !camera.bird_eye_active {
+            // Guardar el estado actual antes de cambiar a vista aérea
+            camera.previous_state = Some((
+                camera.eye,
+                camera.center,
+                camera.orientation
+            ));
+            set_bird_eye_view(camera, celestial_bodies);
+            camera.bird_eye_active = true;
+        }
+    } else if camera.bird_eye_active {
+        // Restaurar la posición anterior cuando se suelta la tecla
+        if let Some((prev_eye, prev_center, prev_orientation)) = camera.previous_state {
+            camera.eye = prev_eye;
+            camera.center = prev_center;
+            camera.orientation = prev_orientation;
+            camera.up = camera.get_up();
+            camera.previous_state = None;
+            camera.bird_eye_active = false;
+        }
+    }
+
+    // Solo procesar otros controles si no estamos en vista aérea
+    if !camera.bird_eye_active {
+        // Warp al cuerpo asignado a cada tecla de warp en la escena (warp_slot)
+        if let Some(slot) = keys.warp.iter().position(|warp_keys| any_pressed(window, warp_keys, KeyRepeat::No)) {
+            let slot = slot as u32 + 1;
+            if let Some(body) = celestial_bodies.iter().find(|body| body.warp_slot == Some(slot)) {
+                warp_to_planet(camera, body, body.warp_distance);
+            }
+        }
+
+        // Ajustar la velocidad base con + y -
+        if any_pressed(window, &keys.speed_up, KeyRepeat::Yes) {
+            camera.scale_speed(SPEED_STEP);
+        }
+        if any_pressed(window, &keys.speed_down, KeyRepeat::Yes) {
+            camera.scale_speed(1.0 / SPEED_STEP);
+        }
+
+        // Movimiento lateral con rotación
+        if any_down(window, &keys.yaw_left) {
+            camera.rotate_yaw(-rotation_speed);
+        } else if any_down(window, &keys.yaw_right) {
+            camera.rotate_yaw(rotation_speed);
+        }
+
+        // Control de pitch
+        if any_down(window, &keys.pitch_up) {
+            camera.rotate_pitch(rotation_speed);
+        }
+        if any_down(window, &keys.pitch_down) {
+            camera.rotate_pitch(-rotation_speed);
+        }
+
+        // Mirar con el ratón mientras está capturado. Fuera de la ventana la posición
+        // queda fija en el borde, y ahí el giro sigue solo (ver MOUSE_EDGE_BAND)
+        if input.mouse_look.captured {
+            if let Some((mouse_x, mouse_y)) = window.get_unscaled_mouse_pos(MouseMode::Clamp) {
+                if let Some((previous_x, previous_y)) = input.mouse_look.previous_position {
+                    camera.rotate_yaw((mouse_x - previous_x) * MOUSE_SENSITIVITY);
+                    camera.rotate_pitch((previous_y - mouse_y) * MOUSE_SENSITIVITY);
+                }
+                input.mouse_look.previous_position = Some((mouse_x, mouse_y));
+
+                let (width, height) = window.get_size();
+                camera.rotate_yaw(mouse_edge_push(mouse_x, width as f32) * MOUSE_EDGE_TURN_RATE * dt);
+                camera.rotate_pitch(-mouse_edge_push(mouse_y, height as f32) * MOUSE_EDGE_TURN_RATE * dt);
+            }
+        }
+
+        // Banking según la velocidad de giro real (cambio de rumbo en este frame, con
+        // las teclas o el ratón): girar a KEY_YAW_RATE pide `bank_angle`, y el roll se
+        // acerca poco a poco, así un giro sostenido inclina más que un toque corto
+        let yaw = camera.yaw();
+        let yaw_rate = match input.previous_yaw {
+            Some(previous) if dt > 0.0 => ((yaw - previous + PI).rem_euclid(2.0 * PI) - PI) / dt,
+            _ => 0.0,
+        };
+        input.previous_yaw = Some(yaw);
+        let target_roll = (-yaw_rate / KEY_YAW_RATE * bank_angle).clamp(-MAX_BANK_ANGLE, MAX_BANK_ANGLE);
+        if target_roll == 0.0 {
+            camera.auto_level(dt, BANK_RESPONSE);
+        } else {
+            camera.ease_roll(target_roll, dt, BANK_RESPONSE);
+        }
+
+        // Empuje de los motores: la nave acelera mientras se mantienen las teclas y
+        // frena poco a poco al soltarlas
+        let mut thrust = Vec3::new(0.0, 0.0, 0.0);
+        
+        if any_down(window, &keys.forward) {
+            thrust += camera.get_forward();
+        }
+        if any_down(window, &keys.back) {
+            thrust -= camera.get_forward() * 0.5;
+        }
+        if any_down(window, &keys.ascend) {
+            thrust += camera.get_up() * 0.7;
+        }
+        if any_down(window, &keys.descend) {
+            thrust -= camera.get_up() * 0.7;
+        }
+        camera.accelerate(thrust, max_speed, dt);
+
+        // Sin colisiones la nave atraviesa los cuerpos libremente
+        if !input.collisions {
+            camera.integrate(dt);
+            return;
+        }
+
+        // Verificar colisiones con la posición predicha: al chocar se quita la componente
+        // de la velocidad que entra en la superficie para deslizarse alrededor del cuerpo
+        if let Some((_, normal)) = check_collision(&camera.predicted_position(dt), celestial_bodies) {
+            let into_surface = camera.velocity.dot(&normal).min(0.0);
+            camera.velocity -= normal * into_surface;
+        }
+        
+        if check_collision(&camera.predicted_position(dt), celestial_bodies).is_none() {
+            camera.integrate(dt);
+        } else {
+            camera.velocity = Vec3::zeros();
+        }
+    }
+}
+
+// Duración de un warp según la distancia a recorrer: los saltos largos (el agujero
+// negro, la vista aérea) tardan más que ir a un planeta cercano
+const WARP_MIN_DURATION: f32 = 1.0;
+const WARP_MAX_DURATION: f32 = 3.0;
+const WARP_SECONDS_PER_UNIT: f32 = 0.01;
+
+fn warp_duration(from: &Vec3, to: &Vec3) -> f32 {
+    (WARP_MIN_DURATION + (to - from).magnitude() * WARP_SECONDS_PER_UNIT).min(WARP_MAX_DURATION)
+}
+
+fn warp_to_planet(camera: &mut Camera, body: &CelestialBody, distance: f32) {
+    // Calcular la posición relativa considerando el movimiento orbital
+    let offset = Vec3::new(0.0, 0.0, distance + body.scale);
+    
+    // Usar la posición actual del planeta
+    let target_pos = body.position + offset;
+    
+    // Calcular la dirección mirando hacia el planeta
+    let target_direction = (body.position - target_pos).normalize();
+    
+    // Iniciar el warp con la posición y dirección calculadas
+    let duration = warp_duration(&camera.eye, &target_pos);
+    camera.start_warp(target_pos, target_direction, duration);
+}
+
+// Salida del primer agujero de gusano cuyo anillo de entrada se atravesó al moverse
+// de `previous_eye` a `eye`, o None si no se cruzó ninguno
+fn wormhole_warp(wormholes: &[Wormhole], previous_eye: &Vec3, eye: &Vec3) -> Option<Vec3> {
+    wormholes.iter()
+        .find(|wormhole| wormhole.crossed_by(previous_eye, eye))
+        .map(|wormhole| wormhole.exit)
+}
+
+// Proyecta un punto del mundo a la pantalla; devuelve (x, y, profundidad) y la w de clip
+fn project_to_screen(uniforms: &Uniforms, position: Vec3) -> Option<(Vec3, f32)> {
+    let position_clip = uniforms.projection_matrix * uniforms.view_matrix * Vec4::new(position.x, position.y, position.z, 1.0);
+
+    if position_clip.w <= 0.0 {
+        return None;
+    }
+
+    let position_ndc = Vec3::new(
+        position_clip.x / position_clip.w,
+        position_clip.y / position_clip.w,
+        position_clip.z / position_clip.w,
+    );
+
+    let position_screen = uniforms.viewport_matrix * Vec4::new(
+        position_ndc.x,
+        position_ndc.y,
+        position_ndc.z,
+        1.0,
+    );
+
+    Some((Vec3::new(position_screen.x, position_screen.y, position_screen.z), position_clip.w))
+}
+
+fn render_trail(
+    framebuffer: &mut Framebuffer,
+    uniforms: &Uniforms,
+    particle: &TrailParticle,
+) {
+    let Some((position_screen, _)) = project_to_screen(uniforms, particle.position) else {
+        return;
+    };
+
+    let x = position_screen.x as usize;
+    let y = position_screen.y as usize;
+
+    if x < framebuffer.width && y < framebuffer.height {
+        // Se desvanece durante su último segundo de vida; con alfa 255 equivale a sobrescribir
+        let alpha = (particle.lifetime.clamp(0.0, 1.0) * 255.0) as u32;
+        let tinted = sun_tint(particle.color, &particle.position, &uniforms.sun_position);
+        let color = (trail_gradient(tinted, particle.age()) & 0x00FFFFFF) | (alpha << 24);
+
+        for (sx, sy) in framebuffer.display_pixel_samples(x, y) {
+            framebuffer.blend_point(sx, sy, position_screen.z, color);
+        }
+    }
+}
+
+// Resplandor blanco-azulado en la salida de los motores: un disco que se desvanece
+// hacia el borde, más grande y opaco cuanto mayor es `intensity` (de 0 a 1)
+fn render_engine_glow(framebuffer: &mut Framebuffer, uniforms: &Uniforms, position: Vec3, intensity: f32) {
+    let Some((center, w)) = project_to_screen(uniforms, position) else {
+        return;
+    };
+
+    let pixels_per_unit = uniforms.projection_matrix[(1, 1)] * framebuffer.height as f32 / 2.0;
+    let radius = ENGINE_GLOW_RADIUS * (0.5 + 0.5 * intensity) * pixels_per_unit / w;
+    if radius < 0.5 {
+        return;
+    }
+
+    let min_x = (center.x - radius).floor().max(0.0) as usize;
+    let min_y = (center.y - radius).floor().max(0.0) as usize;
+    let max_x = ((center.x + radius).ceil() as usize).min(framebuffer.width.saturating_sub(1));
+    let max_y = ((center.y + radius).ceil() as usize).min(framebuffer.height.saturating_sub(1));
+    for y in min_y..=max_y {
+        for x in min_x..=max_x {
+            let distance = Vec2::new(x as f32 + 0.5 - center.x, y as f32 + 0.5 - center.y).magnitude() / radius;
+            if distance >= 1.0 {
+                continue;
+            }
+            // El centro se acerca al blanco y el borde se funde con el fondo
+            let falloff = (1.0 - distance) * (1.0 - distance);
+            let color = ENGINE_GLOW_COLOR.lerp_linear(&Color::new(255, 255, 255), falloff);
+            let alpha = (falloff * intensity.clamp(0.0, 1.0) * 255.0) as u32;
+            framebuffer.blend_point(x, y, center.z, (alpha << 24) | color.to_hex());
+        }
+    }
+}
+
+// Puntos con los que se aproxima cada órbita, opacidad de sus líneas y tope (en
+// pantallas) para los segmentos que quedan muy fuera de la vista
+const ORBIT_SEGMENTS: usize = 128;
+const ORBIT_ALPHA: u32 = 70;
+const ORBIT_MAX_SCREEN_SPAN: f32 = 4.0;
+
+// Dibuja la órbita de `body` alrededor de `center` como una elipse de líneas tenues del
+// color de su paleta, con la misma inclinación que usa `update_orbits`
+fn render_orbit(
+    framebuffer: &mut Framebuffer,
+    uniforms: &Uniforms,
+    fragments: &mut Vec<Fragment>,
+    body: &CelestialBody,
+    center: Vec3,
+    parent_inclination: f32,
+) {
+    if body.semi_major_axis <= 0.0 || matches!(body.shader_type, PlanetType::Sun) {
+        return;
+    }
+
+    let color = body.shader_type.palette_color().to_hex();
+    let inclination = parent_inclination + body.inclination;
+    let limit = framebuffer.width.max(framebuffer.height) as f32 * ORBIT_MAX_SCREEN_SPAN;
+
+    let project = |step: usize| {
+        // Pasos iguales de anomalía excéntrica reparten los puntos de forma pareja por
+        // la elipse (en anomalía media se amontonarían en el afelio)
+        let eccentric_anomaly = step as f32 / ORBIT_SEGMENTS as f32 * 2.0 * PI;
+        let mean_anomaly = eccentric_anomaly - body.eccentricity * eccentric_anomaly.sin();
+        let position = center + orbit_offset(body.semi_major_axis, body.eccentricity, inclination, mean_anomaly);
+        project_to_screen(uniforms, position)
+            .filter(|(screen, _)| screen.x.abs() < limit && screen.y.abs() < limit)
+            .map(|(screen, w)| {
+                let mut vertex = Vertex::new_with_color(position, Color::from_hex(color));
+                vertex.set_transformed(screen, Vec3::new(0.0, 0.0, 1.0));
+                vertex.clip_w = w;
+                vertex
+            })
+    };
+
+    fragments.clear();
+    let mut previous = project(0);
+    for step in 1..=ORBIT_SEGMENTS {
+        let current = project(step);
+        if let (Some(start), Some(end)) = (&previous, &current) {
+            line_aa(start, end, fragments);
+        }
+        previous = current;
+    }
+
+    for fragment in fragments.iter() {
+        let (x, y) = (fragment.position.x as usize, fragment.position.y as usize);
+        if x < framebuffer.width && y < framebuffer.height {
+            let alpha = (ORBIT_ALPHA as f32 * fragment.coverage) as u32;
+            framebuffer.blend_point(x, y, fragment.depth, color | (alpha << 24));
+        }
+    }
+    fragments.clear();
+}
+
+// Estilo de dibujo de las estelas: puntos sueltos o una cinta continua
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TrailStyle {
+    Points,
+    Ribbon,
+}
+
+// Ancho de la cinta en unidades del mundo y cuánto se afina hacia la cola
+// (0 = ancho constante, 1 = termina en punta)
+const RIBBON_WIDTH: f32 = 0.15;
+const RIBBON_TAPER: f32 = 0.95;
+
+// Ancho de la cinta para un punto según su edad relativa (0 = cabeza, 1 = cola)
+fn ribbon_width(width: f32, taper: f32, age_fraction: f32) -> f32 {
+    width * (1.0 - taper.clamp(0.0, 1.0) * age_fraction.clamp(0.0, 1.0))
+}
+
+// Dibuja la estela como una cinta que une las partículas consecutivas (de la más
+// vieja a la más nueva), afinándose hacia la cola
+fn render_trail_ribbon(
+    framebuffer: &mut Framebuffer,
+    uniforms: &Uniforms,
+    particles: &[TrailParticle],
+    width: f32,
+    taper: f32,
+) {
+    if particles.len() < 2 {
+        return;
+    }
+
+    let last = (particles.len() - 1) as f32;
+    let pixels_per_unit = uniforms.projection_matrix[(1, 1)] * framebuffer.height as f32 / 2.0;
+
+    let project = |index: usize| {
+        let particle = &particles[index];
+        project_to_screen(uniforms, particle.position).map(|(screen, w)| {
+            let age_fraction = 1.0 - index as f32 / last;
+            let radius = ribbon_width(width, taper, age_fraction) * pixels_per_unit / w / 2.0;
+            let tinted = sun_tint(particle.color, &particle.position, &uniforms.sun_position);
+            (screen, radius, trail_gradient(tinted, particle.age()))
+        })
+    };
+
+    let mut previous = project(0);
+    for index in 1..particles.len() {
+        let current = project(index);
+        if let (Some(start), Some(end)) = (previous, current) {
+            render_ribbon_segment(framebuffer, start, end);
+        }
+        previous = current;
+    }
+}
+
+// Segmento con radio variable entre sus extremos (una cápsula que se afina)
+fn render_ribbon_segment(framebuffer: &mut Framebuffer, start: (Vec3, f32, u32), end: (Vec3, f32, u32)) {
+    let (a, radius_a, _) = start;
+    let (b, radius_b, color) = end;
+
+    // Segmentos que salen disparados fuera de la pantalla no se dibujan
+    let max_radius = radius_a.max(radius_b).max(0.5);
+    let min_x = (a.x.min(b.x) - max_radius).floor().max(0.0);
+    let min_y = (a.y.min(b.y) - max_radius).floor().max(0.0);
+    let max_x = (a.x.max(b.x) + max_radius).ceil().min(framebuffer.width as f32 - 1.0);
+    let max_y = (a.y.max(b.y) + max_radius).ceil().min(framebuffer.height as f32 - 1.0);
+    if min_x > max_x || min_y > max_y || (max_x - min_x) * (max_y - min_y) > 250_000.0 {
+        return;
+    }
+
+    let segment = Vec3::new(b.x - a.x, b.y - a.y, 0.0);
+    let length_squared = segment.dot(&segment);
+
+    framebuffer.set_current_color(color);
+    for y in min_y as usize..=max_y as usize {
+        for x in min_x as usize..=max_x as usize {
+            let pixel = Vec3::new(x as f32 + 0.5 - a.x, y as f32 + 0.5 - a.y, 0.0);
+            let t = if length_squared > 0.0 {
+                (pixel.dot(&segment) / length_squared).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+
+            // Al menos medio píxel para que la punta no desaparezca por completo
+            let radius = (radius_a + (radius_b - radius_a) * t).max(0.5);
+            if (pixel - segment * t).magnitude() <= radius {
+                let depth = a.z + (b.z - a.z) * t;
+                framebuffer.point_without_depth_write(x, y, depth);
+            }
+        }
+    }
+}
+
+fn set_bird_eye_view(camera: &mut Camera, celestial_bodies: &[CelestialBody]) {
+    // Encontrar el sol (primer cuerpo celeste en nuestro array)
+    let sun_position = celestial_bodies[0].position;
     
-    // Calcular la distancia más lejana de cualquier planeta al sol
+    // Calcular la distancia más lejana de cualquier planeta al sol (su afelio)
     let max_orbital_distance = celestial_bodies
         .iter()
-        .map(|body| body.orbital_distance)
+        .map(|body| body.semi_major_axis * (1.0 + body.eccentricity))
         .max_by(|a, b| a.partial_cmp(b).unwrap())
         .unwrap_or(0.0);
     
@@ -492,29 +2265,209 @@ fn set_bird_eye_view(camera: &mut Camera, celestial_bodies: &[CelestialBody]) {
     let target_direction = Vec3::new(0.0, -1.0, 0.0);
     
     // Iniciar el warp a la nueva posición
-    camera.start_warp(target_pos, target_direction);
+    let duration = warp_duration(&camera.eye, &target_pos);
+    camera.start_warp(target_pos, target_direction, duration);
+}
+
+// Lee el valor de un argumento `--nombre valor` de la línea de comandos
+fn parse_arg<T: std::str::FromStr>(name: &str) -> Option<T> {
+    let args: Vec<String> = std::env::args().collect();
+    args.iter()
+        .position(|arg| arg == name)
+        .and_then(|index| args.get(index + 1))
+        .and_then(|value| value.parse().ok())
+}
+
+fn has_flag(name: &str) -> bool {
+    std::env::args().any(|arg| arg == name)
+}
+
+fn screenshot_path() -> String {
+    let elapsed = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    format!("screenshot_{}_{:03}.png", elapsed.as_secs(), elapsed.subsec_millis())
+}
+
+// Cuerpos de assets/scene.toml (o la disposición original si no se puede leer), con
+// la fase orbital inicial a partir de la longitud media real de cada planeta en la
+// fecha de observación (--julian-date, o el momento actual)
+fn load_bodies() -> Vec<CelestialBody> {
+    let scene = SceneConfig::load(SCENE_PATH).unwrap_or_else(|error| {
+        eprintln!("Usando la escena por defecto ({})", error);
+        SceneConfig::default()
+    });
+    let mut bodies: Vec<CelestialBody> = scene.bodies.into_iter()
+        .enumerate()
+        .map(|(index, config)| CelestialBody::from_config(index, config))
+        .collect();
+
+    let julian_date = parse_arg("--julian-date").unwrap_or_else(julian_date_now);
+    for body in &mut bodies {
+        if let Some(longitude) = mean_longitude(&body.shader_type, julian_date) {
+            body.orbital_phase = longitude;
+        }
+    }
+    bodies
+}
+
+// Coloca cada cuerpo en su órbita para el instante `simulation_time`
+fn update_orbits(celestial_bodies: &mut [CelestialBody], sun_position: Vec3, simulation_time: f32) {
+    // Cada cuerpo orbita alrededor de su padre (el Sol si no tiene); los padres
+    // van antes en la lista, así que su posición de este paso ya está calculada
+    for index in 0..celestial_bodies.len() {
+        if matches!(celestial_bodies[index].shader_type, PlanetType::Sun) {
+            continue; // El sol no se mueve
+        }
+
+        let (center, parent_inclination) = match celestial_bodies[index].parent_index {
+            Some(parent) => (celestial_bodies[parent].position, celestial_bodies[parent].inclination),
+            None => (sun_position, 0.0),
+        };
+
+        let body = &mut celestial_bodies[index];
+        let mean_anomaly = body.orbital_phase + simulation_time * body.orbital_speed;
+        // Ambas inclinaciones giran sobre X, así que se suman: la del hijo es relativa al plano del padre
+        let inclination = parent_inclination + body.inclination;
+        body.position = center + orbit_offset(body.semi_major_axis, body.eccentricity, inclination, mean_anomaly);
+    }
+}
+
+// Vista desde arriba y algo inclinada de todo el sistema, para las miniaturas
+const THUMBNAIL_EYE: Vec3 = Vec3::new(0.0, 25.0, 45.0);
+
+// --thumbnail <archivo.png>: dibuja un solo frame del sistema en su posición inicial
+// con `render_frame`, sin abrir la ventana, y lo guarda como PNG
+fn save_thumbnail(path: &str, width: usize, height: usize) {
+    let mut bodies = load_bodies();
+    let sun_position = bodies.iter()
+        .find(|body| matches!(body.shader_type, PlanetType::Sun))
+        .map_or(Vec3::new(0.0, 0.0, 0.0), |sun| sun.position);
+    update_orbits(&mut bodies, sun_position, 0.0);
+
+    let mut camera = Camera::new(THUMBNAIL_EYE, sun_position, Vec3::new(0.0, 1.0, 0.0));
+    camera.bird_eye_active = true; // Sin la nave delante
+    let pixels = render_frame(width, height, &camera, &bodies, 0);
+
+    let rgb: Vec<u8> = pixels.iter()
+        .flat_map(|&pixel| [(pixel >> 16) as u8, (pixel >> 8) as u8, pixel as u8])
+        .collect();
+    match image::save_buffer(path, &rgb, width as u32, height as u32, image::ColorType::Rgb8) {
+        Ok(()) => println!("Miniatura guardada en {}", path),
+        Err(error) => eprintln!("No se pudo guardar la miniatura {}: {}", path, error),
+    }
+}
+
+// Rasterizador de líneas (`line` o `line_aa`), para compararlos en el benchmark
+type LineRasterizer = fn(&Vertex, &Vertex, &mut Vec<Fragment>);
+
+// Vértice ya proyectado a pantalla, para el benchmark del rasterizador
+fn screen_vertex(x: f32, y: f32) -> Vertex {
+    let mut vertex = Vertex::new(Vec3::new(x, y, 0.5), Vec3::new(0.0, 0.0, 1.0), Vec2::new(0.0, 0.0));
+    vertex.transformed_position = vertex.position;
+    vertex
+}
+
+// --bench-raster: mide cuánto tarda `triangle` en rasterizar triángulos de distinto
+// tamaño sobre un framebuffer del tamaño del render. El costo depende del área de la
+// caja envolvente recortada a la pantalla, así que un triángulo que se sale mucho de
+// la pantalla (el Sol visto de cerca) cuesta lo mismo que uno que la llena justo
+fn run_raster_benchmark(width: usize, height: usize) {
+    const ITERATIONS: u32 = 50;
+    let (w, h) = (width as f32, height as f32);
+    let cases = [
+        ("pequeño (8x8)", [screen_vertex(0.0, 0.0), screen_vertex(0.0, 8.0), screen_vertex(8.0, 0.0)]),
+        ("pantalla completa", [screen_vertex(0.0, 0.0), screen_vertex(0.0, 2.0 * h), screen_vertex(2.0 * w, 0.0)]),
+        ("10x fuera de pantalla", [screen_vertex(-10.0 * w, -10.0 * h), screen_vertex(-10.0 * w, 10.0 * h), screen_vertex(10.0 * w, 0.0)]),
+    ];
+
+    let mut fragments = Vec::with_capacity(width * height);
+    println!("Rasterizador, {}x{} muestras, {} iteraciones:", width, height, ITERATIONS);
+    for (name, [a, b, c]) in &cases {
+        let start = std::time::Instant::now();
+        for _ in 0..ITERATIONS {
+            fragments.clear();
+            triangle(a, b, c, (width, height), &mut fragments);
+        }
+        let per_triangle = start.elapsed().as_secs_f64() * 1000.0 / ITERATIONS as f64;
+        println!("  {:<22} {:>8} fragmentos  {:>8.3} ms", name, fragments.len(), per_triangle);
+    }
+
+    // Una diagonal de la pantalla con la línea simple (Bresenham) y la suavizada (Wu)
+    let (start, end) = (screen_vertex(0.0, 0.0), screen_vertex(w - 1.0, h - 1.0));
+    let lines: [(&str, LineRasterizer); 2] = [("línea", line), ("línea suavizada", line_aa)];
+    for (name, rasterize) in lines {
+        let start_time = std::time::Instant::now();
+        for _ in 0..ITERATIONS {
+            fragments.clear();
+            rasterize(&start, &end, &mut fragments);
+        }
+        let per_line = start_time.elapsed().as_secs_f64() * 1000.0 / ITERATIONS as f64;
+        println!("  {:<22} {:>8} fragmentos  {:>8.3} ms", name, fragments.len(), per_line);
+    }
 }
 
+const DEFAULT_WINDOW_WIDTH: usize = 800;
+const DEFAULT_WINDOW_HEIGHT: usize = 600;
+// Lado mínimo de la ventana, para que el HUD y los textos quepan
+const MIN_WINDOW_SIZE: usize = 160;
+
 fn main() {
-    let window_width = 800;
-    let window_height = 600;
-    let framebuffer_width = 800;
-    let framebuffer_height = 600;
+    // Resolución con --width/--height (800x600 por defecto). --fullscreen abre una
+    // ventana sin bordes en la esquina de la pantalla: minifb no tiene pantalla
+    // completa real, así que conviene pasar también la resolución del monitor
+    let window_width = parse_arg("--width").unwrap_or(DEFAULT_WINDOW_WIDTH).max(MIN_WINDOW_SIZE);
+    let window_height = parse_arg("--height").unwrap_or(DEFAULT_WINDOW_HEIGHT).max(MIN_WINDOW_SIZE);
+    let fullscreen = has_flag("--fullscreen");
+    let framebuffer_width = window_width;
+    let framebuffer_height = window_height;
+
+    if has_flag("--bench-raster") {
+        run_raster_benchmark(framebuffer_width * SUPERSAMPLE_SCALE, framebuffer_height * SUPERSAMPLE_SCALE);
+        return;
+    }
+    if let Some(path) = parse_arg::<String>("--thumbnail") {
+        save_thumbnail(&path, framebuffer_width, framebuffer_height);
+        return;
+    }
 
-    let mut framebuffer = Framebuffer::new(framebuffer_width, framebuffer_height);
+    // --packed-depth usa un z-buffer entero de 24 bits en lugar de f32
+    let depth_format = if has_flag("--packed-depth") { DepthFormat::Packed24 } else { DepthFormat::Float32 };
+    let mut framebuffer = Framebuffer::new_supersampled(framebuffer_width, framebuffer_height, SUPERSAMPLE_SCALE, depth_format);
     let mut window = Window::new(
         "Rust Graphics - Renderer Example",
         window_width,
         window_height,
-        WindowOptions::default(),
+        WindowOptions {
+            borderless: fullscreen,
+            title: !fullscreen,
+            topmost: fullscreen,
+            ..WindowOptions::default()
+        },
     )
         .unwrap();
 
-    window.set_position(500, 500);
+    if fullscreen {
+        window.set_position(0, 0);
+    } else {
+        window.set_position(500, 500);
+    }
     window.update();
 
     framebuffer.set_background_color(0x000000);
 
+    // Toda la interfaz 2D se dibuja en su propio buffer ARGB, transparente al limpiarlo,
+    // y se compone sobre la escena una vez por frame
+    let mut overlay = Framebuffer::new(framebuffer_width, framebuffer_height, DepthFormat::Float32);
+    overlay.set_background_color(0x00000000);
+    let mut ui_visible = true;
+    let mut minimap_visible = false;
+    let mut fps_visible = false;
+    let mut bloom_enabled = false;
+    let mut vignette_enabled = true;
+    let mut aberration_enabled = true;
+    let mut input = InputState::new();
+
     
     let mut camera = Camera::new(
         Vec3::new(0.0, 0.0, 5.0),
@@ -522,284 +2475,633 @@ fn main() {
         Vec3::new(0.0, 1.0, 0.0)
     );
 
-    let obj = Obj::load("assets/models/esfera.obj").expect("Failed to load obj");
-    let vertex_arrays = obj.get_vertex_array(); 
-    let mut time = 0;
-    let skybox = Skybox::new(1000);
-
-    let noise = create_noise();
-    let projection_matrix = create_perspective_matrix(window_width as f32, window_height as f32);
-    let viewport_matrix = create_viewport_matrix(framebuffer_width as f32, framebuffer_height as f32);
-    let sun_position = Vec3::new(0.0, 0.0, 0.0);
-    let mut uniforms = Uniforms { 
-        model_matrix: Mat4::identity(), 
-        view_matrix: Mat4::identity(), 
-        projection_matrix, 
-        viewport_matrix, 
-        time: 0, 
-        noise,
-        camera_position: camera.eye,
-        sun_position,
-    };
-
-    
-    let mut celestial_bodies = vec![
-        CelestialBody {
-            position: Vec3::new(0.0, 0.0, 0.0),
-            scale: 2.0,
-            rotation: Vec3::new(0.0, 0.0, 0.0),
-            shader_type: PlanetType::Sun,
-            orbital_distance: 0.0,
-            orbital_speed: 0.0,
-            trail: Trail::new(50000),
-        },
-        CelestialBody {
-            position: Vec3::new(6.0, 0.0, 0.0),
-            scale: 0.4,
-            rotation: Vec3::new(0.0, 0.0, 0.0),
-            shader_type: PlanetType::Mercury,
-            orbital_distance: 12.0,
-            orbital_speed: 0.002,
-            trail: Trail::new(50000),
-        },
-        CelestialBody {
-            position: Vec3::new(12.0, 0.0, 0.0),
-            scale: 0.6,
-            rotation: Vec3::new(0.0, 0.0, 0.0),
-            shader_type: PlanetType::Venus,
-            orbital_distance: 24.0,
-            orbital_speed: 0.0015,
-            trail: Trail::new(50000),
-        },
-        CelestialBody {
-            position: Vec3::new(18.0, 0.0, 0.0),
-            scale: 0.7,
-            rotation: Vec3::new(0.0, 0.0, 0.0),
-            shader_type: PlanetType::Earth,
-            orbital_distance: 36.0,
-            orbital_speed: 0.001,
-            trail: Trail::new(50000),
-        },
-        CelestialBody {
-            position: Vec3::new(24.0, 0.0, 0.0),
-            scale: 0.5,
-            rotation: Vec3::new(0.0, 0.0, 0.0),
-            shader_type: PlanetType::Mars,
-            orbital_distance: 48.0,
-            orbital_speed: 0.0008,
-            trail: Trail::new(50000),
-        },
-        CelestialBody {
-            position: Vec3::new(32.0, 0.0, 0.0),
-            scale: 1.5,
-            rotation: Vec3::new(0.0, 0.0, 0.0),
-            shader_type: PlanetType::Jupiter,
-            orbital_distance: 64.0,
-            orbital_speed: 0.0005,
-            trail: Trail::new(50000),
-        },
-        CelestialBody {
-            position: Vec3::new(40.0, 0.0, 0.0),
-            scale: 1.3,
-            rotation: Vec3::new(0.2, 0.0, 0.0),
-            shader_type: PlanetType::Saturn,
-            orbital_distance: 80.0,
-            orbital_speed: 0.0004,
-            trail: Trail::new(50000),
-        },
-        CelestialBody {
-            position: Vec3::new(48.0, 0.0, 0.0),
-            scale: 0.9,
-            rotation: Vec3::new(0.0, 0.0, 0.0),
-            shader_type: PlanetType::Uranus,
-            orbital_distance: 96.0,
-            orbital_speed: 0.0003,
-            trail: Trail::new(50000),
-        },
-        CelestialBody {
-            position: Vec3::new(56.0, 0.0, 0.0),
-            scale: 0.9,
-            rotation: Vec3::new(0.0, 0.0, 0.0),
-            shader_type: PlanetType::Neptune,
-            orbital_distance: 102.0,
-            orbital_speed: 0.0002,
-            trail: Trail::new(50000),
+    // Tiempo de simulación continuo: avanza `time_scale` por frame (nada en pausa)
+    let mut simulation_time: f32 = 0.0;
+    let mut last_frame = std::time::Instant::now();
+    let mut accumulator: f32 = 0.0;
+    let mut time_scale = 1.0;
+    let mut time_multiplier: f32 = 1.0;
+    let mut paused = false;
+    // La semilla del cielo se puede compartir con --sky-seed para reproducir el mismo cielo;
+    // con --stable-sky se usa siempre la misma (la del cielo por defecto)
+    let sky_seed = parse_arg("--sky-seed").unwrap_or_else(|| {
+        if has_flag("--stable-sky") { DEFAULT_SKY_SEED } else { rand::random() }
+    });
+    println!("Sky seed: {}", sky_seed);
+    // --star-temperature <K> mueve la temperatura media de las estrellas (cielo más azul o más rojo)
+    let star_temperature = StarTemperature::default();
+    let sky_config = SkyConfig {
+        temperature: StarTemperature {
+            mean: parse_arg("--star-temperature").unwrap_or(star_temperature.mean),
+            ..star_temperature
         },
-        CelestialBody {
-            position: Vec3::new(18.0, 0.0, 2.0),
-            scale: 0.2,
-            rotation: Vec3::new(0.0, 0.0, 0.0),
-            shader_type: PlanetType::Moon,
-            orbital_distance: 2.0,
-            orbital_speed: 0.03,
-            trail: Trail::new(50),
-        },
-        CelestialBody {
-            position: Vec3::new(-20.0, 0.0, -20.0),
-            scale: 4.0,
-            rotation: Vec3::new(0.0, 0.0, 0.0),
-            shader_type: PlanetType::BlackHole,
-            orbital_distance: 280.0,
-            orbital_speed: 0.0001,
-            trail: Trail::new(50000),
-        },
-        CelestialBody {
-            position: Vec3::new(-10.0, 0.0, -10.0),
-            scale: 20.5,
-            rotation: Vec3::new(0.0, 0.0, 0.0),
-            shader_type: PlanetType::Rei,
-            orbital_distance: 140.0,
-            orbital_speed: 0.0,
-            trail: Trail::new(5),
+        ..SkyConfig::default()
+    };
+    // Con --cubemap <carpeta> el fondo sale de px/nx/py/ny/pz/nz.png; si falla, estrellas
+    let skybox = match parse_arg::<String>("--cubemap") {
+        Some(directory) => {
+            let paths = CUBEMAP_FACE_NAMES.map(|face| format!("{}/{}.png", directory, face));
+            Skybox::from_cubemap(paths.each_ref().map(String::as_str)).unwrap_or_else(|err| {
+                eprintln!("No se pudo cargar el cubemap de {}: {}", directory, err);
+                Skybox::from_sky_seed(sky_seed, sky_config)
+            })
         },
-    ];
-
-    // Cargar el modelo de la nave (asegúrate de tener un modelo .obj de una nave)
-    let spaceship = Spaceship {
-        model: Obj::load("assets/models/nave.obj").expect("Failed to load spaceship"),
-        scale: 0.02,
-        offset: Vec3::new(0.0, -0.1, -1.0),
+        None => Skybox::from_sky_seed(sky_seed, sky_config),
     };
-    let spaceship_vertices = spaceship.model.get_vertex_array();
 
-    let frustum = Frustum::new(
-        45.0,           // FOV en grados
-        0.1,            // Near plane
-        1000.0,         // Far plane
-        window_width as f32 / window_height as f32  // Aspect ratio
-    );
+    let mut celestial_bodies = load_bodies();
+
+    // El Sol (la fuente de luz y el foco de las órbitas) está donde lo coloque la escena
+    let sun_position = celestial_bodies.iter()
+        .find(|body| matches!(body.shader_type, PlanetType::Sun))
+        .map(|sun| sun.position)
+        .unwrap_or(Vec3::new(0.0, 0.0, 0.0));
+
+    let mut renderer = Renderer::new(&framebuffer, skybox);
+    if has_flag("--generated-spheres") {
+        renderer.use_generated_spheres();
+    }
+    // --bake-surfaces hornea una sola vez la superficie de los cuerpos estáticos
+    if has_flag("--bake-surfaces") {
+        renderer.bake_surfaces(&celestial_bodies);
+    }
+
+    // Portales de agujero de gusano (entrada, salida, radio)
+    let wormholes = vec![
+        Wormhole::new(Vec3::new(0.0, 6.0, 15.0), Vec3::new(150.0, 10.0, 60.0), 2.5),
+        Wormhole::new(Vec3::new(-40.0, -5.0, 20.0), Vec3::new(-200.0, 30.0, -200.0), 3.0),
+    ];
 
-    // Cargar el modelo de Rei
-    let rei_model = Obj::load("assets/models/Rei_A-Pose_2.obj").expect("Failed to load Rei model");
-    let rei_vertices = rei_model.get_vertex_array();
+    let mut comet_shower = CometShower::new(12, 150.0, 200.0, sky_seed);
+    let mut asteroid_belt = AsteroidBelt::new(ASTEROID_COUNT, ASTEROID_BELT_RADII.0, ASTEROID_BELT_RADII.1, ASTEROID_BELT_SEED);
+    asteroid_belt.update(simulation_time, sun_position);
+    let mut cull_log_timer = std::time::Instant::now();
+    let mut frames_since_log = 0;
+    let mut frame_stats = FrameStats::new();
+    let mut exhaust = Trail::new(EXHAUST_MAX_PARTICLES);
+    let mut previous_engine_position = renderer.spaceship.engine_position(&camera);
+
+    // Controles de la nave desde assets/keys.toml (las acciones que no aparecen, o
+    // todas si no se puede leer, usan las teclas de siempre)
+    let key_bindings = KeyBindings::load(KEY_BINDINGS_PATH).unwrap_or_else(|error| {
+        eprintln!("Usando los controles por defecto ({})", error);
+        KeyBindings::default()
+    });
 
     while window.is_open() {
-        if window.is_key_down(Key::Escape) {
+        if any_down(&window, &key_bindings.quit) {
             break;
         }
 
-        time += 1;
+        // Cámara lenta suave mientras se mantiene `bullet_time` (Z por defecto).
+        // `pause` (Espacio) pausa la simulación; `time_slower` y `time_faster` ([ y ])
+        // reducen a la mitad o duplican su velocidad.
+        // La cámara se sigue moviendo durante la pausa
+        if any_pressed(&window, &key_bindings.pause, KeyRepeat::No) {
+            paused = !paused;
+        }
+        if any_pressed(&window, &key_bindings.time_slower, KeyRepeat::No) {
+            time_multiplier = (time_multiplier * 0.5).max(MIN_TIME_MULTIPLIER);
+        }
+        if any_pressed(&window, &key_bindings.time_faster, KeyRepeat::No) {
+            time_multiplier = (time_multiplier * 2.0).min(MAX_TIME_MULTIPLIER);
+        }
+
+        // Tiempo real transcurrido desde el frame anterior, acumulado para la simulación
+        let now = std::time::Instant::now();
+        let elapsed = now.duration_since(last_frame).as_secs_f32();
+        frame_stats.record(elapsed);
+        let frame_time = elapsed.min(MAX_FRAME_TIME);
+        last_frame = now;
+        accumulator += frame_time;
+
+        let time_scale_target = if any_down(&window, &key_bindings.bullet_time) { BULLET_TIME_SCALE } else { 1.0 } * time_multiplier;
+        while accumulator >= FIXED_DT {
+            accumulator -= FIXED_DT;
+
+            time_scale = ease_time_scale(time_scale, time_scale_target, TIME_SCALE_EASE);
+            let time_step = if paused { 0.0 } else { time_scale };
+            simulation_time += time_step;
+
+            // Actualizar posiciones de los planetas
+            let previous_positions: Vec<Vec3> = celestial_bodies.iter()
+                .map(|body| body.position)
+                .collect();
+
+            update_orbits(&mut celestial_bodies, sun_position, simulation_time);
+
+            comet_shower.update(FIXED_DT * time_step, sun_position);
+            asteroid_belt.update(simulation_time, sun_position);
+
+            // Actualizar las estelas al final del paso
+            for (body, previous_position) in celestial_bodies.iter_mut().zip(&previous_positions) {
+                body.trail.update(FIXED_DT * time_step);
+                if let Some(tail) = &mut body.comet_tail {
+                    tail.update(FIXED_DT * time_step, body.position, sun_position);
+                }
+            
+                let palette_color = 0xFF000000 | body.shader_type.palette_color().to_hex();
+
+                let color = if body.speed_colored_trail {
+                    let speed = (body.position - previous_position).magnitude();
+                    0xFF000000 | speed_to_color(speed, TRAIL_SPEED_MIN, TRAIL_SPEED_MAX).to_hex()
+                } else {
+                    palette_color
+                };
+            
+                // En pausa los cuerpos no se mueven: no apilar partículas en el mismo punto
+                if !paused {
+                    let is_moon = matches!(body.shader_type, PlanetType::Moon);
+                    body.trail.add_segment(*previous_position, body.position, color, is_moon);
+                }
+            }
+        }
+
+        let time = simulation_time as u32;
         
         // Actualizar la cámara antes de manejar el input
-        camera.update_warp(0.016); // 60 FPS aproximadamente
-        handle_input(&window, &mut camera, &celestial_bodies);
+        camera.update_warp(frame_time);
+        let previous_eye = camera.eye;
+        handle_input(&window, &key_bindings, &mut camera, &celestial_bodies, &mut input, frame_time);
+        camera.update_chase(frame_time);
+        // Desplazamiento por paso fijo, como las velocidades de la cámara
+        let camera_velocity = if frame_time > 0.0 { (camera.eye - previous_eye) * (FIXED_DT / frame_time) } else { Vec3::zeros() };
+
+        // Mientras se acelera hacia adelante los motores brillan según la velocidad y
+        // sueltan partículas en la cola de la nave, que quedan atrás en el espacio
+        let thrusting = any_down(&window, &key_bindings.forward) && !camera.bird_eye_active && !camera.warp_state.is_active;
+        let engine_position = renderer.spaceship.engine_position(&camera);
+        exhaust.update(frame_time);
+        if thrusting {
+            exhaust.emit(previous_engine_position, engine_position, 0xFF000000 | EXHAUST_COLOR.to_hex(), EXHAUST_LIFETIME, EXHAUST_SPAWN_INTERVAL, frame_time);
+        } else {
+            exhaust.spawn_timer = 0.0;
+        }
+        previous_engine_position = engine_position;
+        let glow_target = if thrusting { (camera.velocity.magnitude() * FIXED_DT / camera.speed).min(1.0) } else { 0.0 };
+        renderer.engine_glow += (glow_target - renderer.engine_glow) * (1.0 - (-ENGINE_GLOW_RESPONSE * frame_time).exp());
+
+        // Atravesar el anillo de entrada de un agujero de gusano lleva a su salida
+        if !camera.warp_state.is_active {
+            if let Some(exit) = wormhole_warp(&wormholes, &previous_eye, &camera.eye) {
+                let duration = warp_duration(&camera.eye, &exit);
+                camera.start_warp(exit, camera.get_forward(), duration);
+            }
+        }
+
+        if any_pressed(&window, &key_bindings.toon, KeyRepeat::No) {
+            renderer.uniforms.toon_enabled = !renderer.uniforms.toon_enabled;
+        }
+        if any_pressed(&window, &key_bindings.poster, KeyRepeat::No) {
+            renderer.uniforms.silhouette_mode = !renderer.uniforms.silhouette_mode;
+        }
+        if any_pressed(&window, &key_bindings.trail_style, KeyRepeat::No) {
+            renderer.trail_style = match renderer.trail_style {
+                TrailStyle::Points => TrailStyle::Ribbon,
+                TrailStyle::Ribbon => TrailStyle::Points,
+            };
+        }
+        if any_pressed(&window, &key_bindings.orbits, KeyRepeat::No) {
+            renderer.show_orbits = !renderer.show_orbits;
+        }
+        if any_pressed(&window, &key_bindings.comet_shower, KeyRepeat::No) {
+            comet_shower.trigger(sun_position);
+        }
+        if any_pressed(&window, &key_bindings.render_mode, KeyRepeat::No) {
+            renderer.context.render_mode = renderer.context.render_mode.next();
+        }
+        if any_pressed(&window, &key_bindings.fps, KeyRepeat::No) {
+            fps_visible = !fps_visible;
+        }
+        if any_pressed(&window, &key_bindings.minimap, KeyRepeat::No) {
+            minimap_visible = !minimap_visible;
+        }
+        if any_pressed(&window, &key_bindings.interface, KeyRepeat::No) {
+            ui_visible = !ui_visible;
+        }
+        if any_pressed(&window, &key_bindings.bloom, KeyRepeat::No) {
+            bloom_enabled = !bloom_enabled;
+        }
+        if any_pressed(&window, &key_bindings.hdr, KeyRepeat::No) {
+            let enabled = !framebuffer.hdr_enabled();
+            framebuffer.set_hdr(enabled);
+            renderer.uniforms.sun_emission = if enabled { SUN_HDR_EMISSION } else { 1.0 };
+        }
+        if any_pressed(&window, &key_bindings.chase_camera, KeyRepeat::No) {
+            camera.toggle_chase();
+        }
+        // El cursor solo se oculta: minifb no lo atrapa en la ventana, así que en el
+        // borde el giro continúa por sí solo (ver MOUSE_EDGE_BAND)
+        if any_pressed(&window, &key_bindings.mouse_look, KeyRepeat::No) {
+            input.mouse_look.toggle();
+            window.set_cursor_visibility(!input.mouse_look.captured);
+        }
+        if any_pressed(&window, &key_bindings.vignette, KeyRepeat::No) {
+            vignette_enabled = !vignette_enabled;
+        }
+        if any_pressed(&window, &key_bindings.aberration, KeyRepeat::No) {
+            aberration_enabled = !aberration_enabled;
+        }
+        if any_pressed(&window, &key_bindings.collisions, KeyRepeat::No) {
+            input.collisions = !input.collisions;
+        }
         
         framebuffer.clear();
+        overlay.clear();
         
-        // 1. Primero renderizar el skybox (fondo)
-        skybox.render(&mut framebuffer, &uniforms, camera.eye);
+        let world = World {
+            bodies: &celestial_bodies,
+            wormholes: &wormholes,
+            asteroids: &asteroid_belt.asteroids,
+            comets: &comet_shower.comets,
+            exhaust: &exhaust.particles,
+        };
+        renderer.draw_scene(&mut framebuffer, &camera, &world, simulation_time);
+
+        // Cada segundo se imprime un resumen con el promedio por frame de triángulos
+        // dibujados, fragmentos y triángulos descartados por el backface culling
+        frames_since_log += 1;
+        if cull_log_timer.elapsed().as_secs_f32() >= 1.0 {
+            let context = &mut renderer.context;
+            println!(
+                "{:.0} FPS ({:.2} ms) | por frame: {} triángulos, {} fragmentos, {} descartados por backface culling",
+                frame_stats.fps(),
+                frame_stats.average_frame_time * 1000.0,
+                context.drawn_triangles / frames_since_log,
+                context.drawn_fragments / frames_since_log,
+                context.culled_triangles / frames_since_log,
+            );
+            context.culled_triangles = 0;
+            context.drawn_triangles = 0;
+            context.drawn_fragments = 0;
+            frames_since_log = 0;
+            cull_log_timer = std::time::Instant::now();
+        }
+
+        // Aviso de colisión inminente (no aplica durante warps, en vista aérea ni sin colisiones)
+        if input.collisions && !camera.warp_state.is_active && !camera.bird_eye_active {
+            let imminent = collision_imminent(&camera.eye, &camera_velocity, &celestial_bodies, COLLISION_LOOKAHEAD_FRAMES);
+            if imminent.is_some() {
+                render_collision_warning(&mut overlay);
+            }
+        }
 
-        uniforms.camera_position = camera.eye;  // Actualizar posición de la cámara
-        let camera_forward = camera.get_forward();
+        render_labels(&mut overlay, &framebuffer, &renderer.uniforms, &celestial_bodies);
+        render_hud(&mut overlay, &camera, &camera_velocity, &celestial_bodies, input.collisions);
+        if fps_visible {
+            render_frame_stats(&mut overlay, &frame_stats);
+        }
+        if minimap_visible {
+            render_minimap(&mut overlay, &camera, &celestial_bodies, &sun_position);
+        }
 
-        // Renderizar planetas con culling
-        for body in &celestial_bodies {
-            let apparent_radius = body.scale * 2.0;
-            
-            if frustum.is_visible(&camera.eye, &camera_forward, &body.position, apparent_radius) {
-                uniforms.model_matrix = create_model_matrix(
-                    body.position,
-                    body.scale,
-                    body.rotation + Vec3::new(0.0, time as f32 * 0.01, 0.0)
+        // Aviso parpadeante cuando un cuerpo eclipsa a otro
+        if let Some(&(occluder, occluded)) = detect_eclipses(&celestial_bodies, &sun_position).first() {
+            if (time / 30).is_multiple_of(2) {
+                let notice = format!(
+                    "Eclipse: {} sobre {}",
+                    celestial_bodies[occluder].name,
+                    celestial_bodies[occluded].name
                 );
-                uniforms.view_matrix = create_view_matrix(camera.eye, camera.center, camera.up);
-                uniforms.time = time;
-                
-                // Usar los vértices correspondientes según el tipo
-                let vertices = match body.shader_type {
-                    PlanetType::Rei => &rei_vertices,
-                    _ => &vertex_arrays,
-                };
-                
-                render(&mut framebuffer, &uniforms, vertices, &body.shader_type);
+                let x = overlay.width.saturating_sub(text_width(&notice)) / 2;
+                overlay.draw_text(x, 12, &notice, 0xFFD8C070);
             }
         }
 
-        // Solo renderizar la nave si no estamos en vista aérea
-        if !camera.bird_eye_active {
-            let ship_position = camera.eye 
-                + camera.get_forward() * spaceship.offset.z 
-                + camera.get_up() * spaceship.offset.y
-                + camera.get_right() * spaceship.offset.x;
-            
-            uniforms.model_matrix = create_model_matrix(
-                ship_position,
-                spaceship.scale,
-                Vec3::new(
-                    0.0,
-                    -camera.yaw + PI * 1.5,
-                    camera.roll
-                )
-            );
-            uniforms.view_matrix = create_view_matrix(camera.eye, camera.center, camera.up);
-            
-            render(&mut framebuffer, &uniforms, &spaceship_vertices, &PlanetType::Spaceship);
-        }
-
-        // Actualizar posiciones de los planetas
-        let earth_position = celestial_bodies.iter()
-            .find(|b| matches!(b.shader_type, PlanetType::Earth))
-            .map(|earth| earth.position)
-            .unwrap_or(Vec3::new(0.0, 0.0, 0.0));
-
-        for body in &mut celestial_bodies {
-            match body.shader_type {
-                PlanetType::Sun => (), // El sol no se mueve
-                PlanetType::Moon => {
-                    let moon_angle = time as f32 * body.orbital_speed;
-                    body.position = earth_position + Vec3::new(
-                        body.orbital_distance * moon_angle.cos(),
-                        0.0,
-                        body.orbital_distance * moon_angle.sin()
-                    );
-                },
-                PlanetType::BlackHole => {
-                    let angle = time as f32 * body.orbital_speed;
-                    body.position.x = body.orbital_distance * angle.cos();
-                    body.position.z = body.orbital_distance * angle.sin();
-                },
-                _ => {
-                    let angle = time as f32 * body.orbital_speed;
-                    body.position.x = body.orbital_distance * angle.cos();
-                    body.position.z = body.orbital_distance * angle.sin();
+        // Tooltip del cuerpo bajo el cursor (solo si no se está arrastrando ni mirando con el ratón)
+        if let Some((mouse_x, mouse_y)) = window.get_mouse_pos(MouseMode::Discard) {
+            if !window.get_mouse_down(MouseButton::Left) && !input.mouse_look.captured {
+                let screen_size = (framebuffer_width as f32, framebuffer_height as f32);
+                let hovered = screen_to_body(mouse_x, mouse_y, screen_size, &camera, &renderer.uniforms.projection_matrix, &celestial_bodies);
+                if let Some(index) = hovered {
+                    let lines = tooltip_lines(&celestial_bodies[index], &sun_position);
+                    render_tooltip(&mut overlay, mouse_x as usize, mouse_y as usize, &lines);
                 }
             }
         }
 
-        // Primero renderizar las estelas
-        for body in &celestial_bodies {
-            for particle in &body.trail.particles {
-                render_trail(&mut framebuffer, &uniforms, particle);
-            }
+        // Promediar las muestras a la resolución de la ventana; la interfaz se compone
+        // encima al final, salvo que esté oculta con H
+        framebuffer.tone_map(HDR_EXPOSURE);
+        framebuffer.resolve();
+        if bloom_enabled {
+            framebuffer.apply_bloom(BLOOM_THRESHOLD, BLOOM_RADIUS, BLOOM_INTENSITY);
+        }
+        let distortion = black_hole_distortion(&camera.view_eye(), &celestial_bodies);
+        if vignette_enabled {
+            framebuffer.apply_vignette(distortion * VIGNETTE_MAX_STRENGTH);
+        }
+        if aberration_enabled {
+            framebuffer.apply_chromatic_aberration(distortion * CHROMATIC_ABERRATION_MAX_PIXELS);
+        }
+        if ui_visible {
+            composite_overlay(&mut framebuffer, &overlay);
         }
 
-        // Actualizar las estelas al final del frame
-        for body in &mut celestial_bodies {
-            body.trail.update(0.016);
-            
-            let color = match body.shader_type {
-                PlanetType::Sun => 0xFFFFAA00,     // Naranja brillante
-                PlanetType::Mercury => 0xFFAA8866,  // Marrón claro
-                PlanetType::Venus => 0xFFFFCC99,    // Amarillo pálido
-                PlanetType::Earth => 0xFF0066FF,    // Azul brillante
-                PlanetType::Mars => 0xFFFF3300,     // Rojo anaranjado
-                PlanetType::Jupiter => 0xFFFFAA66,  // Naranja suave
-                PlanetType::Saturn => 0xFFFFCC66,   // Dorado
-                PlanetType::Uranus => 0xFF66FFFF,   // Cyan claro
-                PlanetType::Neptune => 0xFF0066FF,  // Azul profundo
-                PlanetType::Moon => 0xFFCCCCCC,     // Gris claro
-                PlanetType::BlackHole => 0xFF440044, // Púrpura oscuro
-                PlanetType::Spaceship => 0xFFFFFFFF, // Blanco
-                PlanetType::Rei => 0xFFFF69B4,      // Rosa (Hot Pink)
-            };
-            
-            let is_moon = matches!(body.shader_type, PlanetType::Moon);
-            body.trail.add_particle(body.position, color, is_moon);
+        // `screenshot` (F12 por defecto) guarda lo que se ve en pantalla como PNG con marca de tiempo
+        if any_pressed(&window, &key_bindings.screenshot, KeyRepeat::No) {
+            let path = screenshot_path();
+            match framebuffer.save_png(&path) {
+                Ok(()) => println!("Captura guardada en {}", path),
+                Err(error) => eprintln!("No se pudo guardar la captura {}: {}", path, error),
+            }
         }
 
         window
-            .update_with_buffer(&framebuffer.buffer, framebuffer_width, framebuffer_height)
+            .update_with_buffer(&framebuffer.resolved, framebuffer_width, framebuffer_height)
             .unwrap();
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mouse_edge_push_turns_only_near_the_edges() {
+        let width = 800.0;
+        assert_eq!(mouse_edge_push(width / 2.0, width), 0.0);
+        assert_eq!(mouse_edge_push(0.0, width), -1.0);
+        assert_eq!(mouse_edge_push(width - 1.0, width), 1.0);
+
+        // Dentro de la franja el giro crece hacia el borde
+        let (outer, inner) = (mouse_edge_push(width - 5.0, width), mouse_edge_push(width - 30.0, width));
+        assert!(outer > inner && inner > 0.0);
+    }
+
+    #[test]
+    fn larger_body_keeps_more_detail_at_the_same_distance() {
+        let lod_table = LodTable::default();
+        let distance = 12.0;
+        // A 12 unidades, Júpiter (radio 1.5) está a 8 radios y la Luna (radio 0.2) a 60
+        assert_eq!(calculate_detail_level(distance, 1.5, &lod_table), 1);
+        assert_eq!(calculate_detail_level(distance, 0.2, &lod_table), 2);
+        assert!(calculate_detail_level(distance, 1.5, &lod_table) < calculate_detail_level(distance, 0.2, &lod_table));
+
+        // Muy cerca cualquier cuerpo usa el máximo detalle
+        assert_eq!(calculate_detail_level(0.5, 0.2, &lod_table), 0);
+    }
+
+    #[test]
+    fn speed_ramp_goes_from_blue_to_red() {
+        let rgb = |color: Color| (color.r, color.g, color.b);
+        assert_eq!(rgb(speed_to_color(TRAIL_SPEED_MIN, TRAIL_SPEED_MIN, TRAIL_SPEED_MAX)), (0, 0, 255));
+        assert_eq!(rgb(speed_to_color(TRAIL_SPEED_MAX, TRAIL_SPEED_MIN, TRAIL_SPEED_MAX)), (255, 0, 0));
+        // Fuera del rango se queda en los extremos, y a la mitad está el verde
+        assert_eq!(rgb(speed_to_color(0.0, TRAIL_SPEED_MIN, TRAIL_SPEED_MAX)), (0, 0, 255));
+        assert_eq!(rgb(speed_to_color(1.0, TRAIL_SPEED_MIN, TRAIL_SPEED_MAX)), (255, 0, 0));
+        assert_eq!(rgb(speed_to_color(0.03, TRAIL_SPEED_MIN, TRAIL_SPEED_MAX)), (0, 255, 0));
+    }
+
+    #[test]
+    fn sun_pulse_is_periodic_and_bounded() {
+        let period = (2.0 * PI / SUN_PULSE_FREQUENCY).round() as u32;
+        for time in (0..3 * period).step_by(7) {
+            let pulse = sun_pulse(time);
+            assert!((1.0 - SUN_PULSE_AMPLITUDE..=1.0 + SUN_PULSE_AMPLITUDE).contains(&pulse));
+            assert!((pulse - sun_pulse(time + period)).abs() < 1e-3);
+        }
+
+        // En un periodo recorre todo el rango
+        let pulses: Vec<f32> = (0..period).map(sun_pulse).collect();
+        let (min, max) = pulses.iter().fold((f32::MAX, f32::MIN), |(min, max), &p| (min.min(p), max.max(p)));
+        assert!(min < 1.0 - SUN_PULSE_AMPLITUDE * 0.99 && max > 1.0 + SUN_PULSE_AMPLITUDE * 0.99);
+    }
+
+    // Cuerpos de una escena escrita en el formato de assets/scene.toml
+    fn scene_bodies(text: &str) -> Vec<CelestialBody> {
+        SceneConfig::parse(text).unwrap().bodies.into_iter()
+            .enumerate()
+            .map(|(index, config)| CelestialBody::from_config(index, config))
+            .collect()
+    }
+
+    #[test]
+    fn collision_warning_hit_vs_diverge() {
+        // Radio de colisión 2, con la superficie a 8 unidades del origen
+        let bodies = scene_bodies("[[body]]\ntype = \"earth\"\nposition = [10.0, 0.0, 0.0]\nscale = 1.0");
+        let origin = Vec3::new(0.0, 0.0, 0.0);
+        let lookahead = COLLISION_LOOKAHEAD_FRAMES;
+
+        assert_eq!(collision_imminent(&origin, &Vec3::new(1.0, 0.0, 0.0), &bodies, lookahead), Some(0));
+        // Pasando de largo, alejándose o demasiado lento para llegar a tiempo no hay aviso
+        assert_eq!(collision_imminent(&origin, &Vec3::new(0.0, 1.0, 0.0), &bodies, lookahead), None);
+        assert_eq!(collision_imminent(&origin, &Vec3::new(-1.0, 0.0, 0.0), &bodies, lookahead), None);
+        assert_eq!(collision_imminent(&origin, &Vec3::new(0.1, 0.0, 0.0), &bodies, lookahead), None);
+    }
+
+    // Uniforms para dibujar en `framebuffer` con la cámara en +z mirando al origen
+    fn camera_uniforms(framebuffer: &Framebuffer, distance: f32) -> Uniforms {
+        let (width, height) = (framebuffer.width as f32, framebuffer.height as f32);
+        let mut uniforms = Uniforms::new(create_perspective_matrix(width, height), create_viewport_matrix(width, height));
+        uniforms.camera_position = Vec3::new(0.0, 0.0, distance);
+        uniforms.view_matrix = create_view_matrix(uniforms.camera_position, Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        uniforms.sun_position = Vec3::new(10.0, 0.0, 10.0);
+        uniforms
+    }
+
+    fn scratch_capacities(context: &RenderContext) -> [usize; 4] {
+        [
+            context.transformed_vertices.capacity(),
+            context.triangles.capacity(),
+            context.fragments.capacity(),
+            context.colors.capacity(),
+        ]
+    }
+
+    #[test]
+    fn scratch_buffers_stop_growing_after_the_largest_draw() {
+        let mut framebuffer = Framebuffer::new(64, 64, DepthFormat::Float32);
+        let mut context = RenderContext::new(framebuffer.width * framebuffer.height);
+        let fragment_capacity = context.fragments.capacity();
+
+        // El primer cuerpo es el más grande: la malla más fina, lo bastante lejos para
+        // que se vea casi la mitad de sus triángulos
+        let largest = Obj::uv_sphere(32, 64).get_indexed_mesh();
+        let uniforms = camera_uniforms(&framebuffer, 20.0);
+        render(&mut framebuffer, &mut context, &uniforms, &largest, &PlanetType::Mars, RenderPass::Opaque, Surface::default());
+        let capacities = scratch_capacities(&context);
+        // Los buffers por fragmento reservados alcanzaron sin crecer
+        assert_eq!(capacities[2..], [fragment_capacity; 2]);
+
+        let bodies = [
+            (Obj::uv_sphere(12, 24).get_indexed_mesh(), 3.0),
+            (Obj::icosphere(1).get_indexed_mesh(), 8.0),
+            (Obj::fallback_box(Vec3::new(0.5, 0.3, 0.8)).get_indexed_mesh(), 4.0),
+            (Obj::uv_sphere(32, 64).get_indexed_mesh(), 2.5),
+            (Obj::icosphere(3).get_indexed_mesh(), 1.5),
+        ];
+        for _ in 0..3 {
+            for (mesh, distance) in &bodies {
+                framebuffer.clear();
+                let uniforms = camera_uniforms(&framebuffer, *distance);
+                render(&mut framebuffer, &mut context, &uniforms, mesh, &PlanetType::Mars, RenderPass::Opaque, Surface::default());
+                assert!(!context.fragments.is_empty());
+                assert_eq!(scratch_capacities(&context), capacities);
+            }
+        }
+    }
+
+    #[test]
+    fn bodies_fade_across_the_draw_distance_band() {
+        let draw_distance = DrawDistance { distance: 600.0, fade_band: 150.0 };
+        assert_eq!(draw_distance_fade(100.0, &draw_distance), Some(1.0));
+        assert_eq!(draw_distance_fade(450.0, &draw_distance), Some(1.0));
+        assert_eq!(draw_distance_fade(525.0, &draw_distance), Some(0.5));
+        assert_eq!(draw_distance_fade(600.0, &draw_distance), None);
+
+        // La opacidad baja sin saltos a lo largo de la banda
+        let fades: Vec<f32> = (450..600).map(|d| draw_distance_fade(d as f32, &draw_distance).unwrap()).collect();
+        assert!(fades.windows(2).all(|pair| pair[1] <= pair[0] && pair[0] - pair[1] < 0.01));
+
+        // Sin banda el corte es directo
+        let hard = DrawDistance { distance: 600.0, fade_band: 0.0 };
+        assert_eq!(draw_distance_fade(599.0, &hard), Some(1.0));
+    }
+
+    #[test]
+    fn tooltip_shows_the_hovered_body_and_nothing_over_empty_space() {
+        let bodies = scene_bodies("[[body]]\ntype = \"earth\"\nname = \"Tierra\"\nposition = [0.0, 0.0, 0.0]\nscale = 1.0");
+        let camera = Camera::new(Vec3::new(0.0, 0.0, 10.0), Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        let screen_size = (800.0, 600.0);
+        let projection = create_perspective_matrix(screen_size.0, screen_size.1);
+
+        let hovered = screen_to_body(400.0, 300.0, screen_size, &camera, &projection, &bodies);
+        assert_eq!(hovered, Some(0));
+        let sun_position = Vec3::new(0.0, 0.0, -20.0);
+        assert_eq!(tooltip_lines(&bodies[0], &sun_position), ["Tierra", "Escala: 1.00", "Dist. al Sol: 20.0"]);
+
+        // En una esquina solo hay espacio vacío
+        assert_eq!(screen_to_body(5.0, 5.0, screen_size, &camera, &projection, &bodies), None);
+    }
+
+    #[test]
+    fn trail_taper_is_monotone() {
+        let widths: Vec<f32> = (0..=20).map(|i| ribbon_width(RIBBON_WIDTH, RIBBON_TAPER, i as f32 / 20.0)).collect();
+        assert_eq!(widths[0], RIBBON_WIDTH);
+        assert!(widths.windows(2).all(|pair| pair[1] < pair[0]));
+        assert!((widths[20] - RIBBON_WIDTH * (1.0 - RIBBON_TAPER)).abs() < 1e-6);
+
+        // Sin afinado el ancho es constante, y el afinado nunca da un ancho negativo
+        assert_eq!(ribbon_width(RIBBON_WIDTH, 0.0, 1.0), RIBBON_WIDTH);
+        assert_eq!(ribbon_width(RIBBON_WIDTH, 2.0, 1.0), 0.0);
+    }
+
+    #[test]
+    fn bullet_time_eases_in_and_out() {
+        // Al mantener la tecla la escala baja sin saltos y sin pasarse del objetivo
+        let mut time_scale = 1.0;
+        assert!(ease_time_scale(time_scale, BULLET_TIME_SCALE, TIME_SCALE_EASE) < time_scale);
+        for _ in 0..200 {
+            let next = ease_time_scale(time_scale, BULLET_TIME_SCALE, TIME_SCALE_EASE);
+            assert!(next <= time_scale && next >= BULLET_TIME_SCALE);
+            assert!(time_scale - next <= (1.0 - BULLET_TIME_SCALE) * TIME_SCALE_EASE + 1e-6);
+            time_scale = next;
+        }
+        assert!((time_scale - BULLET_TIME_SCALE).abs() < 1e-3);
+
+        // Al soltarla vuelve a la velocidad normal de la misma forma
+        for _ in 0..200 {
+            let next = ease_time_scale(time_scale, 1.0, TIME_SCALE_EASE);
+            assert!(next >= time_scale && next <= 1.0);
+            time_scale = next;
+        }
+        assert!((time_scale - 1.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn collinear_bodies_make_an_eclipse() {
+        let scene = |moon_y: f32| scene_bodies(&format!(
+            "[[body]]\ntype = \"sun\"\n[[body]]\ntype = \"earth\"\nposition = [20.0, 0.0, 0.0]\nscale = 0.7\n\
+             [[body]]\ntype = \"moon\"\nposition = [18.0, {}, 0.0]\nscale = 0.2",
+            moon_y,
+        ));
+        let sun_position = Vec3::new(0.0, 0.0, 0.0);
+
+        // Sol, Luna y Tierra alineados: la Luna oculta a la Tierra
+        assert_eq!(detect_eclipses(&scene(0.0), &sun_position), vec![(2, 1)]);
+        // Casi alineados, dentro de la suma de radios, todavía cuenta
+        assert_eq!(detect_eclipses(&scene(0.8), &sun_position), vec![(2, 1)]);
+        // Fuera del eje no hay eclipse
+        assert!(detect_eclipses(&scene(3.0), &sun_position).is_empty());
+    }
+
+
+    #[test]
+    fn transparent_bodies_are_drawn_after_every_opaque_one() {
+        let mut bodies = scene_bodies("\
+[[body]]
+type = \"earth\"
+position = [0.0, 0.0, -20.0]
+
+[[body]]
+type = \"mars\"
+position = [30.0, 0.0, 0.0]
+
+[[body]]
+type = \"jupiter\"
+position = [0.0, 0.0, 520.0]
+
+[[body]]
+type = \"moon\"
+position = [-30.0, 0.0, 0.0]
+");
+        // El primero de la lista es transparente y el tercero se desvanece por la distancia
+        bodies[0].render_pass = RenderPass::Transparent;
+        let eye = Vec3::new(0.0, 0.0, 0.0);
+        let draw_distance = DrawDistance::default();
+
+        let drawn: Vec<(usize, RenderPass)> = RenderPass::ORDER.iter()
+            .flat_map(|&pass| bodies_in_pass(&bodies, pass, eye, &draw_distance).map(move |(index, _, _)| (index, pass)))
+            .collect();
+        assert_eq!(drawn, vec![
+            (1, RenderPass::Opaque),
+            (3, RenderPass::Opaque),
+            (0, RenderPass::Transparent),
+            (2, RenderPass::Transparent),
+        ]);
+    }
+
+
+    #[test]
+    fn crossing_a_wormhole_ring_warps_to_its_exit() {
+        let wormholes = [
+            Wormhole::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 100.0), 2.0),
+            Wormhole::new(Vec3::new(50.0, 0.0, 0.0), Vec3::new(50.0, 80.0, 0.0), 1.0),
+        ];
+
+        // Atravesar el anillo de entrada lleva a la salida de ese mismo agujero
+        let exit = wormhole_warp(&wormholes, &Vec3::new(0.5, 0.5, -0.2), &Vec3::new(0.5, 0.5, 0.3));
+        assert_eq!(exit, Some(wormholes[0].exit));
+        let exit = wormhole_warp(&wormholes, &Vec3::new(50.2, -0.5, 0.0), &Vec3::new(50.2, 0.5, 0.0));
+        assert_eq!(exit, Some(wormholes[1].exit));
+
+        // Pasar justo por fuera del anillo no activa el warp
+        assert_eq!(wormhole_warp(&wormholes, &Vec3::new(2.2, 0.0, -0.2), &Vec3::new(2.2, 0.0, 0.3)), None);
+        assert_eq!(wormhole_warp(&wormholes, &Vec3::new(51.5, -0.5, 0.0), &Vec3::new(51.5, 0.5, 0.0)), None);
+    }
+
+
+    #[test]
+    fn parallel_shading_matches_one_fragment_at_a_time() {
+        let framebuffer = Framebuffer::new(64, 64, DepthFormat::Float32);
+        let uniforms = camera_uniforms(&framebuffer, 3.0);
+        // Varios bloques de rayon, con el último incompleto
+        let fragments: Vec<Fragment> = (0..SHADING_CHUNK_SIZE * 3 + 17)
+            .map(|i| {
+                let angle = i as f32 * 0.01;
+                let point = Vec3::new(angle.cos(), (i as f32 * 0.003).sin(), angle.sin()).normalize();
+                Fragment::new(Vec2::new((i % 64) as f32, (i / 64 % 64) as f32), Color::black(), 0.5, point, 0.8, point, 1.0)
+            })
+            .collect();
+        let mut colors = vec![Vec3::zeros(); fragments.len()];
+        shade_fragments(&fragments, &mut colors, &uniforms, &PlanetType::Earth, Surface::default());
+
+        let gain = emission(&PlanetType::Earth, &uniforms);
+        for (fragment, color) in fragments.iter().zip(&colors) {
+            let shaded = fragment_shader(fragment, &uniforms, &PlanetType::Earth, Surface::default()).to_float() * gain;
+            assert_eq!(*color, apply_fog(shaded, fragment, &uniforms));
+        }
+    }
+}
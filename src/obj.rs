@@ -1,12 +1,48 @@
-use tobj;
+use std::collections::{HashMap, HashSet};
 use nalgebra_glm::{Vec2, Vec3};
 use crate::vertex::Vertex;
+use crate::color::Color;
+use crate::texture::{direction_to_uv, uv_to_direction};
 
 pub struct Obj {
     meshes: Vec<Mesh>,
+    materials: Vec<Material>,
 }
 
+// Material de un archivo .mtl; por ahora solo se usa el color difuso (`Kd`)
+#[derive(Clone)]
+pub struct Material {
+    pub diffuse: Color,
+}
+
+// Forma indexada del modelo: cada vértice aparece una sola vez y cada trío de
+// `indices` forma un triángulo, así los vértices compartidos se transforman una vez
+pub struct IndexedMesh {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
+}
+
+// El renderer usa la forma indexada; la lista plana queda para quien la prefiera
+#[allow(dead_code)]
+impl IndexedMesh {
+    // Lista plana de triángulos (tres vértices seguidos por cara)
+    pub fn to_vertex_array(&self) -> Vec<Vertex> {
+        self.indices.iter().map(|&index| self.vertices[index as usize].clone()).collect()
+    }
+}
+
+// Color de las caras sin material en un modelo que sí tiene .mtl
+const DEFAULT_DIFFUSE: Color = Color::new(192, 192, 192);
+
+// Veces que se subdivide el icosaedro de `Obj::fallback_sphere` (1280 triángulos)
+const FALLBACK_SPHERE_SUBDIVISIONS: u32 = 3;
+
+// Cada grupo (`o`/`g`) del archivo es un mesh; un grupo con varios `usemtl`
+// llega partido en varios meshes con el mismo nombre
 struct Mesh {
+    name: String,
+    // Índice en `Obj::materials`, compartido por todos los triángulos del mesh
+    material: Option<usize>,
     vertices: Vec<Vec3>,
     normals: Vec<Vec3>,
     texcoords: Vec<Vec2>,
@@ -15,15 +51,30 @@ struct Mesh {
 
 impl Obj {
     pub fn load(filename: &str) -> Result<Self, tobj::LoadError> {
-        let (models, _) = tobj::load_obj(filename, &tobj::LoadOptions {
+        let (models, materials) = tobj::load_obj(filename, &tobj::LoadOptions {
             single_index: true,
             triangulate: true,
             ..Default::default()
         })?;
+        // Sin el .mtl el modelo se carga igual, solo que sin colores propios
+        let materials: Vec<Material> = match materials {
+            Ok(materials) => materials.into_iter()
+                .map(|material| {
+                    let [r, g, b] = material.diffuse.unwrap_or([0.75, 0.75, 0.75]);
+                    Material { diffuse: Color::from_float(r, g, b) }
+                })
+                .collect(),
+            Err(error) => {
+                eprintln!("Sin materiales para {} ({}), se usan los colores por defecto", filename, error);
+                Vec::new()
+            }
+        };
 
         let meshes = models.into_iter().map(|model| {
             let mesh = model.mesh;
             Mesh {
+                name: model.name,
+                material: mesh.material_id.filter(|&id| id < materials.len()),
                 vertices: mesh.positions.chunks(3)
                     .map(|v| Vec3::new(v[0], v[1], v[2]))
                     .collect(),
@@ -37,26 +88,555 @@ impl Obj {
             }
         }).collect();
 
-        Ok(Obj { meshes })
+        // Los archivos exportados sin `vn` reciben normales suaves calculadas
+        let mut obj = Obj { meshes, materials };
+        for mesh in obj.meshes.iter_mut().filter(|mesh| mesh.normals.is_empty()) {
+            mesh.recompute_normals();
+        }
+        Ok(obj)
+    }
+
+    // Anillo plano triangulado en el plano XZ (normal +Y), entre los dos radios
+    pub fn annulus(inner_radius: f32, outer_radius: f32, segments: u32) -> Self {
+        let mut vertices = Vec::with_capacity(segments as usize * 2);
+        let mut normals = Vec::with_capacity(segments as usize * 2);
+        let mut texcoords = Vec::with_capacity(segments as usize * 2);
+        let mut indices = Vec::with_capacity(segments as usize * 6);
+
+        for i in 0..segments {
+            let angle = i as f32 / segments as f32 * std::f32::consts::TAU;
+            let (sin, cos) = angle.sin_cos();
+            let u = i as f32 / segments as f32;
+
+            vertices.push(Vec3::new(cos * inner_radius, 0.0, sin * inner_radius));
+            vertices.push(Vec3::new(cos * outer_radius, 0.0, sin * outer_radius));
+            normals.push(Vec3::new(0.0, 1.0, 0.0));
+            normals.push(Vec3::new(0.0, 1.0, 0.0));
+            texcoords.push(Vec2::new(u, 0.0));
+            texcoords.push(Vec2::new(u, 1.0));
+
+            let inner = i * 2;
+            let outer = inner + 1;
+            let next_inner = (inner + 2) % (segments * 2);
+            let next_outer = next_inner + 1;
+            indices.extend_from_slice(&[inner, next_inner, outer, outer, next_inner, next_outer]);
+        }
+
+        Obj {
+            meshes: vec![Mesh { name: String::from("annulus"), material: None, vertices, normals, texcoords, indices }],
+            materials: Vec::new(),
+        }
+    }
+
+    // Esfera de radio 1 que se usa cuando falta el modelo de un planeta
+    pub fn fallback_sphere() -> Self {
+        Self::icosphere(FALLBACK_SPHERE_SUBDIVISIONS)
+    }
+
+    // Esfera de radio 1 por paralelos y meridianos: `rings` bandas de latitud de polo a
+    // polo y `sectors` husos de longitud. La columna de la costura se repite con U = 1
+    // para que la textura no se pliegue, y cada polo tiene un vértice por huso
+    pub fn uv_sphere(rings: u32, sectors: u32) -> Self {
+        let (rings, sectors) = (rings.max(2), sectors.max(3));
+        let mut vertices = Vec::with_capacity(((rings + 1) * (sectors + 1)) as usize);
+        let mut texcoords = Vec::with_capacity(vertices.capacity());
+        let mut indices = Vec::with_capacity((rings * sectors * 6) as usize);
+
+        for ring in 0..=rings {
+            for sector in 0..=sectors {
+                let uv = Vec2::new(sector as f32 / sectors as f32, ring as f32 / rings as f32);
+                vertices.push(uv_to_direction(uv));
+                texcoords.push(uv);
+            }
+        }
+
+        let row = sectors + 1;
+        for ring in 0..rings {
+            for sector in 0..sectors {
+                let top = ring * row + sector;
+                let bottom = top + row;
+                // En la primera y la última banda uno de los dos triángulos degenera en el polo
+                if ring != 0 {
+                    indices.extend_from_slice(&[top, top + 1, bottom]);
+                }
+                if ring != rings - 1 {
+                    indices.extend_from_slice(&[bottom, top + 1, bottom + 1]);
+                }
+            }
+        }
+
+        // En la esfera unitaria la normal es la propia posición
+        let normals = vertices.clone();
+        Obj {
+            meshes: vec![Mesh { name: String::from("uv_sphere"), material: None, vertices, normals, texcoords, indices }],
+            materials: Vec::new(),
+        }
     }
 
+    // Esfera de radio 1 a partir de un icosaedro: cada cara se subdivide en cuatro
+    // `subdivisions` veces y los vértices nuevos se proyectan sobre la esfera, así
+    // todos los triángulos tienen casi el mismo tamaño (sin amontonarse en los polos)
+    pub fn icosphere(subdivisions: u32) -> Self {
+        let t = (1.0 + 5.0_f32.sqrt()) / 2.0;
+        let mut vertices: Vec<Vec3> = [
+            (-1.0, t, 0.0), (1.0, t, 0.0), (-1.0, -t, 0.0), (1.0, -t, 0.0),
+            (0.0, -1.0, t), (0.0, 1.0, t), (0.0, -1.0, -t), (0.0, 1.0, -t),
+            (t, 0.0, -1.0), (t, 0.0, 1.0), (-t, 0.0, -1.0), (-t, 0.0, 1.0),
+        ].iter().map(|&(x, y, z)| Vec3::new(x, y, z).normalize()).collect();
+        let mut indices: Vec<u32> = vec![
+            0, 11, 5, 0, 5, 1, 0, 1, 7, 0, 7, 10, 0, 10, 11,
+            1, 5, 9, 5, 11, 4, 11, 10, 2, 10, 7, 6, 7, 1, 8,
+            3, 9, 4, 3, 4, 2, 3, 2, 6, 3, 6, 8, 3, 8, 9,
+            4, 9, 5, 2, 4, 11, 6, 2, 10, 8, 6, 7, 9, 8, 1,
+        ];
+
+        for _ in 0..subdivisions {
+            // Cada arista se parte una sola vez aunque la compartan dos caras
+            let mut midpoints: HashMap<(u32, u32), u32> = HashMap::new();
+            let mut midpoint = |a: u32, b: u32, vertices: &mut Vec<Vec3>| {
+                *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                    vertices.push(((vertices[a as usize] + vertices[b as usize]) / 2.0).normalize());
+                    vertices.len() as u32 - 1
+                })
+            };
+
+            let mut subdivided = Vec::with_capacity(indices.len() * 4);
+            for triangle in indices.chunks_exact(3) {
+                let (a, b, c) = (triangle[0], triangle[1], triangle[2]);
+                let ab = midpoint(a, b, &mut vertices);
+                let bc = midpoint(b, c, &mut vertices);
+                let ca = midpoint(c, a, &mut vertices);
+                subdivided.extend_from_slice(&[a, ab, ca, b, bc, ab, c, ca, bc, ab, bc, ca]);
+            }
+            indices = subdivided;
+        }
+
+        let mut texcoords: Vec<Vec2> = vertices.iter().map(direction_to_uv).collect();
+        for triangle in indices.chunks_exact_mut(3) {
+            // Un triángulo que cruza la costura tiene esquinas con U cerca de 0 y de 1;
+            // las de U pequeña se duplican con U + 1 para que la textura no se recorra al revés
+            let wraps = triangle.iter().any(|&i| texcoords[i as usize].x > 0.75)
+                && triangle.iter().any(|&i| texcoords[i as usize].x < 0.25);
+            if wraps {
+                for index in triangle.iter_mut() {
+                    if texcoords[*index as usize].x < 0.5 {
+                        vertices.push(vertices[*index as usize]);
+                        texcoords.push(texcoords[*index as usize] + Vec2::new(1.0, 0.0));
+                        *index = vertices.len() as u32 - 1;
+                    }
+                }
+            }
+
+            // En el polo la longitud no está definida: cada triángulo que lo toca usa
+            // su propia copia con la U promedio de las otras dos esquinas
+            for corner in 0..3 {
+                let index = triangle[corner] as usize;
+                if vertices[index].y.abs() > 1.0 - 1e-6 {
+                    let others = [triangle[(corner + 1) % 3] as usize, triangle[(corner + 2) % 3] as usize];
+                    let u = (texcoords[others[0]].x + texcoords[others[1]].x) / 2.0;
+                    vertices.push(vertices[index]);
+                    texcoords.push(Vec2::new(u, texcoords[index].y));
+                    triangle[corner] = vertices.len() as u32 - 1;
+                }
+            }
+        }
+
+        let normals = vertices.clone();
+        Obj {
+            meshes: vec![Mesh { name: String::from("icosphere"), material: None, vertices, normals, texcoords, indices }],
+            materials: Vec::new(),
+        }
+    }
+
+    // Caja centrada en el origen con esas medias longitudes por eje, con normales
+    // planas (cuatro vértices propios por cara); reemplaza a la nave si falta su modelo
+    pub fn fallback_box(half_extents: Vec3) -> Self {
+        let mut vertices = Vec::with_capacity(24);
+        let mut normals = Vec::with_capacity(24);
+        let mut texcoords = Vec::with_capacity(24);
+        let mut indices = Vec::with_capacity(36);
+
+        for axis in 0..3 {
+            for sign in [-1.0, 1.0] {
+                let mut normal = Vec3::new(0.0, 0.0, 0.0);
+                normal[axis] = sign;
+                // Dos ejes del plano de la cara, ordenados para que u × v apunte hacia afuera
+                let mut u = Vec3::new(0.0, 0.0, 0.0);
+                let mut v = Vec3::new(0.0, 0.0, 0.0);
+                u[(axis + 1) % 3] = sign;
+                v[(axis + 2) % 3] = 1.0;
+
+                let first = vertices.len() as u32;
+                for (du, dv) in [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)] {
+                    vertices.push((normal + u * du + v * dv).component_mul(&half_extents));
+                    normals.push(normal);
+                    texcoords.push(Vec2::new((du + 1.0) / 2.0, (1.0 - dv) / 2.0));
+                }
+                indices.extend_from_slice(&[first, first + 1, first + 2, first, first + 2, first + 3]);
+            }
+        }
+
+        Obj {
+            meshes: vec![Mesh { name: String::from("box"), material: None, vertices, normals, texcoords, indices }],
+            materials: Vec::new(),
+        }
+    }
+
+    pub fn with_consistent_winding(filename: &str) -> Result<Self, tobj::LoadError> {
+        let mut obj = Self::load(filename)?;
+        obj.fix_winding();
+        Ok(obj)
+    }
+
+    // Flip every triangle whose geometric normal disagrees with its vertex normals,
+    // so the winding order can be trusted for backface culling
+    pub fn fix_winding(&mut self) {
+        for mesh in &mut self.meshes {
+            if mesh.normals.is_empty() {
+                continue;
+            }
+
+            for triangle in mesh.indices.chunks_exact_mut(3) {
+                let (a, b, c) = (triangle[0] as usize, triangle[1] as usize, triangle[2] as usize);
+
+                let face_normal = (mesh.vertices[b] - mesh.vertices[a])
+                    .cross(&(mesh.vertices[c] - mesh.vertices[a]));
+                let vertex_normal = mesh.normals[a] + mesh.normals[b] + mesh.normals[c];
+
+                if face_normal.dot(&vertex_normal) < 0.0 {
+                    triangle.swap(1, 2);
+                }
+            }
+        }
+    }
+
+    // Reemplaza las normales de todos los meshes por normales suaves, aunque el
+    // archivo ya traiga las suyas (útil si se ven facetadas)
+    #[allow(dead_code)]
+    pub fn recompute_normals(&mut self) {
+        for mesh in &mut self.meshes {
+            mesh.recompute_normals();
+        }
+    }
+
+    // Versión simplificada del modelo por agrupamiento de vértices: todos los vértices
+    // que caen en la misma celda de una rejilla de lado `cell_size` se funden en uno
+    // (posición y normal promedio) y se descartan los triángulos que degeneran
+    pub fn decimated(&self, cell_size: f32) -> Self {
+        let meshes = self.meshes.iter().map(|mesh| {
+            let mut cells: HashMap<(i32, i32, i32), u32> = HashMap::new();
+            let mut remap = Vec::with_capacity(mesh.vertices.len());
+            let mut sums: Vec<(Vec3, Vec3, Vec2, f32)> = Vec::new();
+
+            for (index, position) in mesh.vertices.iter().enumerate() {
+                let cell = (
+                    (position.x / cell_size).floor() as i32,
+                    (position.y / cell_size).floor() as i32,
+                    (position.z / cell_size).floor() as i32,
+                );
+                let normal = mesh.normals.get(index).cloned().unwrap_or(Vec3::new(0.0, 0.0, 0.0));
+                let tex_coords = mesh.texcoords.get(index).cloned().unwrap_or(Vec2::new(0.0, 0.0));
+
+                let merged = *cells.entry(cell).or_insert_with(|| {
+                    sums.push((Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 0.0), tex_coords, 0.0));
+                    sums.len() as u32 - 1
+                });
+                let sum = &mut sums[merged as usize];
+                sum.0 += position;
+                sum.1 += normal;
+                sum.3 += 1.0;
+                remap.push(merged);
+            }
+
+            let mut seen = HashSet::new();
+            let mut indices = Vec::with_capacity(mesh.indices.len());
+            for triangle in mesh.indices.chunks_exact(3) {
+                let merged = [remap[triangle[0] as usize], remap[triangle[1] as usize], remap[triangle[2] as usize]];
+                if merged[0] == merged[1] || merged[1] == merged[2] || merged[0] == merged[2] {
+                    continue;
+                }
+
+                let mut key = merged;
+                key.sort_unstable();
+                if seen.insert(key) {
+                    indices.extend_from_slice(&merged);
+                }
+            }
+
+            Mesh {
+                name: mesh.name.clone(),
+                material: mesh.material,
+                vertices: sums.iter().map(|(position, _, _, count)| position / *count).collect(),
+                normals: if mesh.normals.is_empty() {
+                    Vec::new()
+                } else {
+                    sums.iter().map(|(_, normal, _, _)| normal.normalize()).collect()
+                },
+                texcoords: sums.iter().map(|(_, _, tex_coords, _)| *tex_coords).collect(),
+                indices,
+            }
+        }).collect();
+
+        // Fundir vértices puede invertir algún triángulo
+        let mut obj = Obj { meshes, materials: self.materials.clone() };
+        obj.fix_winding();
+        obj
+    }
+
+    // Si el modelo trae colores de un .mtl (guardados en `Vertex::color`)
+    pub fn has_materials(&self) -> bool {
+        !self.materials.is_empty()
+    }
+
+    // Nombres de los grupos del modelo, sin repetir y en el orden del archivo
+    pub fn group_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = Vec::new();
+        for mesh in &self.meshes {
+            if !names.contains(&mesh.name.as_str()) {
+                names.push(&mesh.name);
+            }
+        }
+        names
+    }
+
+    #[allow(dead_code)]
+    pub fn get_vertex_array_for_group(&self, name: &str) -> Vec<Vertex> {
+        self.get_indexed_mesh_for_group(name).to_vertex_array()
+    }
+
+    // Todos los grupos concatenados, un vértice por esquina de cada triángulo
+    #[allow(dead_code)]
     pub fn get_vertex_array(&self) -> Vec<Vertex> {
-        let mut vertices = Vec::new();
+        self.get_indexed_mesh().to_vertex_array()
+    }
+
+    pub fn get_indexed_mesh_for_group(&self, name: &str) -> IndexedMesh {
+        let mut indexed = IndexedMesh { vertices: Vec::new(), indices: Vec::new() };
+
+        for mesh in self.meshes.iter().filter(|mesh| mesh.name == name) {
+            mesh.append_indexed(&mut indexed, &self.materials);
+        }
+
+        indexed
+    }
+
+    // Todos los grupos concatenados, en forma indexada
+    pub fn get_indexed_mesh(&self) -> IndexedMesh {
+        let mut indexed = IndexedMesh { vertices: Vec::new(), indices: Vec::new() };
 
         for mesh in &self.meshes {
-            for &index in &mesh.indices {
-                let position = mesh.vertices[index as usize];
-                let normal = mesh.normals.get(index as usize)
-                    .cloned()
-                    .unwrap_or(Vec3::new(0.0, 1.0, 0.0));
-                let tex_coords = mesh.texcoords.get(index as usize)
-                    .cloned()
-                    .unwrap_or(Vec2::new(0.0, 0.0));
+            mesh.append_indexed(&mut indexed, &self.materials);
+        }
+
+        indexed
+    }
+}
 
-                vertices.push(Vertex::new(position, normal, tex_coords));
+impl Mesh {
+    // Normal suave por vértice: promedio de las normales de las caras que lo usan,
+    // ponderadas por el ángulo de cada cara en ese vértice (así la forma en que se
+    // triangularon las caras no sesga el resultado). Los vértices duplicados en la
+    // misma posición (costuras de UV) se tratan como uno solo
+    fn recompute_normals(&mut self) {
+        let mut by_position: HashMap<[u32; 3], Vec3> = HashMap::new();
+        let key = |position: &Vec3| [position.x.to_bits(), position.y.to_bits(), position.z.to_bits()];
+
+        for triangle in self.indices.chunks_exact(3) {
+            let corners = [self.vertices[triangle[0] as usize], self.vertices[triangle[1] as usize], self.vertices[triangle[2] as usize]];
+            let face_normal = (corners[1] - corners[0]).cross(&(corners[2] - corners[0]));
+            if face_normal.magnitude() == 0.0 {
+                continue;
+            }
+            let face_normal = face_normal.normalize();
+
+            for i in 0..3 {
+                let (corner, next, previous) = (corners[i], corners[(i + 1) % 3], corners[(i + 2) % 3]);
+                let angle = (next - corner).normalize().dot(&(previous - corner).normalize()).clamp(-1.0, 1.0).acos();
+                *by_position.entry(key(&corner)).or_insert(Vec3::new(0.0, 0.0, 0.0)) += face_normal * angle;
             }
         }
 
-        vertices
+        self.normals = self.vertices.iter()
+            .map(|position| {
+                let normal = by_position.get(&key(position)).cloned().unwrap_or(Vec3::new(0.0, 0.0, 0.0));
+                if normal.magnitude() > 0.0 { normal.normalize() } else { Vec3::new(0.0, 1.0, 0.0) }
+            })
+            .collect();
+    }
+
+    // Los índices del mesh se desplazan por los vértices que ya tiene `indexed`
+    fn append_indexed(&self, indexed: &mut IndexedMesh, materials: &[Material]) {
+        let offset = indexed.vertices.len() as u32;
+        indexed.vertices.extend((0..self.vertices.len()).map(|index| self.vertex(index, materials)));
+        indexed.indices.extend(self.indices.iter().map(|&index| index + offset));
+    }
+
+    fn vertex(&self, index: usize, materials: &[Material]) -> Vertex {
+        let color = self.material
+            .map_or(DEFAULT_DIFFUSE, |material| materials[material].diffuse);
+        let normal = self.normals.get(index)
+            .cloned()
+            .unwrap_or(Vec3::new(0.0, 1.0, 0.0));
+        let tex_coords = self.texcoords.get(index)
+            .cloned()
+            .unwrap_or(Vec2::new(0.0, 0.0));
+
+        let mut vertex = Vertex::new(self.vertices[index], normal, tex_coords);
+        vertex.color = color;
+        vertex
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Cubo unitario sin `vn`, con las caras en el orden antihorario de siempre
+    const CUBE_WITHOUT_NORMALS: &str = "\
+v -1 -1 -1
+v 1 -1 -1
+v 1 1 -1
+v -1 1 -1
+v -1 -1 1
+v 1 -1 1
+v 1 1 1
+v -1 1 1
+f 1 4 3 2
+f 5 6 7 8
+f 1 2 6 5
+f 4 8 7 3
+f 1 5 8 4
+f 2 3 7 6
+";
+
+    fn assert_cube_normals_point_outward(obj: &Obj) {
+        let mesh = obj.get_indexed_mesh();
+        assert_eq!(mesh.vertices.len(), 8);
+        assert_eq!(mesh.indices.len(), 36);
+        for vertex in &mesh.vertices {
+            assert!((vertex.normal.magnitude() - 1.0).abs() < 1e-5);
+            // En cada esquina la normal promedio apunta hacia afuera, en diagonal
+            assert!((vertex.normal - vertex.position.normalize()).magnitude() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn cube_without_normals_gets_outward_normals() {
+        let path = std::env::temp_dir().join(format!("cubo_sin_normales_{}.obj", std::process::id()));
+        std::fs::write(&path, CUBE_WITHOUT_NORMALS).unwrap();
+        let mut obj = Obj::load(path.to_str().unwrap()).unwrap();
+        std::fs::remove_file(&path).ok();
+        assert_cube_normals_point_outward(&obj);
+
+        // Con normales ya puestas (aunque estén mal) se pueden forzar las suaves
+        for mesh in &mut obj.meshes {
+            mesh.normals = vec![Vec3::new(0.0, 1.0, 0.0); mesh.vertices.len()];
+        }
+        obj.recompute_normals();
+        assert_cube_normals_point_outward(&obj);
+    }
+
+    #[test]
+    fn reversed_triangle_gets_flipped() {
+        // Dos triángulos en el plano z = 0 con normales hacia +z; el segundo está al revés
+        let vertices = vec![
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(1.0, 1.0, 0.0),
+        ];
+        let mesh = Mesh {
+            name: String::from("quad"),
+            material: None,
+            normals: vec![Vec3::new(0.0, 0.0, 1.0); 4],
+            texcoords: Vec::new(),
+            vertices,
+            indices: vec![0, 1, 2, 1, 2, 3],
+        };
+        let mut obj = Obj { meshes: vec![mesh], materials: Vec::new() };
+
+        obj.fix_winding();
+
+        assert_eq!(obj.meshes[0].indices, vec![0, 1, 2, 1, 3, 2]);
+    }
+
+    fn assert_unit_radial_normals(obj: &Obj) {
+        let mesh = obj.get_indexed_mesh();
+        assert!(!mesh.indices.is_empty());
+        for vertex in &mesh.vertices {
+            assert!((vertex.position.magnitude() - 1.0).abs() < 1e-5);
+            assert!((vertex.normal.magnitude() - 1.0).abs() < 1e-5);
+            assert!((vertex.normal - vertex.position).magnitude() < 1e-5);
+        }
+
+        // Cada triángulo mira hacia afuera, como sus normales
+        for triangle in mesh.indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| mesh.vertices[triangle[i] as usize].position);
+            let face_normal = (b - a).cross(&(c - a));
+            assert!(face_normal.dot(&(a + b + c)) > 0.0);
+        }
+    }
+
+    #[test]
+    fn generated_sphere_normals_are_unit_and_radial() {
+        assert_unit_radial_normals(&Obj::uv_sphere(12, 24));
+        assert_unit_radial_normals(&Obj::icosphere(2));
+        // 20 caras del icosaedro, cada subdivisión las multiplica por 4
+        assert_eq!(Obj::icosphere(2).get_indexed_mesh().indices.len(), 20 * 16 * 3);
+    }
+
+
+    #[test]
+    fn flat_vertex_array_follows_the_indices() {
+        let obj = Obj::fallback_box(Vec3::new(1.0, 2.0, 3.0));
+        let indexed = obj.get_indexed_mesh();
+        let flat = obj.get_vertex_array();
+
+        assert_eq!(flat.len(), indexed.indices.len());
+        for (vertex, &index) in flat.iter().zip(&indexed.indices) {
+            assert_eq!(vertex.position, indexed.vertices[index as usize].position);
+            assert_eq!(vertex.normal, indexed.vertices[index as usize].normal);
+        }
+    }
+
+
+    fn triangle_mesh(name: &str, material: Option<usize>, x: f32) -> Mesh {
+        Mesh {
+            name: String::from(name),
+            material,
+            vertices: vec![Vec3::new(x, 0.0, 0.0), Vec3::new(x + 1.0, 0.0, 0.0), Vec3::new(x, 1.0, 0.0)],
+            normals: Vec::new(),
+            texcoords: Vec::new(),
+            indices: vec![0, 1, 2],
+        }
+    }
+
+    #[test]
+    fn group_vertex_array_keeps_only_that_group() {
+        // "casco" llega partido en dos meshes por tener dos materiales
+        let obj = Obj {
+            meshes: vec![
+                triangle_mesh("casco", Some(0), 0.0),
+                triangle_mesh("motor", Some(1), 10.0),
+                triangle_mesh("casco", Some(1), 20.0),
+            ],
+            materials: vec![
+                Material { diffuse: Color::new(255, 0, 0) },
+                Material { diffuse: Color::new(0, 0, 255) },
+            ],
+        };
+        assert_eq!(obj.group_names(), vec!["casco", "motor"]);
+
+        let hull = obj.get_vertex_array_for_group("casco");
+        assert_eq!(hull.len(), 6);
+        assert!(hull[..3].iter().all(|vertex| vertex.position.x < 2.0 && vertex.color.r == 255));
+        assert!(hull[3..].iter().all(|vertex| vertex.position.x >= 20.0 && vertex.color.b == 255));
+
+        let engine = obj.get_vertex_array_for_group("motor");
+        assert_eq!(engine.len(), 3);
+        assert!(engine.iter().all(|vertex| (10.0..12.0).contains(&vertex.position.x)));
+
+        assert!(obj.get_vertex_array_for_group("ala").is_empty());
+        assert_eq!(hull.len() + engine.len(), obj.get_vertex_array().len());
     }
 }
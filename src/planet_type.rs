@@ -1,3 +1,6 @@
+use serde::{de, Deserialize, Deserializer};
+use crate::color::Color;
+
 #[derive(Debug, PartialEq)]
 pub enum PlanetType {
     Sun,
@@ -11,6 +14,85 @@ pub enum PlanetType {
     Uranus,
     Neptune,
     BlackHole,
+    Comet,
+    Asteroid,
+    Wormhole,
+    Rings,
     Spaceship,
     Rei,
-}
\ No newline at end of file
+}
+impl PlanetType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PlanetType::Sun => "Sol",
+            PlanetType::Mercury => "Mercurio",
+            PlanetType::Venus => "Venus",
+            PlanetType::Earth => "Tierra",
+            PlanetType::Moon => "Luna",
+            PlanetType::Mars => "Marte",
+            PlanetType::Jupiter => "Jupiter",
+            PlanetType::Saturn => "Saturno",
+            PlanetType::Uranus => "Urano",
+            PlanetType::Neptune => "Neptuno",
+            PlanetType::BlackHole => "Agujero Negro",
+            PlanetType::Comet => "Cometa",
+            PlanetType::Asteroid => "Asteroide",
+            PlanetType::Wormhole => "Agujero de Gusano",
+            PlanetType::Rings => "Anillos",
+            PlanetType::Spaceship => "Nave",
+            PlanetType::Rei => "Rei",
+        }
+    }
+
+    // Nombre usado en los archivos de escena (assets/scene.toml)
+    pub fn from_key(key: &str) -> Option<Self> {
+        match key {
+            "sun" => Some(PlanetType::Sun),
+            "mercury" => Some(PlanetType::Mercury),
+            "venus" => Some(PlanetType::Venus),
+            "earth" => Some(PlanetType::Earth),
+            "moon" => Some(PlanetType::Moon),
+            "mars" => Some(PlanetType::Mars),
+            "jupiter" => Some(PlanetType::Jupiter),
+            "saturn" => Some(PlanetType::Saturn),
+            "uranus" => Some(PlanetType::Uranus),
+            "neptune" => Some(PlanetType::Neptune),
+            "black_hole" => Some(PlanetType::BlackHole),
+            "comet" => Some(PlanetType::Comet),
+            "rei" => Some(PlanetType::Rei),
+            _ => None,
+        }
+    }
+
+    // Color plano que identifica al cuerpo (estelas, modo silueta)
+    pub fn palette_color(&self) -> Color {
+        match self {
+            PlanetType::Sun => Color::from_hex(0xFFAA00),       // Naranja brillante
+            PlanetType::Mercury => Color::from_hex(0xAA8866),   // Marrón claro
+            PlanetType::Venus => Color::from_hex(0xFFCC99),     // Amarillo pálido
+            PlanetType::Earth => Color::from_hex(0x0066FF),     // Azul brillante
+            PlanetType::Mars => Color::from_hex(0xFF3300),      // Rojo anaranjado
+            PlanetType::Jupiter => Color::from_hex(0xFFAA66),   // Naranja suave
+            PlanetType::Saturn => Color::from_hex(0xFFCC66),    // Dorado
+            PlanetType::Uranus => Color::from_hex(0x66FFFF),    // Cyan claro
+            PlanetType::Neptune => Color::from_hex(0x0066FF),   // Azul profundo
+            PlanetType::Moon => Color::from_hex(0xCCCCCC),      // Gris claro
+            PlanetType::BlackHole => Color::from_hex(0x440044), // Púrpura oscuro
+            PlanetType::Comet => Color::from_hex(0xCCE6FF),     // Blanco azulado
+            PlanetType::Asteroid => Color::from_hex(0x8A7D6E),  // Marrón grisáceo
+            PlanetType::Wormhole => Color::from_hex(0x9966FF),  // Violeta
+            PlanetType::Rings => Color::from_hex(0xD2BEAA),     // Beige
+            PlanetType::Spaceship => Color::from_hex(0xFFFFFF), // Blanco
+            PlanetType::Rei => Color::from_hex(0xFF69B4),       // Rosa (Hot Pink)
+        }
+    }
+}
+
+// En la escena el tipo se escribe con su nombre de `from_key` ("earth", "black_hole")
+impl<'de> Deserialize<'de> for PlanetType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let key = String::deserialize(deserializer)?;
+        PlanetType::from_key(&key)
+            .ok_or_else(|| de::Error::custom(format!("tipo de cuerpo desconocido `{}`", key)))
+    }
+}
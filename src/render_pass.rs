@@ -0,0 +1,59 @@
+// Orden de dibujo: primero los cuerpos opacos, luego el skybox (con prueba de
+// profundidad, así las estrellas quedan detrás de los cuerpos), los transparentes
+// (anillos, atmósferas, estelas) y por último la nave como overlay
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RenderPass {
+    Opaque,
+    Skybox,
+    Transparent,
+    Overlay,
+}
+
+impl RenderPass {
+    pub const ORDER: [RenderPass; 4] = [
+        RenderPass::Opaque,
+        RenderPass::Skybox,
+        RenderPass::Transparent,
+        RenderPass::Overlay,
+    ];
+
+    // Solo los opacos escriben profundidad; los transparentes la respetan sin ocultar lo de atrás
+    pub fn writes_depth(&self) -> bool {
+        matches!(self, RenderPass::Opaque | RenderPass::Overlay)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::framebuffer::{Framebuffer, DepthFormat};
+
+    #[test]
+    fn transparent_is_drawn_after_opaque() {
+        let position = |pass| RenderPass::ORDER.iter().position(|&other| other == pass).unwrap();
+        assert!(position(RenderPass::Opaque) < position(RenderPass::Skybox));
+        assert!(position(RenderPass::Skybox) < position(RenderPass::Transparent));
+        assert!(position(RenderPass::Transparent) < position(RenderPass::Overlay));
+        // ORDER sigue el orden de la enumeración, así ordenar por pase da el mismo resultado
+        assert!(RenderPass::ORDER.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn transparent_blends_without_hiding_what_is_behind() {
+        assert!(RenderPass::Opaque.writes_depth());
+        assert!(!RenderPass::Transparent.writes_depth());
+
+        // Un anillo semitransparente delante de un planeta se mezcla con él...
+        let mut framebuffer = Framebuffer::new(1, 1, DepthFormat::Float32);
+        framebuffer.set_current_color(0x0000FF);
+        framebuffer.point(0, 0, 0.5);
+        framebuffer.blend_point(0, 0, 0.3, 0x80FF0000);
+        let blended = framebuffer.buffer[0];
+        assert!((blended & 0xFF) > 0 && ((blended >> 16) & 0xFF) > 0);
+
+        // ...y no tapa lo que se dibuje después entre los dos
+        framebuffer.set_current_color(0x00FF00);
+        framebuffer.point(0, 0, 0.4);
+        assert_eq!(framebuffer.buffer[0], 0x00FF00);
+    }
+}
@@ -0,0 +1,222 @@
+use std::fs;
+use nalgebra_glm::Vec3;
+use serde::Deserialize;
+use crate::planet_type::PlanetType;
+
+// Descripción de un cuerpo celeste tal como aparece en assets/scene.toml; solo
+// `type` es obligatorio
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BodyConfig {
+    #[serde(rename = "type")]
+    pub shader_type: PlanetType,
+    #[serde(default)]
+    pub position: Vec3,
+    #[serde(default = "default_scale")]
+    pub scale: f32,
+    #[serde(default)]
+    pub rotation: Vec3,
+    // Inclinación fija del eje de giro (ángulos en radianes sobre x, y, z) y
+    // velocidad de giro alrededor de ese eje, en radianes por unidad de tiempo
+    #[serde(default)]
+    pub axial_tilt: Vec3,
+    #[serde(default = "default_spin_speed")]
+    pub spin_speed: f32,
+    #[serde(default)]
+    pub semi_major_axis: f32,
+    #[serde(default)]
+    pub eccentricity: f32,
+    #[serde(default)]
+    pub inclination: f32,
+    #[serde(default)]
+    pub orbital_speed: f32,
+    #[serde(default = "default_trail_capacity")]
+    pub trail_capacity: usize,
+    #[serde(default)]
+    pub speed_colored_trail: bool,
+    #[serde(default = "default_albedo")]
+    pub albedo: f32,
+    // Tecla numérica (1-9) que hace warp hasta el cuerpo, y a qué distancia de su superficie
+    #[serde(default)]
+    pub warp_slot: Option<u32>,
+    #[serde(default = "default_warp_distance")]
+    pub warp_distance: f32,
+    // Radios interior y exterior de un disco de anillos, relativos al radio del cuerpo
+    #[serde(default)]
+    pub rings: Option<(f32, f32)>,
+    // Tipo del cuerpo alrededor del que orbita, como aparece en el archivo; None = el Sol
+    #[serde(rename = "parent", default)]
+    pub parent_type: Option<PlanetType>,
+    // Índice de ese cuerpo (siempre uno anterior), resuelto al cargar la escena
+    #[serde(skip)]
+    pub parent: Option<usize>,
+    // Nombre que se muestra en pantalla; None = el nombre del tipo
+    #[serde(default)]
+    pub name: Option<String>,
+    // Semilla del ruido de la superficie; None = una distinta según su posición en la escena
+    #[serde(default)]
+    pub seed: Option<u64>,
+    // Imagen equirectangular de la superficie y cuánto pesa frente al color procedural
+    #[serde(default)]
+    pub texture: Option<String>,
+    #[serde(default = "default_texture_blend")]
+    pub texture_blend: f32,
+}
+
+// Valores de los campos que no aparecen en el archivo (los demás empiezan en cero o vacíos)
+fn default_scale() -> f32 { 1.0 }
+fn default_spin_speed() -> f32 { 0.01 }
+fn default_trail_capacity() -> usize { 50000 }
+fn default_albedo() -> f32 { 1.0 }
+fn default_warp_distance() -> f32 { 3.0 }
+fn default_texture_blend() -> f32 { 0.8 }
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SceneConfig {
+    #[serde(rename = "body", default)]
+    pub bodies: Vec<BodyConfig>,
+}
+
+impl SceneConfig {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|error| format!("{}: {}", path, error))?;
+        Self::parse(&text).map_err(|error| format!("{}: {}", path, error))
+    }
+
+    // Tablas [[body]] con los campos de `BodyConfig`. Los errores de sintaxis, de tipos
+    // y de claves desconocidas los reporta toml con su línea; los rangos y los padres
+    // se revisan después, cuerpo por cuerpo
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut scene: SceneConfig = toml::from_str(text).map_err(|error| error.to_string())?;
+        if scene.bodies.is_empty() {
+            return Err("la escena no tiene cuerpos".to_string());
+        }
+
+        for index in 0..scene.bodies.len() {
+            let (defined, rest) = scene.bodies.split_at_mut(index);
+            let body = &mut rest[0];
+            body.validate().map_err(|error| format!("cuerpo {}: {}", index + 1, error))?;
+
+            // El padre se busca entre los cuerpos ya definidos, así siempre se actualiza antes que el hijo
+            if let Some(parent_type) = &body.parent_type {
+                let parent = defined.iter().rposition(|other| other.shader_type == *parent_type).ok_or_else(|| {
+                    format!("cuerpo {}: `parent` debe referirse a un cuerpo definido antes", index + 1)
+                })?;
+                body.parent = Some(parent);
+            }
+        }
+
+        Ok(scene)
+    }
+}
+
+impl BodyConfig {
+    // Rangos que el tipo del campo no alcanza a expresar
+    fn validate(&self) -> Result<(), String> {
+        if !(0.0..1.0).contains(&self.eccentricity) {
+            return Err("`eccentricity` debe estar en [0, 1)".to_string());
+        }
+        if self.warp_slot.is_some_and(|slot| !(1..=9).contains(&slot)) {
+            return Err("`warp_slot` debe ser una tecla del 1 al 9".to_string());
+        }
+        if !(0.0..=1.0).contains(&self.texture_blend) {
+            return Err("`texture_blend` debe estar en [0, 1]".to_string());
+        }
+        if self.rings.is_some_and(|(inner, outer)| !(0.0 < inner && inner < outer)) {
+            return Err("`rings` debe ser [radio interior, radio exterior] con 0 < interior < exterior".to_string());
+        }
+        Ok(())
+    }
+}
+
+// La disposición original del sistema solar: assets/scene.toml incluido en el binario,
+// para que la escena de respaldo (si el archivo falta o tiene errores) no sea una
+// segunda copia que mantener a mano
+const DEFAULT_SCENE: &str = include_str!("../assets/scene.toml");
+
+impl Default for SceneConfig {
+    fn default() -> Self {
+        Self::parse(DEFAULT_SCENE).expect("la escena incluida en el binario debe ser válida")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_scene_is_the_bundled_file() {
+        let scene = SceneConfig::default();
+        assert_eq!(scene.bodies[0].shader_type, PlanetType::Sun);
+        assert_eq!(scene.bodies[0].warp_slot, Some(1));
+        assert!(scene.bodies.iter().any(|body| body.shader_type == PlanetType::Saturn && body.rings.is_some()));
+    }
+
+    #[test]
+    fn parses_values_comments_and_defaults() {
+        let scene = SceneConfig::parse(r#"
+            # Un comentario suelto
+            [[body]]
+            type = "earth"   # comentario al final
+            name = "Tierra #2"
+            position = [1.0, -2.5, 3]
+            scale = 0.7
+            speed_colored_trail = true
+            rings = [1.2, 2.5]
+        "#).unwrap();
+
+        let body = &scene.bodies[0];
+        assert_eq!(body.shader_type, PlanetType::Earth);
+        assert_eq!(body.name.as_deref(), Some("Tierra #2"));
+        assert_eq!(body.position, Vec3::new(1.0, -2.5, 3.0));
+        assert_eq!(body.scale, 0.7);
+        assert!(body.speed_colored_trail);
+        assert_eq!(body.rings, Some((1.2, 2.5)));
+        // Lo que no aparece conserva su valor por defecto
+        assert_eq!(body.spin_speed, 0.01);
+        assert_eq!(body.parent, None);
+    }
+
+    #[test]
+    fn parent_refers_to_the_last_body_of_that_type() {
+        let scene = SceneConfig::parse(r#"
+            [[body]]
+            type = "sun"
+            [[body]]
+            type = "earth"
+            [[body]]
+            type = "moon"
+            parent = "earth"
+        "#).unwrap();
+        assert_eq!(scene.bodies[2].parent, Some(1));
+
+        let error = SceneConfig::parse("[[body]]\ntype = \"sun\"\n[[body]]\ntype = \"moon\"\nparent = \"mars\"").err().unwrap();
+        assert!(error.contains("cuerpo 2"), "{}", error);
+    }
+
+    #[test]
+    fn errors_name_the_offending_line() {
+        // Sintaxis, tipos y claves desconocidas: toml indica la línea
+        let cases = [
+            ("type = \"sun\"", "line 1"),
+            ("[[body]]\ntype = \"sun\"\nscale 2.0", "line 3"),
+            ("[[body]]\ntype = \"planeta\"", "line 2"),
+            ("[[body]]\ntype = \"sun\"\nposition = [1.0, 2.0]", "line 3"),
+            ("[[body]]\ntype = \"sun\"\nscale = dos", "line 3"),
+            ("[[body]]\ntype = \"sun\"\nescala = 2.0", "line 3"),
+        ];
+        for (text, expected) in cases {
+            let error = SceneConfig::parse(text).err().unwrap();
+            assert!(error.contains(expected), "{:?}: {}", text, error);
+        }
+
+        // Valores fuera de rango: se indica el cuerpo
+        let error = SceneConfig::parse("[[body]]\ntype = \"sun\"\n[[body]]\ntype = \"comet\"\neccentricity = 1.5").err().unwrap();
+        assert!(error.contains("cuerpo 2") && error.contains("eccentricity"), "{}", error);
+
+        assert!(SceneConfig::parse("# sin cuerpos").is_err());
+        let error = SceneConfig::parse("[[body]]\nscale = 1.0").err().unwrap();
+        assert!(error.contains("type"), "{}", error);
+    }
+}
@@ -1,34 +1,25 @@
 use nalgebra_glm::{Vec3, Vec4, Mat3, mat4_to_mat3};
+use std::f32::consts::{PI, TAU};
 use crate::vertex::Vertex;
 use crate::Uniforms;
 use crate::fragment::Fragment;
 use crate::color::Color;
-use rand::Rng;
-use rand::SeedableRng;
-use rand::rngs::StdRng;
 use crate::planet_type::PlanetType;
+use crate::texture::{Texture, uv_to_direction};
+use nalgebra_glm::Vec2;
+
+// Color de la superficie sin iluminar en un punto del modelo
+pub type SurfaceShader = fn(&Vec3, &Uniforms) -> Color;
 
 pub fn vertex_shader(vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
-  // Transform position
+  // Transform position to clip space; the perspective division happens after clipping
   let position = Vec4::new(
     vertex.position.x,
     vertex.position.y,
     vertex.position.z,
     1.0
   );
-  let transformed = uniforms.projection_matrix * uniforms.view_matrix * uniforms.model_matrix * position;
-
-  // Perform perspective division
-  let w = transformed.w;
-  let ndc_position = Vec4::new(
-    transformed.x / w,
-    transformed.y / w,
-    transformed.z / w,
-    1.0
-  );
-
-  // apply viewport matrix
-  let screen_position = uniforms.viewport_matrix * ndc_position;
+  let clip_position = uniforms.projection_matrix * uniforms.view_matrix * uniforms.model_matrix * position;
 
   // Transform normal
   let model_mat3 = mat4_to_mat3(&uniforms.model_matrix); 
@@ -42,17 +33,98 @@ pub fn vertex_shader(vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
     normal: vertex.normal,
     tex_coords: vertex.tex_coords,
     color: vertex.color,
-    transformed_position: Vec3::new(screen_position.x, screen_position.y, screen_position.z),
+    transformed_position: Vec3::new(0.0, 0.0, 0.0),
     transformed_normal,
+    clip_position,
+    clip_w: clip_position.w,
   }
 }
 
-pub fn fragment_shader(fragment: &Fragment, uniforms: &Uniforms, planet_type: &PlanetType) -> Color {
-    // Obtener la posición del Sol desde los uniforms
-    let sun_position = uniforms.sun_position; // Asegúrate de que esto esté correctamente inicializado
+// Perspective division and viewport transform of a vertex that is already clipped
+pub fn project_vertex(vertex: &mut Vertex, uniforms: &Uniforms) {
+  let w = vertex.clip_position.w;
+  let ndc_position = Vec4::new(
+    vertex.clip_position.x / w,
+    vertex.clip_position.y / w,
+    vertex.clip_position.z / w,
+    1.0
+  );
+
+  // apply viewport matrix
+  let screen_position = uniforms.viewport_matrix * ndc_position;
+  vertex.transformed_position = Vec3::new(screen_position.x, screen_position.y, screen_position.z);
+  vertex.clip_w = w;
+}
 
-    // Calcular la dirección de la luz
-    let light_direction = (sun_position - fragment.vertex_position).normalize();
+// Niebla exponencial según la profundidad en el espacio de vista del fragmento
+// (su w de clip): lleva el color hacia `fog_color` en 1 - exp(-densidad * profundidad)
+// Trabaja con canales en flotante sin recortar, para no perder el brillo HDR del Sol
+pub fn apply_fog(color: Vec3, fragment: &Fragment, uniforms: &Uniforms) -> Vec3 {
+    if uniforms.fog_density <= 0.0 {
+        return color;
+    }
+    let fog = 1.0 - (-uniforms.fog_density * fragment.clip_w.max(0.0)).exp();
+    color.lerp(&uniforms.fog_color.to_float(), fog)
+}
+
+// Multiplicador del color final. El Sol emite su propia luz: con HDR puede pasar de
+// 1.0 y el tone mapping lo comprime; sin HDR simplemente se recorta. El resto de los
+// cuerpos recibe menos luz cuanto más lejos del Sol están (`light_scale`), salvo en
+// modo póster, donde las siluetas son planas
+pub fn emission(planet_type: &PlanetType, uniforms: &Uniforms) -> f32 {
+    match planet_type {
+        _ if uniforms.silhouette_mode => 1.0,
+        PlanetType::Sun => 1.2 * uniforms.sun_pulse * uniforms.sun_emission,
+        _ => uniforms.light_scale,
+    }
+}
+
+// Texturas con las que se sombrea un cuerpo, además de (o en lugar de) su ruido
+#[derive(Clone, Copy, Default)]
+pub struct Surface<'a> {
+    // Superficie horneada con --bake-surfaces: reemplaza por completo al ruido
+    pub baked: Option<&'a Texture>,
+    // Imagen equirectangular del cuerpo y su peso frente al color procedural
+    pub map: Option<(&'a Texture, f32)>,
+}
+
+pub fn fragment_shader(fragment: &Fragment, uniforms: &Uniforms, planet_type: &PlanetType, surface: Surface) -> Color {
+    // Modo póster: cada cuerpo es una silueta plana de su color de paleta
+    if uniforms.silhouette_mode && !matches!(planet_type, PlanetType::Spaceship) {
+        return planet_type.palette_color();
+    }
+
+    // Contorno oscuro en la silueta para el modo cel-shading
+    if uniforms.toon_enabled && !matches!(planet_type, PlanetType::Spaceship) && is_silhouette(fragment, uniforms) {
+        return uniforms.toon.outline_color;
+    }
+
+    let color = procedural_color(fragment, uniforms, planet_type, surface.baked);
+
+    // La imagen se ubica por la dirección desde el centro del cuerpo (longitud y
+    // latitud), así no depende de las coordenadas UV del modelo
+    match surface.map {
+        Some((texture, blend)) => {
+            let light = if matches!(planet_type, PlanetType::Sun) {
+                1.0
+            } else {
+                shade_intensity(sun_lambert(fragment, uniforms).max(0.0), uniforms)
+            };
+            let lod = texture_lod(fragment, uniforms, texture.width);
+            let texel = texture.sample_direction(&fragment.vertex_position, lod) * light;
+            color.lerp_linear(&texel, blend)
+        }
+        None => color,
+    }
+}
+
+// Color del cuerpo según su shader (o su superficie horneada), ya iluminado
+fn procedural_color(fragment: &Fragment, uniforms: &Uniforms, planet_type: &PlanetType, baked: Option<&Texture>) -> Color {
+    // Superficie horneada: se muestrea la textura en lugar de evaluar el ruido
+    if let Some(texture) = baked {
+        let lod = texture_lod(fragment, uniforms, texture.width);
+        return lit_surface(fragment, uniforms, planet_type, texture.sample_direction(&fragment.vertex_position, lod));
+    }
 
     match planet_type {
         PlanetType::Sun => lava_shader(fragment, uniforms),
@@ -70,16 +142,297 @@ pub fn fragment_shader(fragment: &Fragment, uniforms: &Uniforms, planet_type: &P
         PlanetType::Uranus => uranus_shader(fragment, uniforms),
         PlanetType::Neptune => neptune_shader(fragment, uniforms),
         PlanetType::BlackHole => black_hole_shader(fragment, uniforms),
+        PlanetType::Comet => comet_shader(fragment, uniforms),
+        PlanetType::Asteroid => asteroid_shader(fragment, uniforms),
+        PlanetType::Wormhole => wormhole_shader(fragment, uniforms),
+        PlanetType::Rings => ring_shader(fragment, uniforms),
         PlanetType::Spaceship => {
-            
-            Color::new(192, 192, 192) 
+            // Con .mtl cada parte usa su material; si no, cada grupo tiene su propio color
+            if uniforms.vertex_colors {
+                fragment.color
+            } else {
+                SHIP_PART_COLORS[uniforms.mesh_group % SHIP_PART_COLORS.len()]
+            }
         },
         PlanetType::Rei => {
-            // Un color base para Rei
-            let base_color = Color::new(255, 255, 255);  // Color piel claro
-            base_color * fragment.intensity
+            // Un color base para Rei, o el de sus materiales si los tiene
+            let base_color = if uniforms.vertex_colors {
+                fragment.color
+            } else {
+                Color::new(255, 255, 255)  // Color piel claro
+            };
+            base_color * diffuse_intensity(fragment, uniforms)
+        }
+    }
+}
+
+// Colores de las partes de la nave, por índice de grupo; el primero es el casco
+const SHIP_PART_COLORS: [Color; 4] = [
+    Color::new(192, 192, 192),
+    Color::new(60, 90, 160),
+    Color::new(200, 60, 40),
+    Color::new(240, 200, 80),
+];
+
+// Ilumina el color de superficie de un cuerpo estático, venga del ruido o de la
+// textura horneada; el relieve, el brillo y lo que cambia con el tiempo (los
+// casquetes de Marte) se agregan aquí y no en la superficie, para que la versión
+// horneada se vea igual que la procedural
+fn lit_surface(fragment: &Fragment, uniforms: &Uniforms, planet_type: &PlanetType, albedo: Color) -> Color {
+    let position = fragment.vertex_position;
+    match planet_type {
+        PlanetType::Mercury => {
+            // Los cráteres y el terreno inclinan la normal para que la luz marque el relieve
+            let height = |point: &Vec3| terrain_height(point, uniforms, 300.0, 600.0);
+            let normal = perturbed_normal(&position, &position, height, MERCURY_BUMP_STRENGTH);
+            albedo * bumped_intensity(fragment, uniforms, &normal)
+        },
+        PlanetType::Moon => {
+            // El relieve de los cráteres inclina la normal; el polvo lunar apenas da un reflejo difuso
+            let height = |point: &Vec3| terrain_height(point, uniforms, 400.0, 800.0);
+            let normal = perturbed_normal(&position, &position, height, MOON_BUMP_STRENGTH);
+            albedo * bumped_intensity(fragment, uniforms, &normal) + sun_specular(fragment, uniforms, 6.0, 0.08)
+        },
+        PlanetType::Mars => with_mars_ice_caps(&position, albedo, uniforms) * diffuse_intensity(fragment, uniforms),
+        _ => albedo * diffuse_intensity(fragment, uniforms),
+    }
+}
+
+// Superficie de los cuerpos que no se animan con el tiempo, y que por tanto se pueden hornear
+pub fn static_surface(planet_type: &PlanetType) -> Option<SurfaceShader> {
+    match planet_type {
+        PlanetType::Mercury => Some(mercury_surface),
+        PlanetType::Mars => Some(mars_surface),
+        PlanetType::Moon => Some(moon_surface),
+        _ => None,
+    }
+}
+
+// Evalúa la superficie una vez sobre una rejilla UV equirectangular (en el centro de cada texel)
+pub fn bake_surface(surface: SurfaceShader, uniforms: &Uniforms, resolution: (usize, usize)) -> Texture {
+    let (width, height) = resolution;
+    let mut texture = Texture::new(width, height);
+
+    for y in 0..height {
+        for x in 0..width {
+            let uv = Vec2::new(
+                (x as f32 + 0.5) / width as f32,
+                (y as f32 + 0.5) / height as f32,
+            );
+            texture.set(x, y, surface(&uv_to_direction(uv), uniforms));
         }
     }
+
+    texture.with_mipmaps()
+}
+
+// Iluminación difusa compartida por los cuerpos que reciben luz del Sol
+fn diffuse_intensity(fragment: &Fragment, uniforms: &Uniforms) -> f32 {
+    shade_intensity(fragment.intensity, uniforms)
+}
+
+// Aplica el pulso del Sol, el albedo y las bandas del cel-shading a una intensidad de luz
+fn shade_intensity(light: f32, uniforms: &Uniforms) -> f32 {
+    // El albedo indica qué fracción de la luz refleja cada cuerpo
+    let intensity = light * uniforms.sun_pulse * uniforms.albedo;
+
+    if uniforms.toon_enabled {
+        quantize_intensity(intensity, uniforms.toon.levels)
+    } else {
+        intensity
+    }
+}
+
+// Nivel de mipmap de una textura equirectangular de `width` texels de ancho sobre el
+// cuerpo: log2 de cuántos texels cubre el fragmento. A la profundidad `clip_w` un
+// pixel mide clip_w / (pixeles por unidad) en el mundo, que sobre la esfera unitaria
+// del modelo es ese tamaño dividido por la escala del cuerpo (en radianes), y se
+// estira hacia el borde, donde la superficie se ve de canto
+fn texture_lod(fragment: &Fragment, uniforms: &Uniforms, width: usize) -> f32 {
+    let (projection, viewport) = (&uniforms.projection_matrix, &uniforms.viewport_matrix);
+    let pixels_per_unit = (projection[(0, 0)] * viewport[(0, 0)]).abs()
+        .min((projection[(1, 1)] * viewport[(1, 1)]).abs());
+    let model = &uniforms.model_matrix;
+    let body_scale = Vec3::new(model[(0, 0)], model[(1, 0)], model[(2, 0)]).magnitude();
+
+    let view_direction = (uniforms.camera_position - world_position(fragment, uniforms)).normalize();
+    let facing = fragment.normal.normalize().dot(&view_direction).abs().max(MIN_TEXTURE_FACING);
+
+    let angle = fragment.clip_w / (pixels_per_unit * body_scale * facing);
+    let texels = angle * width as f32 / TAU;
+    texels.max(1.0).log2()
+}
+
+// Coseno mínimo entre la normal y la vista al estimar el mipmap, para que el borde
+// de la silueta no salte al nivel más borroso
+const MIN_TEXTURE_FACING: f32 = 0.25;
+
+// Término lambertiano con la dirección real al Sol: normal en el mundo contra la
+// dirección del fragmento hacia `uniforms.sun_position`, en [-1, 1]
+fn sun_lambert(fragment: &Fragment, uniforms: &Uniforms) -> f32 {
+    let sun_direction = (uniforms.sun_position - world_position(fragment, uniforms)).normalize();
+    fragment.normal.normalize().dot(&sun_direction)
+}
+
+// Término especular de Blinn-Phong: (n · h)^brillo, con h a medio camino entre
+// la dirección a la luz y la dirección a la cámara. Cero en el lado sin luz
+fn specular(normal: &Vec3, view_dir: &Vec3, light_dir: &Vec3, shininess: f32) -> f32 {
+    if normal.dot(light_dir) <= 0.0 {
+        return 0.0;
+    }
+    let halfway = (light_dir + view_dir).normalize();
+    normal.dot(&halfway).max(0.0).powf(shininess)
+}
+
+// Reflejo del Sol en el fragmento, visto desde la cámara, escalado por `strength`
+fn sun_specular(fragment: &Fragment, uniforms: &Uniforms, shininess: f32, strength: f32) -> Color {
+    let position = world_position(fragment, uniforms);
+    let normal = fragment.normal.normalize();
+    let view_dir = (uniforms.camera_position - position).normalize();
+    let light_dir = (uniforms.sun_position - position).normalize();
+
+    let highlight = specular(&normal, &view_dir, &light_dir, shininess) * strength * uniforms.sun_pulse;
+    Color::new(255, 250, 235) * highlight
+}
+
+// Término de Fresnel: casi 0 de frente a la cámara y 1 en el borde del disco.
+// `power` controla lo angosto del borde
+fn fresnel(normal: &Vec3, view_dir: &Vec3, power: f32) -> f32 {
+    (1.0 - normal.dot(view_dir).abs()).clamp(0.0, 1.0).powf(power)
+}
+
+// Brillo de la atmósfera en el borde de un planeta: color, ancho (`power`) y
+// cuánto tapa la superficie (`strength`)
+struct Atmosphere {
+    color: Color,
+    power: f32,
+    strength: f32,
+}
+
+const EARTH_ATMOSPHERE: Atmosphere = Atmosphere { color: Color::new(150, 200, 255), power: 2.0, strength: 0.4 };
+const VENUS_ATMOSPHERE: Atmosphere = Atmosphere { color: Color::new(255, 220, 150), power: 0.5, strength: 0.3 };
+const URANUS_ATMOSPHERE: Atmosphere = Atmosphere { color: Color::new(200, 240, 255), power: 3.0, strength: 0.35 };
+const NEPTUNE_ATMOSPHERE: Atmosphere = Atmosphere { color: Color::new(120, 170, 255), power: 3.0, strength: 0.4 };
+
+// Mezcla el color de la atmósfera según el ángulo entre la normal y la dirección
+// real a la cámara, así el halo sigue al borde de la esfera desde cualquier punto de vista
+fn atmosphere_glow(color: Color, fragment: &Fragment, uniforms: &Uniforms, atmosphere: &Atmosphere) -> Color {
+    let view_dir = (uniforms.camera_position - world_position(fragment, uniforms)).normalize();
+    let rim = fresnel(&fragment.normal.normalize(), &view_dir, atmosphere.power);
+    color.lerp_linear(&atmosphere.color, rim * atmosphere.strength)
+}
+
+// Paso (en unidades del modelo) con el que se muestrea el relieve a cada lado del punto
+const BUMP_EPSILON: f32 = 0.0005;
+const MOON_BUMP_STRENGTH: f32 = 0.04;
+const MERCURY_BUMP_STRENGTH: f32 = 0.05;
+
+// Normal inclinada según la pendiente del relieve: se muestrea la altura `noise` en
+// dos direcciones tangentes a la esfera y la normal se aleja de donde el terreno sube.
+// `position` y `base_normal` están en el espacio del modelo, igual que el resultado
+fn perturbed_normal(position: &Vec3, base_normal: &Vec3, noise: impl Fn(&Vec3) -> f32, strength: f32) -> Vec3 {
+    let normal = base_normal.normalize();
+    // Cualquier eje que no sea paralelo a la normal sirve para construir la base tangente
+    let reference = if normal.y.abs() < 0.99 { Vec3::new(0.0, 1.0, 0.0) } else { Vec3::new(1.0, 0.0, 0.0) };
+    let tangent = normal.cross(&reference).normalize();
+    let bitangent = normal.cross(&tangent);
+
+    let height = noise(position);
+    let slope_t = (noise(&(position + tangent * BUMP_EPSILON)) - height) / BUMP_EPSILON;
+    let slope_b = (noise(&(position + bitangent * BUMP_EPSILON)) - height) / BUMP_EPSILON;
+
+    (normal - (tangent * slope_t + bitangent * slope_b) * strength).normalize()
+}
+
+// Luz difusa del Sol sobre una normal del modelo que no es la interpolada del fragmento
+fn bumped_intensity(fragment: &Fragment, uniforms: &Uniforms, model_normal: &Vec3) -> f32 {
+    let normal = (mat4_to_mat3(&uniforms.model_matrix) * model_normal).normalize();
+    let sun_direction = (uniforms.sun_position - world_position(fragment, uniforms)).normalize();
+    shade_intensity(normal.dot(&sun_direction).max(0.0), uniforms)
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+// Cuánto hielo polar hay en `position` (en [0, 1]): la latitud se obtiene de la
+// posición normalizada del vértice y el borde se deforma con ruido para que el
+// casquete no sea un círculo perfecto
+fn ice_cap_factor(position: &Vec3, latitude_threshold: f32, uniforms: &Uniforms) -> f32 {
+    let direction = position.normalize();
+    let latitude = direction.y.clamp(-1.0, 1.0).asin().abs();
+
+    let zoom = 150.0;
+    let wobble = uniforms.noise.cloud.get_noise_3d(
+        direction.x * zoom,
+        direction.y * zoom,
+        direction.z * zoom
+    ) * 0.12;
+
+    smoothstep(latitude_threshold - 0.03, latitude_threshold + 0.03, latitude + wobble)
+}
+
+// Reduce la intensidad a `levels` bandas uniformes entre 0 y 1
+fn quantize_intensity(intensity: f32, levels: u32) -> f32 {
+    if levels < 2 {
+        return intensity;
+    }
+
+    let levels = levels as f32;
+    ((intensity * levels).floor() / (levels - 1.0)).clamp(0.0, 1.0)
+}
+
+// Posición del fragmento en el mundo a partir de su posición en el modelo
+fn world_position(fragment: &Fragment, uniforms: &Uniforms) -> Vec3 {
+    let position = uniforms.model_matrix * Vec4::new(
+        fragment.vertex_position.x,
+        fragment.vertex_position.y,
+        fragment.vertex_position.z,
+        1.0
+    );
+    Vec3::new(position.x, position.y, position.z)
+}
+
+// Fracción del disco del Sol que la Luna tapa vista desde `position`, en [0, 1].
+// Es la intersección del rayo hacia el Sol con la esfera de la Luna, pero comparando
+// tamaños angulares: si la separación entre ambos centros es menor que la diferencia
+// de radios aparentes la sombra es total (umbra), y entre eso y la suma de radios
+// cae suavemente a cero (penumbra)
+fn moon_shadow(position: &Vec3, uniforms: &Uniforms) -> f32 {
+    if uniforms.moon_radius <= 0.0 {
+        return 0.0;
+    }
+
+    let to_sun = uniforms.sun_position - position;
+    let to_moon = uniforms.moon_position - position;
+    let (sun_distance, moon_distance) = (to_sun.magnitude(), to_moon.magnitude());
+    // La Luna tiene que estar delante del Sol y no contener al punto
+    if moon_distance >= sun_distance || moon_distance <= uniforms.moon_radius {
+        return 0.0;
+    }
+
+    let sun_angle = (uniforms.sun_radius / sun_distance).min(1.0).asin().max(1e-4);
+    let moon_angle = (uniforms.moon_radius / moon_distance).min(1.0).asin();
+    let separation = (to_sun.dot(&to_moon) / (sun_distance * moon_distance)).clamp(-1.0, 1.0).acos();
+
+    let outer = sun_angle + moon_angle;
+    if separation >= outer {
+        return 0.0;
+    }
+    // Una Luna más pequeña que el Sol solo tapa la proporción de áreas (eclipse anular)
+    let full_cover = (moon_angle / sun_angle).powi(2).min(1.0);
+    let inner = (moon_angle - sun_angle).abs();
+    if separation <= inner {
+        return full_cover;
+    }
+    full_cover * (1.0 - smoothstep(inner, outer, separation))
+}
+
+// Un fragmento está en la silueta cuando su normal es casi perpendicular a la vista
+fn is_silhouette(fragment: &Fragment, uniforms: &Uniforms) -> bool {
+    let view_direction = (uniforms.camera_position - world_position(fragment, uniforms)).normalize();
+    fragment.normal.normalize().dot(&view_direction).abs() < uniforms.toon.outline_threshold
 }
 
 fn blend_layers(base: Color, clouds: Color) -> Color {
@@ -92,26 +445,12 @@ fn blend_layers(base: Color, clouds: Color) -> Color {
     ) / (3.0 * 255.0);
 
     if cloud_intensity > 0.3 { // Reducido el umbral para que más nubes sean visibles
-        base.lerp(&clouds, 0.7) // Puedes ajustar la opacidad (0.7) según necesites
+        base.lerp_linear(&clouds, 0.7) // Puedes ajustar la opacidad (0.7) según necesites
     } else {
         base
     }
 }
 
-fn random_color_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
-  let seed = uniforms.time as u64;
-
-  let mut rng = StdRng::seed_from_u64(seed);
-
-  let r = rng.gen_range(0..=255);
-  let g = rng.gen_range(0..=255);
-  let b = rng.gen_range(0..=255);
-
-  let random_color = Color::new(r, g, b);
-
-  random_color * fragment.intensity
-}
-
 fn cloud_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     let zoom = 100.0;  // Reducido para nubes más grandes
     let ox = 100.0;
@@ -120,7 +459,7 @@ fn cloud_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     let y = fragment.vertex_position.y;
     let t = uniforms.time as f32 * 0.1;
 
-    let noise_value = uniforms.noise.get_noise_2d(x * zoom + ox + t, y * zoom + oy);
+    let noise_value = uniforms.noise.cloud.get_noise_2d(x * zoom + ox + t, y * zoom + oy);
 
     // Define cloud threshold and colors
     let cloud_threshold = 0.1; // Reducido para más cobertura
@@ -132,7 +471,8 @@ fn cloud_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
         0.0
     };
 
-    cloud_color * (cloud_factor * fragment.intensity)
+    // Las nubes siguen el mismo día y noche que la superficie
+    cloud_color * (cloud_factor * shade_intensity(sun_lambert(fragment, uniforms).max(0.0), uniforms))
 }
 
 
@@ -156,25 +496,25 @@ fn lava_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
   // Pulsate on the z-axis to change spot size
   let pulsate = (t * base_frequency).sin() * pulsate_amplitude;
 
-  // Zoom reducido para patrones más grandes
-  let zoom = 800.0;
-  let noise_value1 = uniforms.noise.get_noise_3d(
+  // Ruido de lava (Perlin FBm, de frecuencia baja): manchas grandes con detalle fino
+  let zoom = 2000.0;
+  let noise_value1 = uniforms.noise.lava.get_noise_3d(
     position.x * zoom,
     position.y * zoom,
     (position.z + pulsate) * zoom
   );
-  let noise_value2 = uniforms.noise.get_noise_3d(
+  let noise_value2 = uniforms.noise.lava.get_noise_3d(
     (position.x + 1000.0) * zoom,
     (position.y + 1000.0) * zoom,
     (position.z + 1000.0 + pulsate) * zoom
   );
-  // Ajuste del contraste del ruido
-  let noise_value = ((noise_value1 + noise_value2) * 0.5 + 0.2).min(1.0);
+  // Ajuste del contraste del ruido (el FBm de lava varía alrededor de ±0.5)
+  let noise_value = (noise_value1 + noise_value2 + 0.2).clamp(0.0, 1.0);
 
-  let color = dark_color.lerp(&bright_color, noise_value);
+  let color = dark_color.lerp_linear(&bright_color, noise_value);
 
-  // Aumentar la intensidad general
-  color * fragment.intensity * 1.2
+  // La intensidad extra y el pulso solar se aplican después, en `emission`
+  color * fragment.intensity
 }
 
 fn earth_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
@@ -188,17 +528,18 @@ fn earth_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
         fragment.depth
     );
 
-    // Un solo nivel de ruido para los continentes
-    let zoom = 250.0;  // Ajustado para continentes más grandes
-    let noise_value = uniforms.noise.get_noise_3d(
+    // Continentes con el ruido de terreno (celular FBm), que toma valores entre
+    // -1 y -0.3 aproximadamente: lo que pasa del umbral es tierra, cerca de un 30%
+    let zoom = 50.0;
+    let noise_value = uniforms.noise.ground.get_noise_3d(
         position.x * zoom,
         position.y * zoom,
         position.z * zoom
-    ).abs();  // Usar valor absoluto para evitar valores negativos
+    );
 
     // Umbral más definido para la separación tierra/agua
-    let threshold = 0.5;
-    let transition_width = 0.1;
+    let threshold = -0.66;
+    let transition_width = 0.03;
 
     // Transición suave entre tierra y agua
     let land_factor = if noise_value < (threshold - transition_width) {
@@ -211,29 +552,82 @@ fn earth_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     };
 
     // Mezclar colores
-    let base_color = ocean_color.lerp(&land_color, land_factor);
+    let base_color = ocean_color.lerp_linear(&land_color, land_factor);
 
-    // Efecto simple de atmósfera en los bordes
-    let atmosphere_color = Color::new(150, 200, 255);
-    let normal_dot = fragment.normal.dot(&Vec3::new(0.0, 0.0, 1.0));
-    let atmosphere_factor = (1.0 - normal_dot.abs()).powf(2.0);
-    
-    let final_color = base_color.lerp(&atmosphere_color, atmosphere_factor * 0.4);
-    
-    final_color * fragment.intensity
+    // Casquetes polares
+    let ice_color = Color::new(245, 248, 255);
+    let ice = ice_cap_factor(&fragment.vertex_position, uniforms.ice_caps.earth_latitude, uniforms);
+    let base_color = base_color.lerp_linear(&ice_color, ice);
+
+    // Atmósfera en los bordes
+    let final_color = atmosphere_glow(base_color, fragment, uniforms, &EARTH_ATMOSPHERE);
+
+    // Día y noche según la dirección real al Sol, con un terminador suave
+    let lambert = sun_lambert(fragment, uniforms);
+    let daylight = smoothstep(-0.15, 0.15, lambert);
+    // Reflejo del Sol solo sobre el océano abierto
+    let ocean = (1.0 - land_factor) * (1.0 - ice);
+    // Durante un eclipse la Luna tapa parte (o todo) el disco del Sol
+    let sunlight = 1.0 - moon_shadow(&world_position(fragment, uniforms), uniforms);
+    let day_color = (final_color * shade_intensity(lambert.max(0.0), uniforms)
+        + sun_specular(fragment, uniforms, 64.0, 0.6) * ocean) * sunlight;
+
+    // Lado nocturno casi negro con destellos azulados dispersos
+    let speckle = uniforms.noise.cloud.get_noise_3d(
+        fragment.vertex_position.x * 3000.0,
+        fragment.vertex_position.y * 3000.0,
+        fragment.vertex_position.z * 3000.0
+    );
+    let speckle_glow = ((speckle - 0.6) / 0.4).clamp(0.0, 1.0);
+    let night_color = final_color * 0.03 + Color::new(90, 120, 200) * (speckle_glow * 0.6);
+
+    // Luces de ciudades: puntos cálidos agrupados sobre los continentes, a plena
+    // intensidad y solo en el lado nocturno (se apagan al cruzar el terminador)
+    let night = 1.0 - smoothstep(-0.1, 0.1, lambert);
+    let city_lights = if land_factor > 0.5 && night > 0.0 {
+        let p = fragment.vertex_position;
+        // El ruido celular vale -1 en el centro de cada celda: las ciudades se agrupan ahí
+        let clusters = uniforms.noise.cell.get_noise_3d(p.x * 60.0, p.y * 60.0, p.z * 60.0);
+        let dots = uniforms.noise.cloud.get_noise_3d(p.x * 4000.0, p.y * 4000.0, p.z * 4000.0);
+        let glow = if clusters < -0.85 { ((dots - 0.3) / 0.3).clamp(0.0, 1.0) } else { 0.0 };
+        Color::new(255, 190, 90) * (glow * night * land_factor)
+    } else {
+        Color::black()
+    };
+
+    night_color.lerp_linear(&day_color, daylight) + city_lights
 }
 
 fn mercury_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+    lit_surface(fragment, uniforms, &PlanetType::Mercury, mercury_surface(&fragment.vertex_position, uniforms))
+}
+
+// Altura del terreno rocoso: el ruido base sube el suelo y los cráteres (donde el
+// ruido fino ronda 0.7, el mismo umbral de su color) lo hunden
+fn terrain_height(position: &Vec3, uniforms: &Uniforms, zoom: f32, crater_zoom: f32) -> f32 {
+    let terrain = uniforms.noise.cloud.get_noise_3d(
+        position.x * zoom,
+        position.y * zoom,
+        position.z * zoom
+    ).abs();
+    let craters = uniforms.noise.cloud.get_noise_3d(
+        position.x * crater_zoom,
+        position.y * crater_zoom,
+        position.z * crater_zoom
+    ).abs();
+    terrain * 0.5 - smoothstep(0.55, 0.85, craters) * 0.3
+}
+
+fn mercury_surface(position: &Vec3, uniforms: &Uniforms) -> Color {
     // Colores grisáceos y marrones para el terreno rocoso
     let dark_color = Color::new(80, 75, 70);    // Gris oscuro
     let light_color = Color::new(170, 160, 150); // Gris claro
     let crater_color = Color::new(60, 55, 50);   // Gris más oscuro para cráteres
     
-    let position = fragment.vertex_position;
     let zoom = 300.0;
     
     // Ruido base para el terreno
-    let terrain = uniforms.noise.get_noise_3d(
+    let terrain = uniforms.noise.cloud.get_noise_3d(
         position.x * zoom,
         position.y * zoom,
         position.z * zoom
@@ -241,20 +635,18 @@ fn mercury_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     
     // Ruido adicional para cráteres
     let crater_zoom = 600.0;
-    let craters = uniforms.noise.get_noise_3d(
+    let craters = uniforms.noise.cloud.get_noise_3d(
         position.x * crater_zoom,
         position.y * crater_zoom,
         position.z * crater_zoom
     ).abs();
     
-    let base_color = dark_color.lerp(&light_color, terrain);
-    let final_color = if craters > 0.7 {
-        base_color.lerp(&crater_color, 0.5)
+    let base_color = dark_color.lerp_linear(&light_color, terrain);
+    if craters > 0.7 {
+        base_color.lerp_linear(&crater_color, 0.5)
     } else {
         base_color
-    };
-    
-    final_color * fragment.intensity
+    }
 }
 
 fn venus_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
@@ -267,32 +659,32 @@ fn venus_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     
     // Patrones de nubes en movimiento
     let cloud_zoom = 150.0;
-    let clouds = uniforms.noise.get_noise_3d(
+    let clouds = uniforms.noise.cloud.get_noise_3d(
         position.x * cloud_zoom + t,
         position.y * cloud_zoom,
         position.z * cloud_zoom
     ).abs();
     
-    let final_color = base_color.lerp(&cloud_color, clouds);
+    let final_color = base_color.lerp_linear(&cloud_color, clouds);
     
     // Efecto de atmósfera densa
-    let atmosphere_factor = (1.0 - fragment.normal.dot(&Vec3::new(0.0, 0.0, 1.0))).powf(0.5);
-    let atmosphere_color = Color::new(255, 220, 150);
-    
-    final_color.lerp(&atmosphere_color, atmosphere_factor * 0.3) * fragment.intensity
+    atmosphere_glow(final_color, fragment, uniforms, &VENUS_ATMOSPHERE) * diffuse_intensity(fragment, uniforms)
 }
 
 fn mars_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+    lit_surface(fragment, uniforms, &PlanetType::Mars, mars_surface(&fragment.vertex_position, uniforms))
+}
+
+fn mars_surface(position: &Vec3, uniforms: &Uniforms) -> Color {
     // Colores rojizos característicos de Marte
     let dark_red = Color::new(145, 50, 20);    // Rojo oscuro
     let light_red = Color::new(200, 80, 30);   // Rojo claro
     let dust_color = Color::new(230, 130, 50);  // Color polvo marciano
     
-    let position = fragment.vertex_position;
     let zoom = 250.0;
     
     // Terreno base
-    let terrain = uniforms.noise.get_noise_3d(
+    let terrain = uniforms.noise.cloud.get_noise_3d(
         position.x * zoom,
         position.y * zoom,
         position.z * zoom
@@ -300,101 +692,178 @@ fn mars_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     
     // Patrones de polvo
     let dust_zoom = 400.0;
-    let dust = uniforms.noise.get_noise_3d(
+    let dust = uniforms.noise.cloud.get_noise_3d(
         position.x * dust_zoom,
         position.y * dust_zoom,
         position.z * dust_zoom
     ).abs();
     
-    let base_color = dark_red.lerp(&light_red, terrain);
-    let final_color = base_color.lerp(&dust_color, dust * 0.3);
-    
-    final_color * fragment.intensity
+    let base_color = dark_red.lerp_linear(&light_red, terrain);
+    base_color.lerp_linear(&dust_color, dust * 0.3)
+}
+
+// Casquetes polares más pequeños y de un blanco azulado
+fn with_mars_ice_caps(position: &Vec3, surface_color: Color, uniforms: &Uniforms) -> Color {
+    let ice_color = Color::new(215, 230, 250);
+    let ice = ice_cap_factor(position, uniforms.ice_caps.mars_latitude, uniforms);
+    surface_color.lerp_linear(&ice_color, ice)
+}
+
+// Latitud (radianes, de -π/2 a π/2) y longitud de un punto de la esfera en espacio de objeto
+fn latitude_longitude(position: &Vec3) -> (f32, f32) {
+    let direction = position.normalize();
+    (direction.y.clamp(-1.0, 1.0).asin(), direction.z.atan2(direction.x))
+}
+
+// Bandas de un gigante gaseoso en [0, 1]: dependen solo de la latitud, así forman
+// anillos paralelos igual de espaciados hasta los polos. La turbulencia desplaza la
+// latitud de entrada para que los bordes de las bandas se arremolinen
+fn gas_giant_bands(position: &Vec3, uniforms: &Uniforms, frequency: f32, swirl: f32, t: f32) -> f32 {
+    let (latitude, _) = latitude_longitude(position);
+    let turbulence = uniforms.noise.cloud.get_noise_3d(
+        position.x * 300.0 + t,
+        position.y * 300.0,
+        position.z * 300.0
+    );
+    let warped = latitude + turbulence * swirl;
+
+    let stripes = (warped * frequency).sin() * 0.5 + 0.5;
+    let variation = uniforms.noise.cloud.get_noise_2d(warped * frequency * 4.0, t).abs();
+    (stripes * 0.7 + variation * 0.3).clamp(0.0, 1.0)
+}
+
+// Gran Mancha Roja: óvalo en el hemisferio sur que deriva despacio en longitud
+const GREAT_RED_SPOT_LATITUDE: f32 = -0.38;
+const GREAT_RED_SPOT_LONGITUDE: f32 = 1.2;
+const GREAT_RED_SPOT_DRIFT: f32 = 0.002;
+// Semiejes del óvalo en radianes (longitud, latitud)
+const GREAT_RED_SPOT_SIZE: (f32, f32) = (0.32, 0.14);
+
+// Cuánto de la Gran Mancha Roja cubre el punto, en [0, 1], con el borde difuminado
+fn great_red_spot(position: &Vec3, time: f32) -> f32 {
+    let (latitude, longitude) = latitude_longitude(position);
+    let center = GREAT_RED_SPOT_LONGITUDE + time * GREAT_RED_SPOT_DRIFT;
+    // Diferencia de longitud en (-π, π], acortada por la latitud como sobre la esfera
+    let delta_longitude = (longitude - center + PI).rem_euclid(TAU) - PI;
+    let u = delta_longitude * latitude.cos() / GREAT_RED_SPOT_SIZE.0;
+    let v = (latitude - GREAT_RED_SPOT_LATITUDE) / GREAT_RED_SPOT_SIZE.1;
+    1.0 - smoothstep(0.6, 1.0, (u * u + v * v).sqrt())
 }
 
 fn jupiter_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     // Colores para las bandas de Júpiter
     let light_band = Color::new(255, 220, 180);  // Banda clara
     let dark_band = Color::new(180, 140, 100);   // Banda oscura
-    let storm_color = Color::new(255, 160, 120); // Color para la Gran Mancha Roja
+    let storm_color = Color::new(200, 90, 60);   // Color para la Gran Mancha Roja
     
     let position = fragment.vertex_position;
     let t = uniforms.time as f32 * 0.1;
     
-    // Bandas horizontales
-    let band_zoom = 100.0;
-    let bands = uniforms.noise.get_noise_2d(
-        position.y * band_zoom,
-        t
-    ).abs();
-    
-    // Turbulencia adicional
-    let turb_zoom = 300.0;
-    let turbulence = uniforms.noise.get_noise_3d(
-        position.x * turb_zoom + t,
-        position.y * turb_zoom,
-        position.z * turb_zoom
-    ).abs();
-    
-    let base_color = dark_band.lerp(&light_band, bands);
-    let final_color = base_color.lerp(&storm_color, turbulence * 0.3);
+    // Bandas horizontales por latitud, arremolinadas por la turbulencia
+    let bands = gas_giant_bands(&position, uniforms, 14.0, 0.08, t);
+    let base_color = dark_band.lerp_linear(&light_band, bands);
+
+    // La mancha tiene su propio remolino interior
+    let spot = great_red_spot(&position, uniforms.time as f32);
+    let swirl = uniforms.noise.cloud.get_noise_3d(position.x * 500.0, position.y * 500.0, position.z * 500.0 + t).abs();
+    let final_color = base_color.lerp_linear(&storm_color, spot * (0.75 + swirl * 0.25));
     
-    final_color * fragment.intensity
+    // Brillo amplio y suave de la atmósfera
+    final_color * diffuse_intensity(fragment, uniforms) + sun_specular(fragment, uniforms, 12.0, 0.2)
 }
 
 fn saturn_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
-    // Colores para Saturno y sus anillos
+    // Colores para Saturno (los anillos son una malla aparte, ver ring_shader)
     let planet_light = Color::new(255, 240, 200);  // Color claro del planeta
     let planet_dark = Color::new(200, 180, 140);   // Color oscuro del planeta
-    let ring_light = Color::new(210, 190, 170);    // Color claro del anillo
-    let ring_dark = Color::new(160, 140, 120);     // Color oscuro del anillo
     
     let position = fragment.vertex_position;
-    let normal = fragment.normal;
+
+    // Color del planeta con bandas por latitud, más suaves que las de Júpiter
+    let t = uniforms.time as f32 * 0.08;
+    let bands = gas_giant_bands(&position, uniforms, 10.0, 0.04, t);
+
+    // Franja oscura donde los anillos se interponen entre el planeta y el Sol
+    let shadow = ring_shadow(&world_position(fragment, uniforms), uniforms);
     
-    // Calcular distancia desde el centro
-    let radius = (position.x * position.x + position.z * position.z).sqrt();
-    let y_abs = position.y.abs();
-    
-    // Definir los parámetros del anillo
-    let ring_inner = 1.2;    // Donde comienza el anillo (justo fuera del planeta)
-    let ring_outer = 2.5;    // Donde termina el anillo
-    let ring_thickness = 0.1; // Grosor del anillo
-    
-    // Determinar si estamos en el anillo
-    let in_ring = radius >= ring_inner && 
-                  radius <= ring_outer && 
-                  y_abs <= ring_thickness;
-    
-    if in_ring {
-        // Patrón de anillos concéntricos
-        let ring_pattern = ((radius * 20.0).sin() * 0.5 + 0.5).abs();
-        
-        // Variación adicional en los anillos
-        let detail = uniforms.noise.get_noise_2d(
-            radius * 15.0,
-            position.z.atan2(position.x) * 5.0
-        ).abs();
-        
-        // Combinar patrones
-        let ring_factor = ring_pattern * 0.7 + detail * 0.3;
-        
-        // Color final del anillo
-        let ring_color = ring_light.lerp(&ring_dark, ring_factor);
-        
-        // Aplicar sombreado basado en la normal
-        let light_factor = normal.dot(&Vec3::new(0.0, 1.0, 0.0)).abs();
-        ring_color * fragment.intensity * light_factor.max(0.2)
-    } else {
-        // Color del planeta con bandas
-        let t = uniforms.time as f32 * 0.08;
-        let bands = uniforms.noise.get_noise_2d(
-            position.y * 120.0,
-            t
-        ).abs();
-        
-        planet_light.lerp(&planet_dark, bands) * fragment.intensity
+    planet_light.lerp_linear(&planet_dark, bands) * (diffuse_intensity(fragment, uniforms) * (1.0 - shadow))
+}
+
+// Cuánto oscurecen los anillos a un punto del planeta: el rayo hacia el Sol corta el
+// plano del disco y se mira si el corte cae entre los dos radios (bordes suavizados).
+// Los anillos dejan pasar algo de luz, así que la sombra nunca es total
+const RING_SHADOW_OPACITY: f32 = 0.6;
+const RING_SHADOW_EDGE: f32 = 0.1;
+
+fn ring_shadow(position: &Vec3, uniforms: &Uniforms) -> f32 {
+    let Some(rings) = &uniforms.rings else {
+        return 0.0;
+    };
+
+    let sun_direction = (uniforms.sun_position - position).normalize();
+    let facing = sun_direction.dot(&rings.normal);
+    if facing.abs() < 1e-4 {
+        return 0.0; // Rayo paralelo al plano
+    }
+    let distance = (rings.center - position).dot(&rings.normal) / facing;
+    if distance <= 0.0 {
+        return 0.0; // El disco queda del lado opuesto al Sol
+    }
+
+    let radius = (position + sun_direction * distance - rings.center).magnitude();
+    let inside = smoothstep(rings.inner_radius, rings.inner_radius + RING_SHADOW_EDGE, radius)
+        * (1.0 - smoothstep(rings.outer_radius - RING_SHADOW_EDGE, rings.outer_radius, radius));
+    inside * RING_SHADOW_OPACITY
+}
+
+// Cuánto tapa el planeta al Sol visto desde un punto de los anillos: el rayo hacia el
+// Sol contra la esfera del planeta, con una penumbra estrecha según la distancia del
+// rayo al centro de la esfera
+fn planet_shadow_on_rings(position: &Vec3, uniforms: &Uniforms) -> f32 {
+    let Some(rings) = &uniforms.rings else {
+        return 0.0;
+    };
+
+    let sun_direction = (uniforms.sun_position - position).normalize();
+    let to_center = rings.center - position;
+    let along = to_center.dot(&sun_direction);
+    if along <= 0.0 {
+        return 0.0; // El planeta está detrás del punto respecto al Sol
     }
+
+    let miss_distance = (to_center - sun_direction * along).magnitude();
+    let penumbra = rings.planet_radius * 0.05;
+    1.0 - smoothstep(rings.planet_radius - penumbra, rings.planet_radius + penumbra, miss_distance)
+}
+
+fn ring_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+    // Colores del anillo
+    let ring_light = Color::new(210, 190, 170);    // Color claro del anillo
+    let ring_dark = Color::new(160, 140, 120);     // Color oscuro del anillo
+
+    // Distancia desde el centro del disco, en unidades del radio del planeta
+    let position = fragment.vertex_position;
+    let radius = (position.x * position.x + position.z * position.z).sqrt();
+
+    // Patrón de anillos concéntricos
+    let ring_pattern = ((radius * 20.0).sin() * 0.5 + 0.5).abs();
+    
+    // Variación adicional en los anillos
+    let detail = uniforms.noise.cloud.get_noise_2d(
+        radius * 15.0,
+        position.z.atan2(position.x) * 5.0
+    ).abs();
+    
+    // Combinar patrones
+    let ring_factor = ring_pattern * 0.7 + detail * 0.3;
+    let ring_color = ring_light.lerp_linear(&ring_dark, ring_factor);
+
+    // El disco es plano y se ve por ambas caras: se ilumina con el ángulo respecto al Sol.
+    // La parte que queda detrás del planeta solo recibe la luz ambiente
+    let light_factor = sun_lambert(fragment, uniforms).abs().max(0.2);
+    let shadow = planet_shadow_on_rings(&world_position(fragment, uniforms), uniforms);
+    let light_factor = light_factor + (0.2 - light_factor) * shadow;
+    ring_color * shade_intensity(light_factor, uniforms)
 }
 
 fn uranus_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
@@ -407,15 +876,16 @@ fn uranus_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     
     // Patrones de nubes suaves
     let cloud_zoom = 200.0;
-    let clouds = uniforms.noise.get_noise_3d(
+    let clouds = uniforms.noise.cloud.get_noise_3d(
         position.x * cloud_zoom + t,
         position.y * cloud_zoom,
         position.z * cloud_zoom
     ).abs();
     
-    let final_color = base_color.lerp(&cloud_color, clouds * 0.4);
+    let final_color = base_color.lerp_linear(&cloud_color, clouds * 0.4);
+    let final_color = atmosphere_glow(final_color, fragment, uniforms, &URANUS_ATMOSPHERE);
     
-    final_color * fragment.intensity
+    final_color * diffuse_intensity(fragment, uniforms)
 }
 
 fn neptune_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
@@ -428,7 +898,7 @@ fn neptune_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     
     // Patrones de tormentas
     let storm_zoom = 250.0;
-    let storms = uniforms.noise.get_noise_3d(
+    let storms = uniforms.noise.cloud.get_noise_3d(
         position.x * storm_zoom + t,
         position.y * storm_zoom,
         position.z * storm_zoom
@@ -436,27 +906,31 @@ fn neptune_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     
     // Bandas sutiles
     let band_zoom = 150.0;
-    let bands = uniforms.noise.get_noise_2d(
+    let bands = uniforms.noise.cloud.get_noise_2d(
         position.y * band_zoom,
         t
     ).abs();
     
-    let final_color = base_color.lerp(&storm_color, (storms + bands * 0.5) * 0.4);
+    let final_color = base_color.lerp_linear(&storm_color, (storms + bands * 0.5) * 0.4);
+    let final_color = atmosphere_glow(final_color, fragment, uniforms, &NEPTUNE_ATMOSPHERE);
     
-    final_color * fragment.intensity
+    final_color * diffuse_intensity(fragment, uniforms)
 }
 
 fn moon_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+    lit_surface(fragment, uniforms, &PlanetType::Moon, moon_surface(&fragment.vertex_position, uniforms))
+}
+
+fn moon_surface(position: &Vec3, uniforms: &Uniforms) -> Color {
     // Colores base para la luna
     let dark_color = Color::new(100, 100, 100);   // Gris oscuro
     let light_color = Color::new(200, 200, 200);  // Gris claro
     let crater_color = Color::new(80, 80, 80);    // Gris más oscuro para cráteres
     
-    let position = fragment.vertex_position;
     let zoom = 400.0;
     
     // Ruido base para el terreno lunar
-    let terrain = uniforms.noise.get_noise_3d(
+    let terrain = uniforms.noise.cloud.get_noise_3d(
         position.x * zoom,
         position.y * zoom,
         position.z * zoom
@@ -464,20 +938,18 @@ fn moon_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     
     // Ruido adicional para cráteres
     let crater_zoom = 800.0;
-    let craters = uniforms.noise.get_noise_3d(
+    let craters = uniforms.noise.cloud.get_noise_3d(
         position.x * crater_zoom,
         position.y * crater_zoom,
         position.z * crater_zoom
     ).abs();
     
-    let base_color = dark_color.lerp(&light_color, terrain);
-    let final_color = if craters > 0.7 {
-        base_color.lerp(&crater_color, 0.5)
+    let base_color = dark_color.lerp_linear(&light_color, terrain);
+    if craters > 0.7 {
+        base_color.lerp_linear(&crater_color, 0.5)
     } else {
         base_color
-    };
-    
-    final_color * fragment.intensity
+    }
 }
 
 fn black_hole_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
@@ -506,7 +978,7 @@ fn black_hole_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     let distortion = 1.0 / (radius + 0.5);
     
     // Patrones de ruido para más detalle
-    let noise = uniforms.noise.get_noise_3d(
+    let noise = uniforms.noise.cloud.get_noise_3d(
         position.x * 2.0 + t,
         position.y * 2.0,
         position.z * 2.0 - t
@@ -521,16 +993,228 @@ fn black_hole_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     } else if radius < 1.0 {
         // Región interna psicodélica
         let factor = ((radius - 0.3) / 0.7).powf(0.5);
-        let base = core_color.lerp(&inner_color, factor * effect);
+        let base = core_color.lerp_linear(&inner_color, factor * effect);
         base * (distortion * 0.5)
     } else if radius < 2.0 {
         // Región externa con vórtice
         let factor = ((radius - 1.0) / 1.0).powf(0.5);
-        let base = inner_color.lerp(&outer_color, factor * effect);
+        let base = inner_color.lerp_linear(&outer_color, factor * effect);
         base * ((3.0 - radius) * 0.5)
     } else {
         // Espacio exterior con distorsión
         let fade = (1.0 / (radius - 1.5)).min(1.0);
-        outer_color.lerp(&space_color, fade) * (0.5 * fade)
+        outer_color.lerp_linear(&space_color, fade) * (0.5 * fade)
     }
-}
\ No newline at end of file
+}
+
+fn comet_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+    // Hielo sucio: gris azulado con manchas oscuras de polvo
+    let ice_color = Color::new(200, 215, 230);   // Hielo
+    let dust_color = Color::new(70, 65, 60);     // Polvo oscuro
+
+    let position = fragment.vertex_position;
+    let zoom = 500.0;
+
+    let dust = uniforms.noise.cloud.get_noise_3d(
+        position.x * zoom,
+        position.y * zoom,
+        position.z * zoom
+    ).abs();
+
+    let final_color = ice_color.lerp_linear(&dust_color, (dust * 1.5).min(0.8));
+
+    final_color * diffuse_intensity(fragment, uniforms)
+}
+
+fn asteroid_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+    // Roca oscura y polvorienta, con vetas más claras
+    let dark_rock = Color::new(70, 62, 55);
+    let light_rock = Color::new(140, 125, 110);
+
+    let position = fragment.vertex_position;
+    let zoom = 200.0;
+
+    let rock = uniforms.noise.cloud.get_noise_3d(
+        position.x * zoom,
+        position.y * zoom,
+        position.z * zoom
+    ).abs();
+
+    dark_rock.lerp_linear(&light_rock, rock) * diffuse_intensity(fragment, uniforms)
+}
+
+fn wormhole_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+    // Anillo emisivo: remolino violeta y cian que gira con el tiempo
+    let violet = Color::new(150, 80, 255);
+    let cyan = Color::new(80, 230, 255);
+
+    let position = fragment.vertex_position;
+    let angle = position.z.atan2(position.x);
+    let radius = (position.x * position.x + position.z * position.z).sqrt();
+    let t = uniforms.time as f32 * 0.05;
+
+    let swirl = ((angle * 6.0 + radius * 12.0 - t).sin() + 1.0) * 0.5;
+    // Más brillante hacia el borde interior del anillo
+    let glow = 1.0 - ((radius - 0.8) / 0.2).clamp(0.0, 1.0) * 0.5;
+
+    violet.lerp_linear(&cyan, swirl) * glow
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm::Mat4;
+
+    // Uniforms de un cuerpo de radio 1 en el origen, visto de frente
+    fn test_uniforms() -> Uniforms {
+        let mut uniforms = Uniforms::new(Mat4::identity(), Mat4::identity());
+        uniforms.camera_position = Vec3::new(0.0, 0.0, 5.0);
+        uniforms
+    }
+
+    // Fragmento totalmente iluminado sobre la esfera unitaria, en la dirección `point`
+    fn surface_fragment(point: Vec3) -> Fragment {
+        let point = point.normalize();
+        Fragment::new(Vec2::new(0.0, 0.0), Color::black(), 0.5, point, 1.0, point, 1.0)
+    }
+
+    // Fragmento justo en el centro del texel (x, y) de una textura de ese tamaño, tan
+    // cerca de la cámara que se muestrea el nivel de mipmap completo
+    fn texel_fragment(size: (usize, usize), x: usize, y: usize) -> Fragment {
+        let uv = Vec2::new((x as f32 + 0.5) / size.0 as f32, (y as f32 + 0.5) / size.1 as f32);
+        let mut fragment = surface_fragment(uv_to_direction(uv));
+        fragment.clip_w = 1e-4;
+        fragment
+    }
+
+    fn assert_close(a: Color, b: Color, tolerance: i32) {
+        for (x, y) in [(a.r, b.r), (a.g, b.g), (a.b, b.b)] {
+            assert!((x as i32 - y as i32).abs() <= tolerance, "{} vs {}", a, b);
+        }
+    }
+
+    #[test]
+    fn baked_moon_keeps_its_relief_and_highlight() {
+        let mut uniforms = test_uniforms();
+        uniforms.sun_position = Vec3::new(4.0, 3.0, 5.0);
+        let size = (256, 128);
+        let baked = bake_surface(moon_surface, &uniforms, size);
+
+        for (x, y) in [(10, 40), (70, 64), (200, 90)] {
+            let fragment = texel_fragment(size, x, y);
+            let procedural = procedural_color(&fragment, &uniforms, &PlanetType::Moon, None);
+            let from_texture = procedural_color(&fragment, &uniforms, &PlanetType::Moon, Some(&baked));
+            assert_close(procedural, from_texture, 2);
+        }
+    }
+
+    #[test]
+    fn baked_mars_follows_the_ice_caps() {
+        let mut uniforms = test_uniforms();
+        let baked = bake_surface(mars_surface, &uniforms, (64, 32));
+        let pole = surface_fragment(Vec3::new(0.0, 1.0, 0.0));
+
+        // Con los casquetes por defecto el polo es hielo blanco azulado
+        let icy = procedural_color(&pole, &uniforms, &PlanetType::Mars, Some(&baked));
+        assert!(icy.b > icy.r);
+
+        // Si los casquetes retroceden más allá del polo, la textura horneada lo refleja
+        uniforms.ice_caps.mars_latitude = 100.0_f32.to_radians();
+        let bare = procedural_color(&pole, &uniforms, &PlanetType::Mars, Some(&baked));
+        assert!(bare.r > bare.b);
+    }
+
+    #[test]
+    fn quantizes_into_toon_bands() {
+        // Con 3 niveles las bandas son [0, 1/3), [1/3, 2/3) y [2/3, 1]
+        assert_eq!(quantize_intensity(0.7, 3), 1.0);
+        assert_eq!(quantize_intensity(0.5, 3), 0.5);
+        assert_eq!(quantize_intensity(0.2, 3), 0.0);
+        // Con menos de dos niveles no hay bandas
+        assert_eq!(quantize_intensity(0.7, 1), 0.7);
+    }
+
+    #[test]
+    fn albedo_scales_reflected_light() {
+        let mut uniforms = test_uniforms();
+        let fragment = surface_fragment(Vec3::new(0.0, 0.0, 1.0));
+
+        let bright = procedural_color(&fragment, &uniforms, &PlanetType::Mars, None);
+        uniforms.albedo = 0.5;
+        let dim = procedural_color(&fragment, &uniforms, &PlanetType::Mars, None);
+
+        assert!(dim.r < bright.r);
+        assert!((dim.r as f32 - bright.r as f32 * 0.5).abs() <= 1.0);
+        assert!((shade_intensity(0.8, &uniforms) - 0.8 * 0.5 * uniforms.sun_pulse).abs() < 1e-6);
+    }
+
+    #[test]
+    fn silhouette_mode_paints_a_flat_palette_color() {
+        let mut uniforms = test_uniforms();
+        uniforms.silhouette_mode = true;
+        uniforms.sun_position = Vec3::new(10.0, 0.0, 0.0);
+        let lit = surface_fragment(Vec3::new(1.0, 0.0, 0.0));
+        let mut dark = surface_fragment(Vec3::new(-1.0, 0.2, 0.0));
+        dark.intensity = 0.0;
+
+        // Sin luz ni textura: todo el disco es el color de paleta del cuerpo
+        for planet_type in [PlanetType::Earth, PlanetType::Mars, PlanetType::Jupiter] {
+            let expected = planet_type.palette_color().to_hex();
+            for fragment in [&lit, &dark] {
+                assert_eq!(fragment_shader(fragment, &uniforms, &planet_type, Surface::default()).to_hex(), expected);
+            }
+        }
+
+        // La nave no se convierte en silueta
+        uniforms.mesh_group = 1;
+        let ship = fragment_shader(&lit, &uniforms, &PlanetType::Spaceship, Surface::default());
+        assert_ne!(ship.to_hex(), PlanetType::Spaceship.palette_color().to_hex());
+    }
+
+    #[test]
+    fn baked_surfaces_match_the_shaders() {
+        let mut uniforms = test_uniforms();
+        uniforms.sun_position = Vec3::new(-3.0, 2.0, 6.0);
+        let size = (128, 64);
+
+        for planet_type in [PlanetType::Mercury, PlanetType::Mars, PlanetType::Moon] {
+            let surface = static_surface(&planet_type).unwrap();
+            let baked = bake_surface(surface, &uniforms, size);
+
+            for (x, y) in [(3, 20), (64, 32), (100, 50), (127, 5)] {
+                // Cada texel guarda la superficie en su centro...
+                let uv = Vec2::new((x as f32 + 0.5) / size.0 as f32, (y as f32 + 0.5) / size.1 as f32);
+                assert_close(baked.sample_nearest(uv), surface(&uv_to_direction(uv), &uniforms), 0);
+
+                // ...y sombreado da lo mismo que el shader procedural en ese punto
+                let fragment = texel_fragment(size, x, y);
+                let procedural = procedural_color(&fragment, &uniforms, &planet_type, None);
+                let from_texture = procedural_color(&fragment, &uniforms, &planet_type, Some(&baked));
+                assert_close(procedural, from_texture, 2);
+            }
+        }
+        assert!(static_surface(&PlanetType::Earth).is_none());
+    }
+
+
+    #[test]
+    fn planet_light_follows_the_sun_pulse() {
+        let mut uniforms = test_uniforms();
+        let mut fragment = surface_fragment(Vec3::new(0.0, 0.0, 1.0));
+        fragment.intensity = 0.6;
+
+        // Cresta y valle del pulso (seno en π/2 y 3π/2)
+        let (p1, p2) = (crate::sun_pulse(589), crate::sun_pulse(196));
+        assert!(p2 > p1);
+
+        uniforms.sun_pulse = p1;
+        let dim_light = diffuse_intensity(&fragment, &uniforms);
+        let dim = procedural_color(&fragment, &uniforms, &PlanetType::Mars, None);
+        uniforms.sun_pulse = p2;
+        let bright_light = diffuse_intensity(&fragment, &uniforms);
+        let bright = procedural_color(&fragment, &uniforms, &PlanetType::Mars, None);
+
+        assert!((bright_light / dim_light - p2 / p1).abs() < 1e-5);
+        assert!(bright.r > dim.r);
+    }
+}
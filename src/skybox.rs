@@ -1,75 +1,590 @@
-use nalgebra_glm::{Vec3, Vec4};
+use nalgebra_glm::{Vec2, Vec3, Vec4};
 use rand::prelude::*;
+use rand::rngs::StdRng;
 use std::f32::consts::PI;
 use crate::{Framebuffer, Uniforms};
+use crate::color::Color;
+use crate::texture::Texture;
 
 pub struct Star {
     position: Vec3,
     brightness: f32,
+    color: Vec3,
+    // Offset of the twinkle cycle, so the stars don't all pulse together
+    phase: f32,
+}
+
+pub struct Nebula {
+    position: Vec3,
+    radius: f32,
+    color: Vec3,
+}
+
+// Seed of the default sky, so it is the same on every run unless another is given
+pub const DEFAULT_SKY_SEED: u64 = 0x5747_A125;
+
+pub struct SkyConfig {
+    pub star_count: usize,
+    pub nebula_count: usize,
+    pub temperature: StarTemperature,
+}
+
+impl Default for SkyConfig {
+    fn default() -> Self {
+        SkyConfig {
+            star_count: 1000,
+            nebula_count: 4,
+            temperature: StarTemperature::default(),
+        }
+    }
+}
+
+// Log-normal distribution of star surface temperatures (Kelvin). Raising `mean`
+// gives a bluer sky, lowering it a redder one; `spread` is the standard
+// deviation of ln(T)
+pub struct StarTemperature {
+    pub mean: f32,
+    pub spread: f32,
+}
+
+impl Default for StarTemperature {
+    fn default() -> Self {
+        // Mostly white/yellow, with some blue and some red tails
+        StarTemperature {
+            mean: 6000.0,
+            spread: 0.35,
+        }
+    }
+}
+
+const MIN_STAR_TEMPERATURE: f32 = 2000.0;
+const MAX_STAR_TEMPERATURE: f32 = 40000.0;
+
+impl StarTemperature {
+    fn sample(&self, rng: &mut StdRng) -> f32 {
+        // Box-Muller for a standard normal sample
+        let u1 = rng.gen::<f32>().max(f32::MIN_POSITIVE);
+        let u2 = rng.gen::<f32>();
+        let normal = (-2.0 * u1.ln()).sqrt() * (2.0 * PI * u2).cos();
+
+        (self.mean.ln() + normal * self.spread)
+            .exp()
+            .clamp(MIN_STAR_TEMPERATURE, MAX_STAR_TEMPERATURE)
+    }
+}
+
+// Approximate sRGB color of a blackbody at the given temperature (fit by Tanner
+// Helland), scaled so the brightest channel is 1
+fn blackbody_color(kelvin: f32) -> Vec3 {
+    let t = kelvin / 100.0;
+
+    let red = if t <= 66.0 { 255.0 } else { 329.698_73 * (t - 60.0).powf(-0.133_204_76) };
+    let green = if t <= 66.0 {
+        99.470_8 * t.ln() - 161.119_57
+    } else {
+        288.122_17 * (t - 60.0).powf(-0.075_514_85)
+    };
+    let blue = if t >= 66.0 {
+        255.0
+    } else if t <= 19.0 {
+        0.0
+    } else {
+        138.517_73 * (t - 10.0).ln() - 305.044_8
+    };
+
+    let color = Vec3::new(red, green, blue).map(|channel| channel.clamp(0.0, 255.0));
+    color / color.max()
+}
+
+// Screen-space gravitational lens (in framebuffer samples). Stars inside
+// `radius` are hidden behind the hole; the rest are pushed outwards towards an
+// Einstein ring of radius `strength`
+pub struct GravitationalLens {
+    pub center: (f32, f32),
+    pub radius: f32,
+    pub strength: f32,
+}
+
+// Beyond this many Einstein radii the lens no longer moves the stars
+const LENS_REACH: f32 = 6.0;
+
+impl GravitationalLens {
+    // Projects a black hole at `position` with the given world radius; None when
+    // it is behind the camera
+    pub fn around(framebuffer: &Framebuffer, uniforms: &Uniforms, position: Vec3, radius: f32, strength: f32) -> Option<Self> {
+        let projected = uniforms.projection_matrix * uniforms.view_matrix * Vec4::new(position.x, position.y, position.z, 1.0);
+        if projected.w <= 0.0 {
+            return None;
+        }
+
+        let ndc = projected / projected.w;
+        let screen_pos = uniforms.viewport_matrix * Vec4::new(ndc.x, ndc.y, ndc.z, 1.0);
+        let pixel_radius = radius * uniforms.projection_matrix[(1, 1)] / projected.w * framebuffer.height as f32 / 2.0;
+
+        Some(GravitationalLens {
+            center: (screen_pos.x, screen_pos.y),
+            radius: pixel_radius,
+            strength: pixel_radius * strength,
+        })
+    }
+
+    // Where a star seen at (x, y) appears once lensed, or None if the hole hides it.
+    // Uses the point-lens image position θ = (β + sqrt(β² + 4θE²)) / 2, which
+    // falls off as θE² / β, faded out to nothing at LENS_REACH Einstein radii
+    fn deflect(&self, x: f32, y: f32) -> Option<(f32, f32)> {
+        let (dx, dy) = (x - self.center.0, y - self.center.1);
+        let distance = (dx * dx + dy * dy).sqrt();
+        let reach = self.strength * LENS_REACH;
+
+        if distance < self.radius {
+            return None;
+        }
+        if distance >= reach || self.strength <= 0.0 {
+            return Some((x, y));
+        }
+
+        let image = (distance + (distance * distance + 4.0 * self.strength * self.strength).sqrt()) / 2.0;
+        let falloff = 1.0 - distance / reach;
+        let lensed = distance + (image - distance) * falloff;
+        let scale = lensed / distance;
+
+        Some((self.center.0 + dx * scale, self.center.1 + dy * scale))
+    }
+
+    // Inverse of `deflect`: which unlensed point of the sky ends up at (x, y).
+    // From the lens equation β = θ - θE² / θ, with the same fade-out
+    fn source(&self, x: f32, y: f32) -> Option<(f32, f32)> {
+        let (dx, dy) = (x - self.center.0, y - self.center.1);
+        let distance = (dx * dx + dy * dy).sqrt();
+        let reach = self.strength * LENS_REACH;
+
+        if distance < self.radius {
+            return None;
+        }
+        if distance >= reach || self.strength <= 0.0 {
+            return Some((x, y));
+        }
+
+        let source = distance - self.strength * self.strength / distance;
+        let falloff = 1.0 - distance / reach;
+        let unlensed = distance + (source - distance) * falloff;
+        let scale = unlensed / distance;
+
+        Some((self.center.0 + dx * scale, self.center.1 + dy * scale))
+    }
+}
+
+// Cubemap faces, in the usual +X, -X, +Y, -Y, +Z, -Z order
+pub const CUBEMAP_FACE_NAMES: [&str; 6] = ["px", "nx", "py", "ny", "pz", "nz"];
+
+// What fills the background: the procedural star field or six cubemap images
+enum SkyMode {
+    StarField {
+        stars: Vec<Star>,
+        nebulae: Vec<Nebula>,
+    },
+    Cubemap(Box<[Texture; 6]>),
 }
 
 pub struct Skybox {
-    stars: Vec<Star>,
+    mode: SkyMode,
+}
+
+impl Default for Skybox {
+    fn default() -> Self {
+        Self::new(SkyConfig::default().star_count, DEFAULT_SKY_SEED)
+    }
 }
 
 impl Skybox {
-    pub fn new(star_count: usize) -> Self {
-        let mut rng = rand::thread_rng();
-        let mut stars = Vec::with_capacity(star_count);
+    // Star field with `star_count` stars and the default nebulae and temperatures
+    pub fn new(star_count: usize, seed: u64) -> Self {
+        Self::from_sky_seed(seed, SkyConfig { star_count, ..SkyConfig::default() })
+    }
 
-        for _ in 0..star_count {
-            // Generate random spherical coordinates
-            let theta = rng.gen::<f32>() * 2.0 * PI;  // Azimuth angle
-            let phi = rng.gen::<f32>() * PI;          // Polar angle
-            let radius = 100.0;  // Fixed radius for all stars
+    // Everything in the sky is drawn from a single RNG, so the same seed and
+    // config always produce the same sky
+    pub fn from_sky_seed(seed: u64, config: SkyConfig) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut stars = Vec::with_capacity(config.star_count);
 
-            // Convert spherical to Cartesian coordinates
-            let x = radius * phi.sin() * theta.cos();
-            let y = radius * phi.sin() * theta.sin();
-            let z = radius * phi.cos();
+        for _ in 0..config.star_count {
+            let position = random_sky_position(&mut rng);
 
             // Random brightness between 0.5 and 1.0
             let brightness = rng.gen::<f32>() * 0.5 + 0.5;
 
+            // Tinted by the star's surface temperature
+            let color = blackbody_color(config.temperature.sample(&mut rng));
+
             stars.push(Star {
-                position: Vec3::new(x, y, z),
+                position,
                 brightness,
+                color,
+                phase: rng.gen::<f32>() * 2.0 * PI,
             });
         }
 
-        Skybox { stars }
-    }
-
-    pub fn render(&self, framebuffer: &mut Framebuffer, uniforms: &Uniforms, camera_position: Vec3) {
-        for star in &self.stars {
-            // Calculate star position relative to camera
-            let position = star.position + camera_position;
-            
-            // Project the star position to screen space
-            let pos_vec4 = Vec4::new(position.x, position.y, position.z, 1.0);
-            let projected = uniforms.projection_matrix * uniforms.view_matrix * pos_vec4;
-
-            // Perform perspective division
-            if projected.w <= 0.0 { continue; }
-            let ndc = projected / projected.w;
-
-            // Apply viewport transform
-            let screen_pos = uniforms.viewport_matrix * Vec4::new(ndc.x, ndc.y, ndc.z, 1.0);
-            
-            // Check if star is in front of camera and within screen bounds
-            if screen_pos.z < 0.0 { continue; }
-            
-            let x = screen_pos.x as usize;
-            let y = screen_pos.y as usize;
-            
-            if x < framebuffer.width && y < framebuffer.height {
-                // Calculate star color based on brightness
-                let intensity = (star.brightness * 255.0) as u8;
-                let color = (intensity as u32) << 16 | (intensity as u32) << 8 | intensity as u32;
-                
-                framebuffer.set_current_color(color);
-                framebuffer.point(x, y, 100.0);
+        let nebulae = (0..config.nebula_count)
+            .map(|_| Nebula {
+                position: random_sky_position(&mut rng),
+                radius: rng.gen_range(8.0..20.0),
+                color: Vec3::new(
+                    rng.gen_range(0.1..0.35),
+                    rng.gen_range(0.0..0.15),
+                    rng.gen_range(0.15..0.4),
+                ),
+            })
+            .collect();
+
+        Skybox { mode: SkyMode::StarField { stars, nebulae } }
+    }
+
+    // Loads the six faces of a cubemap (PNG, in CUBEMAP_FACE_NAMES order)
+    pub fn from_cubemap(paths: [&str; 6]) -> image::ImageResult<Self> {
+        let faces = [
+            Texture::load(paths[0])?,
+            Texture::load(paths[1])?,
+            Texture::load(paths[2])?,
+            Texture::load(paths[3])?,
+            Texture::load(paths[4])?,
+            Texture::load(paths[5])?,
+        ];
+        Ok(Skybox { mode: SkyMode::Cubemap(Box::new(faces)) })
+    }
+
+    // `warp_speed` in [0, 1] stretches the stars into radial streaks while warping
+    pub fn render(&self, framebuffer: &mut Framebuffer, uniforms: &Uniforms, camera_position: Vec3, lens: Option<&GravitationalLens>, warp_speed: f32) {
+        match &self.mode {
+            SkyMode::StarField { stars, nebulae } => {
+                render_star_field(framebuffer, uniforms, stars, nebulae, camera_position, lens, warp_speed);
+            },
+            SkyMode::Cubemap(faces) => {
+                render_cubemap(framebuffer, uniforms, faces, camera_position, lens);
+            },
+        }
+    }
+}
+
+// Depth of the sky background: behind everything already in the z-buffer, but
+// still in front of a cleared (infinitely far) sample
+const BACKGROUND_DEPTH: f32 = f32::MAX;
+
+fn render_star_field(
+    framebuffer: &mut Framebuffer,
+    uniforms: &Uniforms,
+    stars: &[Star],
+    nebulae: &[Nebula],
+    camera_position: Vec3,
+    lens: Option<&GravitationalLens>,
+    warp_speed: f32,
+) {
+    // Nebulae first so the stars are drawn on top of them
+    for nebula in nebulae {
+        render_nebula(framebuffer, uniforms, nebula, camera_position);
+    }
+
+    // The sky is drawn after the opaque bodies and only fills background samples, so
+    // the stars stay hidden behind every body however far away it is drawn
+    for star in stars {
+        let Some((x, y, _)) = project_to_screen(framebuffer, uniforms, star.position + camera_position) else {
+            continue;
+        };
+
+        // Bend the star around the black hole, if there is one on screen
+        let (x, y) = match lens {
+            Some(lens) => {
+                let Some((lx, ly)) = lens.deflect(x as f32, y as f32) else {
+                    continue;
+                };
+                if lx < 0.0 || ly < 0.0 || lx as usize >= framebuffer.width || ly as usize >= framebuffer.height {
+                    continue;
+                }
+                (lx as usize, ly as usize)
+            },
+            None => (x, y),
+        };
+
+        // Calculate star color based on brightness, with a slow twinkle
+        let intensity = star.brightness * twinkle(star.phase, uniforms.time) * 255.0;
+        let color = Color::new(
+            (intensity * star.color.x) as u8,
+            (intensity * star.color.y) as u8,
+            (intensity * star.color.z) as u8,
+        );
+
+        if warp_speed > 0.0 {
+            render_streak(framebuffer, (x, y), color, warp_speed);
+        }
+
+        for &(dx, dy) in star_footprint(star.brightness) {
+            let px = x as isize + dx * framebuffer.scale as isize;
+            let py = y as isize + dy * framebuffer.scale as isize;
+            if px < 0 || py < 0 || px as usize >= framebuffer.width || py as usize >= framebuffer.height {
+                continue;
+            }
+            for (sx, sy) in framebuffer.display_pixel_samples(px as usize, py as usize) {
+                if framebuffer.should_draw(sx, sy, BACKGROUND_DEPTH) {
+                    framebuffer.point_no_depth(sx, sy, color.to_hex());
+                }
+            }
+        }
+    }
+}
+
+// Fraction of the distance to the screen center covered by a streak at full warp speed
+const STREAK_LENGTH: f32 = 0.35;
+
+// Line from the star back towards the screen center (where the camera is heading),
+// fading out towards its tail. Its length follows the warp speed, so the streaks
+// grow and shrink with the warp easing
+fn render_streak(framebuffer: &mut Framebuffer, head: (usize, usize), color: Color, warp_speed: f32) {
+    let center = (framebuffer.width as f32 / 2.0, framebuffer.height as f32 / 2.0);
+    let head = (head.0 as f32, head.1 as f32);
+    let shrink = 1.0 - STREAK_LENGTH * warp_speed.clamp(0.0, 1.0);
+    let tail = (center.0 + (head.0 - center.0) * shrink, center.1 + (head.1 - center.1) * shrink);
+
+    let steps = (head.0 - tail.0).abs().max((head.1 - tail.1).abs()) as usize;
+    for step in 0..steps {
+        let t = step as f32 / steps as f32;
+        let x = tail.0 + (head.0 - tail.0) * t;
+        let y = tail.1 + (head.1 - tail.1) * t;
+        if x < 0.0 || y < 0.0 || x as usize >= framebuffer.width || y as usize >= framebuffer.height {
+            continue;
+        }
+
+        let faded = (color * t).to_hex();
+        for (sx, sy) in framebuffer.display_pixel_samples(x as usize, y as usize) {
+            if framebuffer.should_draw(sx, sy, BACKGROUND_DEPTH) {
+                framebuffer.point_no_depth(sx, sy, faded);
             }
         }
     }
 }
+
+fn render_nebula(framebuffer: &mut Framebuffer, uniforms: &Uniforms, nebula: &Nebula, camera_position: Vec3) {
+    let position = nebula.position + camera_position;
+    let projected = uniforms.projection_matrix * uniforms.view_matrix * Vec4::new(position.x, position.y, position.z, 1.0);
+    let Some((center_x, center_y, _)) = project_to_screen(framebuffer, uniforms, position) else {
+        return;
+    };
+
+    // Screen-space radius of the nebula at its distance from the camera
+    let pixel_radius = nebula.radius * uniforms.projection_matrix[(1, 1)] / projected.w * framebuffer.height as f32 / 2.0;
+    let reach = pixel_radius.ceil() as i32;
+
+    for dy in -reach..=reach {
+        for dx in -reach..=reach {
+            let x = center_x as i32 + dx;
+            let y = center_y as i32 + dy;
+            if x < 0 || y < 0 || x as usize >= framebuffer.width || y as usize >= framebuffer.height {
+                continue;
+            }
+
+            let distance = ((dx * dx + dy * dy) as f32).sqrt() / pixel_radius;
+            if distance >= 1.0 {
+                continue;
+            }
+
+            // Soft falloff towards the edge, added over whatever is already there
+            let falloff = (1.0 - distance) * (1.0 - distance) * 255.0;
+            let glow = Color::new(
+                (nebula.color.x * falloff) as u8,
+                (nebula.color.y * falloff) as u8,
+                (nebula.color.z * falloff) as u8,
+            );
+            let (x, y) = (x as usize, y as usize);
+            if !framebuffer.should_draw(x, y, BACKGROUND_DEPTH) {
+                continue;
+            }
+            let color = Color::from_hex(framebuffer.buffer[y * framebuffer.width + x]) + glow;
+            framebuffer.point_no_depth(x, y, color.to_hex());
+        }
+    }
+}
+
+// Fills every display pixel not covered by a body with the cubemap texel seen along
+// the ray from the camera through that pixel
+fn render_cubemap(
+    framebuffer: &mut Framebuffer,
+    uniforms: &Uniforms,
+    faces: &[Texture; 6],
+    camera_position: Vec3,
+    lens: Option<&GravitationalLens>,
+) {
+    let Some(unproject) = (uniforms.viewport_matrix * uniforms.projection_matrix * uniforms.view_matrix).try_inverse() else {
+        return;
+    };
+    let scale = framebuffer.scale;
+
+    for y in (0..framebuffer.height).step_by(scale) {
+        for x in (0..framebuffer.width).step_by(scale) {
+            // Center of the display pixel, in framebuffer samples
+            let (sx, sy) = (x as f32 + scale as f32 * 0.5, y as f32 + scale as f32 * 0.5);
+            let (sx, sy) = match lens {
+                Some(lens) => match lens.source(sx, sy) {
+                    Some(source) => source,
+                    None => {
+                        for (px, py) in framebuffer.display_pixel_samples(x, y) {
+                            if framebuffer.should_draw(px, py, BACKGROUND_DEPTH) {
+                                framebuffer.point_no_depth(px, py, 0x000000);
+                            }
+                        }
+                        continue;
+                    },
+                },
+                None => (sx, sy),
+            };
+
+            let world = unproject * Vec4::new(sx, sy, 1.0, 1.0);
+            if world.w.abs() < f32::EPSILON {
+                continue;
+            }
+            let direction = world.xyz() / world.w - camera_position;
+            let color = sample_cubemap(faces, &direction).to_hex();
+
+            for (px, py) in framebuffer.display_pixel_samples(x, y) {
+                if framebuffer.should_draw(px, py, BACKGROUND_DEPTH) {
+                    framebuffer.point_no_depth(px, py, color);
+                }
+            }
+        }
+    }
+}
+
+// Picks the face along the direction's major axis and the texel on it, using
+// the OpenGL cubemap face orientation
+fn sample_cubemap(faces: &[Texture; 6], direction: &Vec3) -> Color {
+    let (x, y, z) = (direction.x, direction.y, direction.z);
+    let (ax, ay, az) = (x.abs(), y.abs(), z.abs());
+
+    let (face, sc, tc, major) = if ax >= ay && ax >= az {
+        if x > 0.0 { (0, -z, -y, ax) } else { (1, z, -y, ax) }
+    } else if ay >= az {
+        if y > 0.0 { (2, x, z, ay) } else { (3, x, -z, ay) }
+    } else if z > 0.0 {
+        (4, x, -y, az)
+    } else {
+        (5, -x, -y, az)
+    };
+
+    if major <= 0.0 {
+        return Color::black();
+    }
+    // Nearest texel: bilinear wrapping would bleed each face's opposite edge into its border
+    let uv = Vec2::new((sc / major + 1.0) * 0.5, (tc / major + 1.0) * 0.5);
+    faces[face].sample_nearest(uv)
+}
+
+// Display pixels covered by a star: the brightest get a small plus, the next
+// ones a 2x2 square, and the rest (most of them) a single pixel
+fn star_footprint(brightness: f32) -> &'static [(isize, isize)] {
+    const SINGLE: [(isize, isize); 1] = [(0, 0)];
+    const SQUARE: [(isize, isize); 4] = [(0, 0), (1, 0), (0, 1), (1, 1)];
+    const PLUS: [(isize, isize); 5] = [(0, 0), (-1, 0), (1, 0), (0, -1), (0, 1)];
+
+    match brightness {
+        b if b >= BRIGHT_STAR => &PLUS,
+        b if b >= MEDIUM_STAR => &SQUARE,
+        _ => &SINGLE,
+    }
+}
+
+// Brightness thresholds for the larger star footprints
+const BRIGHT_STAR: f32 = 0.95;
+const MEDIUM_STAR: f32 = 0.85;
+
+// Brightness multiplier in [1 - TWINKLE_DEPTH, 1]; two unrelated frequencies
+// keep the flicker from looking periodic
+const TWINKLE_DEPTH: f32 = 0.3;
+
+fn twinkle(phase: f32, time: u32) -> f32 {
+    let t = time as f32;
+    let wave = (t * 0.07 + phase).sin() * 0.6 + (t * 0.19 + phase * 2.3).sin() * 0.4;
+    1.0 - TWINKLE_DEPTH * (wave * 0.5 + 0.5)
+}
+
+fn random_sky_position(rng: &mut StdRng) -> Vec3 {
+    // Generate random spherical coordinates
+    let theta = rng.gen::<f32>() * 2.0 * PI;  // Azimuth angle
+    let phi = rng.gen::<f32>() * PI;          // Polar angle
+    let radius = 100.0;  // Fixed radius for all stars
+
+    // Convert spherical to Cartesian coordinates
+    let x = radius * phi.sin() * theta.cos();
+    let y = radius * phi.sin() * theta.sin();
+    let z = radius * phi.cos();
+
+    Vec3::new(x, y, z)
+}
+
+// Projects a world position to a pixel, returning None when it is behind the
+// camera or outside the framebuffer
+fn project_to_screen(framebuffer: &Framebuffer, uniforms: &Uniforms, position: Vec3) -> Option<(usize, usize, f32)> {
+    // Project the position to clip space
+    let pos_vec4 = Vec4::new(position.x, position.y, position.z, 1.0);
+    let projected = uniforms.projection_matrix * uniforms.view_matrix * pos_vec4;
+
+    // Perform perspective division
+    if projected.w <= 0.0 { return None; }
+    let ndc = projected / projected.w;
+
+    // Apply viewport transform
+    let screen_pos = uniforms.viewport_matrix * Vec4::new(ndc.x, ndc.y, ndc.z, 1.0);
+
+    // Check if it is in front of camera and within screen bounds
+    if screen_pos.z < 0.0 { return None; }
+
+    let x = screen_pos.x as usize;
+    let y = screen_pos.y as usize;
+
+    if x < framebuffer.width && y < framebuffer.height {
+        Some((x, y, screen_pos.z))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::framebuffer::DepthFormat;
+
+    #[test]
+    fn streaks_do_not_paint_over_distant_bodies() {
+        let mut framebuffer = Framebuffer::new(64, 64, DepthFormat::Float32);
+        // A body sample close to the far plane, well beyond the star sphere
+        framebuffer.set_current_color(0x336699);
+        framebuffer.point(60, 32, 0.999);
+
+        render_streak(&mut framebuffer, (63, 32), Color::new(255, 255, 255), 1.0);
+
+        assert_eq!(framebuffer.buffer[32 * 64 + 60], 0x336699);
+        // Empty background along the streak is still drawn
+        assert_ne!(framebuffer.buffer[32 * 64 + 58], 0);
+    }
+
+    // Everything that defines a star field, flattened so two skies can be compared
+    fn sky_values(skybox: &Skybox) -> Vec<f32> {
+        let SkyMode::StarField { stars, nebulae } = &skybox.mode else {
+            panic!("expected a star field");
+        };
+        let stars = stars.iter().flat_map(|star| {
+            [star.position.x, star.position.y, star.position.z, star.brightness, star.color.x, star.color.y, star.color.z, star.phase]
+        });
+        let nebulae = nebulae.iter().flat_map(|nebula| {
+            [nebula.position.x, nebula.position.y, nebula.position.z, nebula.radius, nebula.color.x, nebula.color.y, nebula.color.z]
+        });
+        stars.chain(nebulae).collect()
+    }
+
+    #[test]
+    fn same_seed_gives_an_identical_sky() {
+        let first = Skybox::from_sky_seed(42, SkyConfig::default());
+        let second = Skybox::from_sky_seed(42, SkyConfig::default());
+        assert_eq!(sky_values(&first), sky_values(&second));
+
+        let other = Skybox::from_sky_seed(43, SkyConfig::default());
+        assert_ne!(sky_values(&first), sky_values(&other));
+    }
+}
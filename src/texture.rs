@@ -0,0 +1,187 @@
+use nalgebra_glm::{Vec2, Vec3};
+use std::f32::consts::PI;
+use crate::color::Color;
+
+// Textura RGB en memoria, muestreada por coordenadas UV en [0, 1]
+pub struct Texture {
+    pub width: usize,
+    pub height: usize,
+    texels: Vec<Color>,
+    // Versiones reducidas a la mitad, desde la siguiente a la original hasta 1x1
+    // (vacío salvo que se pidan con `with_mipmaps`)
+    mipmaps: Vec<Texture>,
+}
+
+impl Texture {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            texels: vec![Color::black(); width * height],
+            mipmaps: Vec::new(),
+        }
+    }
+
+    // Carga una imagen PNG; la transparencia se descarta y los canales de 16 bits se reducen a 8
+    pub fn load(path: &str) -> image::ImageResult<Self> {
+        let image = image::open(path)?.into_rgb8();
+        let (width, height) = image.dimensions();
+        let texels = image.pixels().map(|p| Color::new(p[0], p[1], p[2])).collect();
+        Ok(Self {
+            width: width as usize,
+            height: height as usize,
+            texels,
+            mipmaps: Vec::new(),
+        })
+    }
+
+    // Calcula la cadena de mipmaps. Cada nivel promedia (en luz lineal) bloques de
+    // 2x2 texels del anterior, así el último es un solo texel con el color promedio
+    pub fn with_mipmaps(mut self) -> Self {
+        let mut mipmaps: Vec<Texture> = Vec::new();
+        while let Some(next) = mipmaps.last().unwrap_or(&self).half_size() {
+            mipmaps.push(next);
+        }
+        self.mipmaps = mipmaps;
+        self
+    }
+
+    // Nivel siguiente de la cadena (None si ya es de 1x1). Con un lado impar el
+    // último texel de esa fila o columna se promedia consigo mismo
+    fn half_size(&self) -> Option<Texture> {
+        if self.width == 1 && self.height == 1 {
+            return None;
+        }
+
+        let mut half = Texture::new(self.width.div_ceil(2), self.height.div_ceil(2));
+        for y in 0..half.height {
+            for x in 0..half.width {
+                let (x0, y0) = (x * 2, y * 2);
+                let (x1, y1) = ((x0 + 1).min(self.width - 1), (y0 + 1).min(self.height - 1));
+                let sum = self.get(x0, y0).to_linear() + self.get(x1, y0).to_linear()
+                    + self.get(x0, y1).to_linear() + self.get(x1, y1).to_linear();
+                half.set(x, y, Color::from_linear(sum / 4.0));
+            }
+        }
+        Some(half)
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, color: Color) {
+        if x < self.width && y < self.height {
+            self.texels[y * self.width + x] = color;
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Color {
+        self.texels[y * self.width + x]
+    }
+
+    // Muestreo bilineal: mezcla (en luz lineal) los cuatro texels alrededor del punto.
+    // U se repite, así en la costura de una textura equirectangular se mezcla la
+    // última columna con la primera, y V se limita a los bordes
+    pub fn sample(&self, uv: Vec2) -> Color {
+        let x = uv.x.rem_euclid(1.0) * self.width as f32 - 0.5;
+        let y = uv.y.clamp(0.0, 1.0) * self.height as f32 - 0.5;
+        let (fx, fy) = (x - x.floor(), y - y.floor());
+
+        let x0 = (x.floor() as isize).rem_euclid(self.width as isize) as usize;
+        let x1 = (x0 + 1) % self.width;
+        let y0 = (y.floor().max(0.0) as usize).min(self.height - 1);
+        let y1 = (y0 + 1).min(self.height - 1);
+
+        let top = self.get(x0, y0).to_linear().lerp(&self.get(x1, y0).to_linear(), fx);
+        let bottom = self.get(x0, y1).to_linear().lerp(&self.get(x1, y1).to_linear(), fx);
+        Color::from_linear(top.lerp(&bottom, fy))
+    }
+
+    // Muestreo trilineal: `lod` es el nivel de mipmap (0 = la imagen original, cada
+    // unidad la mitad de resolución) y entre dos niveles se mezclan ambos
+    pub fn sample_lod(&self, uv: Vec2, lod: f32) -> Color {
+        if self.mipmaps.is_empty() || lod <= 0.0 {
+            return self.sample(uv);
+        }
+
+        let lod = lod.min(self.mipmaps.len() as f32);
+        let finer = lod.floor() as usize;
+        let level = |index: usize| if index == 0 { self } else { &self.mipmaps[index - 1] };
+        let t = lod - finer as f32;
+        if t == 0.0 {
+            return level(finer).sample(uv);
+        }
+        level(finer).sample(uv).lerp_linear(&level(finer + 1).sample(uv), t)
+    }
+
+    // Muestreo del texel más cercano, con las mismas reglas de borde que `sample`
+    pub fn sample_nearest(&self, uv: Vec2) -> Color {
+        let u = uv.x.rem_euclid(1.0);
+        let v = uv.y.clamp(0.0, 1.0);
+        let x = ((u * self.width as f32) as usize).min(self.width - 1);
+        let y = ((v * self.height as f32) as usize).min(self.height - 1);
+        self.get(x, y)
+    }
+
+    // Muestreo equirectangular a partir de una dirección desde el centro de la esfera,
+    // en el nivel de mipmap `lod`
+    pub fn sample_direction(&self, direction: &Vec3, lod: f32) -> Color {
+        self.sample_lod(direction_to_uv(direction), lod)
+    }
+}
+
+// Proyección equirectangular: U recorre la longitud y V va del polo norte (0) al sur (1)
+pub fn direction_to_uv(direction: &Vec3) -> Vec2 {
+    let direction = direction.normalize();
+    Vec2::new(
+        0.5 + direction.z.atan2(direction.x) / (2.0 * PI),
+        direction.y.clamp(-1.0, 1.0).acos() / PI,
+    )
+}
+
+// Punto de la esfera unitaria correspondiente a unas coordenadas UV
+pub fn uv_to_direction(uv: Vec2) -> Vec3 {
+    let longitude = (uv.x - 0.5) * 2.0 * PI;
+    let polar = uv.y * PI;
+    Vec3::new(
+        polar.sin() * longitude.cos(),
+        polar.cos(),
+        polar.sin() * longitude.sin(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Promedio de todos los texels en luz lineal, el color que debería quedar
+    // en el último nivel de la cadena
+    fn linear_average(texture: &Texture) -> Color {
+        let sum = texture.texels.iter().fold(Vec3::zeros(), |sum, texel| sum + texel.to_linear());
+        Color::from_linear(sum / texture.texels.len() as f32)
+    }
+
+    #[test]
+    fn smallest_mip_is_the_average_of_the_base() {
+        let mut texture = Texture::new(8, 4);
+        for y in 0..4 {
+            for x in 0..8 {
+                let color = if (x + y) % 2 == 0 { Color::new(255, 255, 255) } else { Color::new(0, 0, 0) };
+                texture.set(x, y, color);
+            }
+        }
+        texture.set(3, 1, Color::new(200, 40, 90));
+        let expected = linear_average(&texture);
+
+        let texture = texture.with_mipmaps();
+        let smallest = texture.mipmaps.last().unwrap();
+        assert_eq!((smallest.width, smallest.height), (1, 1));
+
+        // En el nivel más alto cualquier UV da ese único texel
+        for uv in [Vec2::new(0.1, 0.2), Vec2::new(0.5, 0.5), Vec2::new(0.9, 0.7)] {
+            let color = texture.sample_lod(uv, texture.mipmaps.len() as f32);
+            for (got, want) in [(color.r, expected.r), (color.g, expected.g), (color.b, expected.b)] {
+                assert!(got.abs_diff(want) <= 2, "{:?} vs {:?}", color, expected);
+            }
+        }
+        // Un promedio ingenuo en sRGB daría ~128 para el tablero, bastante más oscuro
+        assert!(expected.g > 160);
+    }
+}
@@ -1,46 +1,61 @@
 use nalgebra_glm::{Vec3, dot, Vec2};
 use crate::fragment::Fragment;
 use crate::vertex::Vertex;
-use crate::color::Color;
 
-pub fn triangle(v1: &Vertex, v2: &Vertex, v3: &Vertex) -> Vec<Fragment> {
-  let mut fragments = Vec::new();
+// Fragments are appended to `fragments`, so a single buffer can be reused across
+// triangles and frames without allocating. Only pixels inside the triangle's
+// bounding box, clamped to the `viewport` (width, height), are tested
+pub fn triangle(v1: &Vertex, v2: &Vertex, v3: &Vertex, viewport: (usize, usize), fragments: &mut Vec<Fragment>) {
   let (a, b, c) = (v1.transformed_position, v2.transformed_position, v3.transformed_position);
 
-  let (min_x, min_y, max_x, max_y) = calculate_bounding_box(&a, &b, &c);
+  let triangle_area = edge_function(&a, &b, &c);
+  if triangle_area == 0.0 {
+    return;
+  }
+
+  let Some((min_x, min_y, max_x, max_y)) = calculate_bounding_box(&a, &b, &c, viewport) else {
+    return;
+  };
 
   let light_dir = Vec3::new(0.0, 0.0, 1.0);
 
-  let triangle_area = edge_function(&a, &b, &c);
+  // 1/w at each vertex: attributes divided by w interpolate linearly in screen space
+  let (inv_w1, inv_w2, inv_w3) = (1.0 / v1.clip_w, 1.0 / v2.clip_w, 1.0 / v3.clip_w);
 
-  // Iterate over each pixel in the bounding box
-  for y in min_y..=max_y {
-    for x in min_x..=max_x {
-      let point = Vec3::new(x as f32 + 0.5, y as f32 + 0.5, 0.0);
+  // The edge functions are affine in x, so the barycentric weights change by a constant
+  // amount from one pixel to the next: they are evaluated once per row and then stepped
+  let step_x = (
+    (c.y - b.y) / triangle_area,
+    (a.y - c.y) / triangle_area,
+    (b.y - a.y) / triangle_area,
+  );
 
-      // Calculate barycentric coordinates
-      let (w1, w2, w3) = barycentric_coordinates(&point, &a, &b, &c, triangle_area);
+  for y in min_y..=max_y {
+    let row_start = Vec3::new(min_x as f32 + 0.5, y as f32 + 0.5, 0.0);
+    let (mut w1, mut w2, mut w3) = barycentric_coordinates(&row_start, &a, &b, &c, triangle_area);
 
-      // Check if the point is inside the triangle
-      if w1 >= 0.0 && w1 <= 1.0 && 
-         w2 >= 0.0 && w2 <= 1.0 &&
-         w3 >= 0.0 && w3 <= 1.0 {
+    for x in min_x..=max_x {
+      // Inside the triangle when all three weights are non-negative (they sum to 1)
+      if w1 >= 0.0 && w2 >= 0.0 && w3 >= 0.0 {
+        // Perspective-correct weights: interpolate attribute/w and 1/w, then divide back
+        let inv_w = w1 * inv_w1 + w2 * inv_w2 + w3 * inv_w3;
+        let (p1, p2, p3) = (w1 * inv_w1 / inv_w, w2 * inv_w2 / inv_w, w3 * inv_w3 / inv_w);
 
         // Interpolate normal
-        let normal = v1.transformed_normal * w1 + v2.transformed_normal * w2 + v3.transformed_normal * w3;
+        let normal = v1.transformed_normal * p1 + v2.transformed_normal * p2 + v3.transformed_normal * p3;
         let normal = normal.normalize();
 
         // Calculate lighting intensity
         let intensity = dot(&normal, &light_dir).max(0.0);
 
-        // Create a gray color (unchanged)
-        let color = Color::new(100, 100, 100); // Medium gray
+        // Material color is per triangle, so it is taken flat from the first vertex
+        let color = v1.color;
 
-        // Interpolate depth
+        // Interpolate depth (NDC depth is already linear in screen space)
         let depth = a.z * w1 + b.z * w2 + c.z * w3;
 
         // Positions of the original vertex
-        let vertex_position = v1.position * w1 + v2.position * w2 + v3.position * w3;
+        let vertex_position = v1.position * p1 + v2.position * p2 + v3.position * p3;
 
         fragments.push(Fragment::new(
             Vec2::new(x as f32, y as f32),
@@ -49,21 +64,150 @@ pub fn triangle(v1: &Vertex, v2: &Vertex, v3: &Vertex) -> Vec<Fragment> {
             normal,
             intensity,
             vertex_position,
+            1.0 / inv_w,
         ));
       }
+
+      w1 += step_x.0;
+      w2 += step_x.1;
+      w3 += step_x.2;
     }
   }
+}
 
-  fragments
+// Only the three edges of the triangle, for wireframe rendering
+pub fn wireframe(v1: &Vertex, v2: &Vertex, v3: &Vertex, fragments: &mut Vec<Fragment>) {
+  line_aa(v1, v2, fragments);
+  line_aa(v2, v3, fragments);
+  line_aa(v3, v1, fragments);
 }
 
-fn calculate_bounding_box(v1: &Vec3, v2: &Vec3, v3: &Vec3) -> (i32, i32, i32, i32) {
-    let min_x = v1.x.min(v2.x).min(v3.x).floor() as i32;
-    let min_y = v1.y.min(v2.y).min(v3.y).floor() as i32;
-    let max_x = v1.x.max(v2.x).max(v3.x).ceil() as i32;
-    let max_y = v1.y.max(v2.y).max(v3.y).ceil() as i32;
+// Rasterizes the segment between two projected vertices with Bresenham's algorithm,
+// interpolating the vertex attributes along it (perspective-correct, like `triangle`)
+pub fn line(v1: &Vertex, v2: &Vertex, fragments: &mut Vec<Fragment>) {
+  let (a, b) = (v1.transformed_position, v2.transformed_position);
 
-    (min_x, min_y, max_x, max_y)
+  let (mut x, mut y) = (a.x.floor() as i32, a.y.floor() as i32);
+  let (end_x, end_y) = (b.x.floor() as i32, b.y.floor() as i32);
+  let (dx, dy) = ((end_x - x).abs(), -(end_y - y).abs());
+  let (step_x, step_y) = (if x < end_x { 1 } else { -1 }, if y < end_y { 1 } else { -1 });
+  let steps = dx.max(-dy).max(1) as f32;
+  let mut error = dx + dy;
+  let mut step = 0;
+
+  loop {
+    // Negative coordinates would wrap when converted to pixel indices
+    if x >= 0 && y >= 0 {
+      fragments.push(line_fragment(v1, v2, step as f32 / steps, x, y));
+    }
+
+    if x == end_x && y == end_y {
+      break;
+    }
+    let doubled = 2 * error;
+    if doubled >= dy {
+      error += dy;
+      x += step_x;
+    }
+    if doubled <= dx {
+      error += dx;
+      y += step_y;
+    }
+    step += 1;
+  }
+}
+
+// A single fragment at the projected vertex, for point rendering
+pub fn point(v: &Vertex) -> Option<Fragment> {
+  let p = v.transformed_position;
+  (p.x >= 0.0 && p.y >= 0.0).then(|| line_fragment(v, v, 0.0, p.x.floor() as i32, p.y.floor() as i32))
+}
+
+// Anti-aliased version of `line` (Xiaolin Wu): each step along the major axis emits
+// the two pixels straddling the segment, with `coverage` split by how close each one
+// is to it. The end pixels are further scaled by how much of them the segment spans
+pub fn line_aa(v1: &Vertex, v2: &Vertex, fragments: &mut Vec<Fragment>) {
+  let (a, b) = (v1.transformed_position, v2.transformed_position);
+
+  // Walk along the axis with the larger extent; `minor` is the other coordinate
+  let steep = (b.y - a.y).abs() > (b.x - a.x).abs();
+  let ((a_major, a_minor), (b_major, b_minor)) = if steep { ((a.y, a.x), (b.y, b.x)) } else { ((a.x, a.y), (b.x, b.y)) };
+  let span = b_major - a_major;
+  if span.abs() < f32::EPSILON {
+    if a.x >= 0.0 && a.y >= 0.0 {
+      fragments.push(line_fragment(v1, v2, 0.0, a.x.floor() as i32, a.y.floor() as i32));
+    }
+    return;
+  }
+  let gradient = (b_minor - a_minor) / span;
+  let (low, high) = (a_major.min(b_major), a_major.max(b_major));
+
+  for major in low.floor() as i32..=high.floor() as i32 {
+    // Sample at the pixel center, clamped to the segment for the end pixels
+    let center = (major as f32 + 0.5).clamp(low, high);
+    let t = (center - a_major) / span;
+    let minor = a_minor + gradient * (center - a_major);
+    let span_coverage = ((major + 1) as f32).min(high) - (major as f32).max(low);
+    let span_coverage = span_coverage.clamp(0.0, 1.0).max(if low.floor() == high.floor() { 1.0 } else { 0.0 });
+
+    // The two pixels whose centers bracket the segment
+    let below = (minor - 0.5).floor();
+    let fraction = minor - 0.5 - below;
+    for (offset, weight) in [(0, 1.0 - fraction), (1, fraction)] {
+      let minor_pixel = below as i32 + offset;
+      let (x, y) = if steep { (minor_pixel, major) } else { (major, minor_pixel) };
+      let coverage = weight * span_coverage;
+      // Negative coordinates would wrap when converted to pixel indices
+      if x >= 0 && y >= 0 && coverage > 0.0 {
+        let mut fragment = line_fragment(v1, v2, t, x, y);
+        fragment.coverage = coverage;
+        fragments.push(fragment);
+      }
+    }
+  }
+}
+
+// Fragment at (x, y), a fraction `t` of the way (in screen space) from v1 to v2
+fn line_fragment(v1: &Vertex, v2: &Vertex, t: f32, x: i32, y: i32) -> Fragment {
+  let (inv_w1, inv_w2) = (1.0 / v1.clip_w, 1.0 / v2.clip_w);
+  let inv_w = inv_w1 * (1.0 - t) + inv_w2 * t;
+  let (p1, p2) = ((1.0 - t) * inv_w1 / inv_w, t * inv_w2 / inv_w);
+
+  let normal = (v1.transformed_normal * p1 + v2.transformed_normal * p2).normalize();
+  let intensity = dot(&normal, &Vec3::new(0.0, 0.0, 1.0)).max(0.0);
+  let depth = v1.transformed_position.z * (1.0 - t) + v2.transformed_position.z * t;
+  let vertex_position = v1.position * p1 + v2.position * p2;
+
+  Fragment::new(
+    Vec2::new(x as f32, y as f32),
+    v1.color,
+    depth,
+    normal,
+    intensity,
+    vertex_position,
+    1.0 / inv_w,
+  )
+}
+
+// Twice the signed area of a screen-space triangle. The viewport flips Y, so
+// counter-clockwise (front-facing) triangles come out positive
+pub fn signed_area(a: &Vec3, b: &Vec3, c: &Vec3) -> f32 {
+    edge_function(a, b, c)
+}
+
+// Inclusive pixel bounds of the triangle clipped to the viewport, or None when it
+// lies completely outside
+fn calculate_bounding_box(v1: &Vec3, v2: &Vec3, v3: &Vec3, viewport: (usize, usize)) -> Option<(usize, usize, usize, usize)> {
+    let (width, height) = (viewport.0 as f32, viewport.1 as f32);
+    let min_x = v1.x.min(v2.x).min(v3.x).floor().max(0.0);
+    let min_y = v1.y.min(v2.y).min(v3.y).floor().max(0.0);
+    let max_x = v1.x.max(v2.x).max(v3.x).ceil().min(width - 1.0);
+    let max_y = v1.y.max(v2.y).max(v3.y).ceil().min(height - 1.0);
+
+    if min_x > max_x || min_y > max_y {
+        return None;
+    }
+    Some((min_x as usize, min_y as usize, max_x as usize, max_y as usize))
 }
 
 fn barycentric_coordinates(p: &Vec3, a: &Vec3, b: &Vec3, c: &Vec3, area: f32) -> (f32, f32, f32) {
@@ -79,3 +223,78 @@ fn edge_function(a: &Vec3, b: &Vec3, c: &Vec3) -> f32 {
 }
 
 
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use nalgebra_glm::{Mat4, Vec4, look_at, perspective};
+
+  const SIZE: f32 = 96.0;
+
+  // Camera above and in front of a 2x2 quad on the y = 0 plane, looking down at it
+  // at a steep angle so its far half is strongly foreshortened
+  fn view_projection() -> Mat4 {
+    let projection = perspective(1.0, std::f32::consts::FRAC_PI_3, 0.1, 100.0);
+    let view = look_at(&Vec3::new(0.0, 0.8, 2.2), &Vec3::new(0.0, 0.0, 0.0), &Vec3::new(0.0, 1.0, 0.0));
+    projection * view
+  }
+
+  fn projected_vertex(position: Vec3, view_projection: &Mat4) -> Vertex {
+    let clip = view_projection * Vec4::new(position.x, position.y, position.z, 1.0);
+    let mut vertex = Vertex::new(position, Vec3::new(0.0, 1.0, 0.0), Vec2::new(0.0, 0.0));
+    vertex.transformed_position = Vec3::new(
+      (clip.x / clip.w + 1.0) * SIZE / 2.0,
+      (1.0 - clip.y / clip.w) * SIZE / 2.0,
+      clip.z / clip.w,
+    );
+    vertex.clip_w = clip.w;
+    vertex
+  }
+
+  // Where the ray through a pixel center really hits the y = 0 plane
+  fn ray_hit(x: f32, y: f32, inverse: &Mat4) -> Vec3 {
+    let ndc = Vec2::new((x + 0.5) / SIZE * 2.0 - 1.0, 1.0 - (y + 0.5) / SIZE * 2.0);
+    let unproject = |z: f32| {
+      let point = inverse * Vec4::new(ndc.x, ndc.y, z, 1.0);
+      Vec3::new(point.x, point.y, point.z) / point.w
+    };
+    let (near, far) = (unproject(-1.0), unproject(1.0));
+    near + (far - near) * (near.y / (near.y - far.y))
+  }
+
+  // 4x4 checkerboard over the quad
+  fn checker(point: &Vec3) -> bool {
+    ((point.x * 2.0).floor() + (point.z * 2.0).floor()) as i32 % 2 == 0
+  }
+
+  #[test]
+  fn checkerboard_stays_square_under_oblique_view() {
+    let view_projection = view_projection();
+    let inverse = view_projection.try_inverse().unwrap();
+    let corners = [
+      Vec3::new(-1.0, 0.0, -1.0),
+      Vec3::new(1.0, 0.0, -1.0),
+      Vec3::new(1.0, 0.0, 1.0),
+      Vec3::new(-1.0, 0.0, 1.0),
+    ].map(|corner| projected_vertex(corner, &view_projection));
+
+    let mut fragments = Vec::new();
+    let viewport = (SIZE as usize, SIZE as usize);
+    triangle(&corners[0], &corners[1], &corners[2], viewport, &mut fragments);
+    triangle(&corners[0], &corners[2], &corners[3], viewport, &mut fragments);
+    assert!(fragments.len() > 1000);
+
+    // The interpolated position is the true surface point behind every pixel, so each
+    // checker square keeps its shape (affine interpolation is off by several hundredths)
+    for fragment in &fragments {
+      let expected = ray_hit(fragment.position.x, fragment.position.y, &inverse);
+      let error = (fragment.vertex_position - expected).magnitude();
+      assert!(error < 1e-3, "off by {} at {:?}", error, fragment.position);
+
+      let near_edge = [expected.x, expected.z].iter().any(|c| ((c * 2.0).round() - c * 2.0).abs() < 0.01);
+      if !near_edge {
+        assert_eq!(checker(&fragment.vertex_position), checker(&expected));
+      }
+    }
+  }
+}
@@ -1,4 +1,4 @@
-use nalgebra_glm::{Vec2, Vec3};
+use nalgebra_glm::{Vec2, Vec3, Vec4};
 use crate::color::Color;
 
 #[derive(Clone, Debug)]
@@ -9,6 +9,10 @@ pub struct Vertex {
   pub color: Color,
   pub transformed_position: Vec3,
   pub transformed_normal: Vec3,
+  // Clip-space position from the vertex shader, before perspective division
+  pub clip_position: Vec4,
+  // Clip-space w from the vertex shader, for perspective-correct interpolation
+  pub clip_w: f32,
 }
 
 impl Vertex {
@@ -20,6 +24,8 @@ impl Vertex {
       color: Color::black(),
       transformed_position: position,
       transformed_normal: normal,
+      clip_position: Vec4::new(0.0, 0.0, 0.0, 1.0),
+      clip_w: 1.0,
     }
   }
 
@@ -31,6 +37,22 @@ impl Vertex {
       color,
       transformed_position: Vec3::new(0.0, 0.0, 0.0),
       transformed_normal: Vec3::new(0.0, 0.0, 0.0),
+      clip_position: Vec4::new(0.0, 0.0, 0.0, 1.0),
+      clip_w: 1.0,
+    }
+  }
+
+  // Linear interpolation of every attribute, used to create vertices when clipping
+  pub fn lerp(&self, other: &Vertex, t: f32) -> Vertex {
+    Vertex {
+      position: self.position.lerp(&other.position, t),
+      normal: self.normal.lerp(&other.normal, t),
+      tex_coords: self.tex_coords.lerp(&other.tex_coords, t),
+      color: self.color.lerp(&other.color, t),
+      transformed_position: self.transformed_position.lerp(&other.transformed_position, t),
+      transformed_normal: self.transformed_normal.lerp(&other.transformed_normal, t),
+      clip_position: self.clip_position.lerp(&other.clip_position, t),
+      clip_w: self.clip_w + (other.clip_w - self.clip_w) * t,
     }
   }
 
@@ -49,6 +71,8 @@ impl Default for Vertex {
       color: Color::black(),
       transformed_position: Vec3::new(0.0, 0.0, 0.0),
       transformed_normal: Vec3::new(0.0, 1.0, 0.0),
+      clip_position: Vec4::new(0.0, 0.0, 0.0, 1.0),
+      clip_w: 1.0,
     }
   }
 }
@@ -0,0 +1,61 @@
+use nalgebra_glm::Vec3;
+
+// Par de portales: cruzar el anillo de entrada lleva la nave hasta la salida
+pub struct Wormhole {
+    pub entry: Vec3,
+    pub exit: Vec3,
+    pub radius: f32,
+}
+
+impl Wormhole {
+    pub fn new(entry: Vec3, exit: Vec3, radius: f32) -> Self {
+        Self { entry, exit, radius }
+    }
+
+    // Normal de los anillos: apunta de la entrada hacia la salida
+    pub fn normal(&self) -> Vec3 {
+        (self.exit - self.entry).normalize()
+    }
+
+    // Rotación que lleva el eje Y local del anillo a su normal (ver create_model_matrix)
+    pub fn ring_rotation(&self) -> Vec3 {
+        let normal = self.normal();
+        Vec3::new(normal.y.clamp(-1.0, 1.0).acos(), normal.x.atan2(normal.z), 0.0)
+    }
+
+    // El segmento `from` -> `to` atraviesa el plano del anillo de entrada dentro de su radio
+    pub fn crossed_by(&self, from: &Vec3, to: &Vec3) -> bool {
+        let normal = self.normal();
+        let from_side = (from - self.entry).dot(&normal);
+        let to_side = (to - self.entry).dot(&normal);
+
+        if from_side * to_side > 0.0 || from_side == to_side {
+            return false;
+        }
+
+        let t = from_side / (from_side - to_side);
+        let crossing = from + (to - from) * t;
+        (crossing - self.entry).magnitude() <= self.radius
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crossing_the_ring_vs_a_near_miss() {
+        // Anillo de radio 2 en el origen, con la salida sobre +z
+        let wormhole = Wormhole::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 100.0), 2.0);
+
+        // Atravesarlo dentro del radio, en cualquier sentido
+        assert!(wormhole.crossed_by(&Vec3::new(0.5, 1.0, -1.0), &Vec3::new(0.5, 1.0, 1.0)));
+        assert!(wormhole.crossed_by(&Vec3::new(1.0, 0.0, 1.0), &Vec3::new(1.0, 0.0, -1.0)));
+
+        // Cruzar el plano justo por fuera del anillo no cuenta
+        assert!(!wormhole.crossed_by(&Vec3::new(2.1, 0.0, -1.0), &Vec3::new(2.1, 0.0, 1.0)));
+        // Acercarse sin llegar al plano, o moverse sobre él, tampoco
+        assert!(!wormhole.crossed_by(&Vec3::new(0.0, 0.0, -1.0), &Vec3::new(0.0, 0.0, -0.1)));
+        assert!(!wormhole.crossed_by(&Vec3::new(0.0, 0.0, 0.0), &Vec3::new(1.0, 0.0, 0.0)));
+    }
+}